@@ -0,0 +1,329 @@
+use super::*;
+
+/// A 28-bit MIDI-CI Unique Identifier.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Muid(pub u32);
+
+impl Muid {
+  pub const BROADCAST: Muid = Muid(BROADCAST);
+
+  /// Generates a random MUID outside of the reserved range.
+  pub fn random() -> Self {
+    use std::hash::{BuildHasher, Hasher};
+    let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+    hasher.write_u128(
+      std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_nanos())
+    );
+    hasher.write_u32(std::process::id());
+    // 0x0FFFFF00 - 0x0FFFFFFF are reserved
+    Self(hasher.finish() as u32 % 0x0FFF_FF00)
+  }
+
+  pub fn is_broadcast(&self) -> bool { *self == Self::BROADCAST }
+
+  fn to_bytes(self) -> [u8; 4] {
+    [0, 7, 14, 21].map(|shift| (self.0 >> shift) as u8 & 0x7f)
+  }
+
+  fn from_bytes(bytes: &[u8]) -> Self {
+    Self(bytes.iter().take(4).enumerate().fold(0, |muid, (i, &b)| muid | u32::from(b & 0x7f) << (7 * i)))
+  }
+}
+
+/// The identity a MIDI-CI device announces during discovery.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct DeviceIdentity {
+  /// SysEx manufacturer ID. One byte IDs are sent as `[id, 0, 0]`.
+  pub manufacturer: [u8; 3],
+  pub family: u16,
+  pub model: u16,
+  pub version: [u8; 4],
+}
+
+/// A five byte Profile ID. Standard profiles start with `0x7E`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ProfileId(pub [u8; 5]);
+
+/// One chunk of a Property Exchange message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PeChunk {
+  pub request: u8,
+  /// JSON header, only sent in the first chunk.
+  pub header: Vec<u8>,
+  /// Total number of chunks, 0 if unknown.
+  pub chunks: u16,
+  /// This chunk's number, counting from 1.
+  pub chunk: u16,
+  pub data: Vec<u8>,
+}
+
+/// The body of a MIDI-CI message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CiMessage {
+  Discovery { identity: DeviceIdentity, categories: u8, max_sysex: u32, output_path: u8 },
+  DiscoveryReply { identity: DeviceIdentity, categories: u8, max_sysex: u32, output_path: u8, function_block: u8 },
+  InvalidateMuid { target: Muid },
+  Ack { original: u8, status: u8, status_data: u8, details: [u8; 5], text: Vec<u8> },
+  Nak { original: u8, status: u8, status_data: u8, details: [u8; 5], text: Vec<u8> },
+  ProfileInquiry,
+  ProfileReply { enabled: Vec<ProfileId>, disabled: Vec<ProfileId> },
+  ProfileOn { profile: ProfileId, channels: u16 },
+  ProfileOff { profile: ProfileId },
+  ProfileEnabled { profile: ProfileId, channels: u16 },
+  ProfileDisabled { profile: ProfileId, channels: u16 },
+  PeCapability { requests: u8 },
+  PeCapabilityReply { requests: u8 },
+  PeGet(PeChunk),
+  PeGetReply(PeChunk),
+  PeSet(PeChunk),
+  PeSetReply(PeChunk),
+  /// Any message this crate does not know, with its payload.
+  Other { sub_id: u8, payload: Vec<u8> },
+}
+
+/// A complete MIDI-CI SysEx message.
+/// ```
+/// use midi::ci::{CiMessage, CiPacket, Muid};
+/// let packet = CiPacket{
+///   device: 0x7F,
+///   source: Muid(0x0123_4567),
+///   destination: Muid::BROADCAST,
+///   message: CiMessage::ProfileInquiry,
+/// };
+/// let sysex = packet.to_sysex();
+/// assert_eq!(&sysex[..5], &[0xF0, 0x7E, 0x7F, 0x0D, 0x20]);
+/// assert_eq!(CiPacket::parse(&sysex), Some(packet));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CiPacket {
+  /// Channel 0 - 15, [`GROUP`] or [`FUNCTION_BLOCK`].
+  pub device: u8,
+  pub source: Muid,
+  pub destination: Muid,
+  pub message: CiMessage,
+}
+
+impl CiMessage {
+  pub fn sub_id(&self) -> u8 {
+    match self {
+      Self::Discovery{ .. } => DISCOVERY,
+      Self::DiscoveryReply{ .. } => DISCOVERY_REPLY,
+      Self::InvalidateMuid{ .. } => INVALIDATE_MUID,
+      Self::Ack{ .. } => ACK,
+      Self::Nak{ .. } => NAK,
+      Self::ProfileInquiry => PROFILE_INQUIRY,
+      Self::ProfileReply{ .. } => PROFILE_REPLY,
+      Self::ProfileOn{ .. } => PROFILE_ON,
+      Self::ProfileOff{ .. } => PROFILE_OFF,
+      Self::ProfileEnabled{ .. } => PROFILE_ENABLED,
+      Self::ProfileDisabled{ .. } => PROFILE_DISABLED,
+      Self::PeCapability{ .. } => PE_CAPABILITY,
+      Self::PeCapabilityReply{ .. } => PE_CAPABILITY_REPLY,
+      Self::PeGet(_) => PE_GET,
+      Self::PeGetReply(_) => PE_GET_REPLY,
+      Self::PeSet(_) => PE_SET,
+      Self::PeSetReply(_) => PE_SET_REPLY,
+      Self::Other{ sub_id, .. } => *sub_id,
+    }
+  }
+
+  fn payload(&self) -> Vec<u8> {
+    let mut out = vec![];
+    match self {
+      Self::Discovery{ identity, categories, max_sysex, output_path } => {
+        put_identity(&mut out, identity, *categories, *max_sysex);
+        out.push(*output_path);
+      },
+      Self::DiscoveryReply{ identity, categories, max_sysex, output_path, function_block } => {
+        put_identity(&mut out, identity, *categories, *max_sysex);
+        out.extend([*output_path, *function_block]);
+      },
+      Self::InvalidateMuid{ target } => out.extend(target.to_bytes()),
+      Self::Ack{ original, status, status_data, details, text }
+      | Self::Nak{ original, status, status_data, details, text } => {
+        out.extend([*original, *status, *status_data]);
+        out.extend(details);
+        put_u14(&mut out, text.len() as u16);
+        out.extend(text);
+      },
+      Self::ProfileInquiry => (),
+      Self::ProfileReply{ enabled, disabled } => {
+        for list in [enabled, disabled] {
+          put_u14(&mut out, list.len() as u16);
+          list.iter().for_each(|p| out.extend(p.0));
+        }
+      },
+      Self::ProfileOn{ profile, channels }
+      | Self::ProfileEnabled{ profile, channels }
+      | Self::ProfileDisabled{ profile, channels } => {
+        out.extend(profile.0);
+        put_u14(&mut out, *channels);
+      },
+      Self::ProfileOff{ profile } => {
+        out.extend(profile.0);
+        put_u14(&mut out, 0);
+      },
+      Self::PeCapability{ requests } | Self::PeCapabilityReply{ requests } => {
+        // Property Exchange 1.0
+        out.extend([*requests, 0, 0]);
+      },
+      Self::PeGet(c) | Self::PeGetReply(c) | Self::PeSet(c) | Self::PeSetReply(c) => {
+        out.push(c.request);
+        put_u14(&mut out, c.header.len() as u16);
+        out.extend(&c.header);
+        put_u14(&mut out, c.chunks);
+        put_u14(&mut out, c.chunk);
+        put_u14(&mut out, c.data.len() as u16);
+        out.extend(&c.data);
+      },
+      Self::Other{ payload, .. } => out.extend(payload),
+    }
+    out
+  }
+
+  fn parse(sub_id: u8, p: &[u8]) -> Option<Self> {
+    let mut r = Reader(p);
+    let message = match sub_id {
+      DISCOVERY => {
+        let (identity, categories, max_sysex) = r.identity()?;
+        // absent before MIDI-CI 1.2
+        let output_path = r.byte().unwrap_or(0);
+        Self::Discovery{ identity, categories, max_sysex, output_path }
+      },
+      DISCOVERY_REPLY => {
+        let (identity, categories, max_sysex) = r.identity()?;
+        let output_path = r.byte().unwrap_or(0);
+        let function_block = r.byte().unwrap_or(FUNCTION_BLOCK);
+        Self::DiscoveryReply{ identity, categories, max_sysex, output_path, function_block }
+      },
+      INVALIDATE_MUID => Self::InvalidateMuid{ target: Muid::from_bytes(r.take(4)?) },
+      ACK | NAK => {
+        // MIDI-CI 1.1 NAKs carry no payload
+        let original = r.byte().unwrap_or(0);
+        let status = r.byte().unwrap_or(0);
+        let status_data = r.byte().unwrap_or(0);
+        let details = r.take(5).map_or([0; 5], |d| d.try_into().unwrap_or([0; 5]));
+        let len = r.u14().unwrap_or(0);
+        let text = r.take(len.into()).unwrap_or_default().to_vec();
+        if sub_id == ACK {
+          Self::Ack{ original, status, status_data, details, text }
+        } else {
+          Self::Nak{ original, status, status_data, details, text }
+        }
+      },
+      PROFILE_INQUIRY => Self::ProfileInquiry,
+      PROFILE_REPLY => {
+        let enabled = r.profiles()?;
+        let disabled = r.profiles()?;
+        Self::ProfileReply{ enabled, disabled }
+      },
+      PROFILE_ON => Self::ProfileOn{ profile: r.profile()?, channels: r.u14().unwrap_or(0) },
+      PROFILE_OFF => Self::ProfileOff{ profile: r.profile()? },
+      PROFILE_ENABLED => Self::ProfileEnabled{ profile: r.profile()?, channels: r.u14().unwrap_or(0) },
+      PROFILE_DISABLED => Self::ProfileDisabled{ profile: r.profile()?, channels: r.u14().unwrap_or(0) },
+      PE_CAPABILITY => Self::PeCapability{ requests: r.byte()? },
+      PE_CAPABILITY_REPLY => Self::PeCapabilityReply{ requests: r.byte()? },
+      PE_GET | PE_GET_REPLY | PE_SET | PE_SET_REPLY => {
+        let request = r.byte()?;
+        let len = r.u14()?;
+        let header = r.take(len.into())?.to_vec();
+        let chunks = r.u14()?;
+        let chunk = r.u14()?;
+        let len = r.u14()?;
+        let data = r.take(len.into())?.to_vec();
+        let c = PeChunk{ request, header, chunks, chunk, data };
+        match sub_id {
+          PE_GET => Self::PeGet(c),
+          PE_GET_REPLY => Self::PeGetReply(c),
+          PE_SET => Self::PeSet(c),
+          _ => Self::PeSetReply(c),
+        }
+      },
+      sub_id => Self::Other{ sub_id, payload: p.to_vec() },
+    };
+    Some(message)
+  }
+}
+
+impl CiPacket {
+  /// Returns the message framed as SysEx, `0xF0` to `0xF7`.
+  pub fn to_sysex(&self) -> Vec<u8> {
+    let mut out = vec![SYSEX_BEGIN, NON_REALTIME, self.device, SUB_ID, self.message.sub_id(), VERSION];
+    out.extend(self.source.to_bytes());
+    out.extend(self.destination.to_bytes());
+    out.extend(self.message.payload());
+    out.push(SYSEX_END);
+    out
+  }
+
+  /// Parses a MIDI-CI SysEx message. Returns `None` for any other SysEx.
+  pub fn parse(sysex: &[u8]) -> Option<Self> {
+    let body = sysex.strip_prefix(&[SYSEX_BEGIN])?;
+    let body = body.strip_suffix(&[SYSEX_END]).unwrap_or(body);
+    match body {
+      [NON_REALTIME, device, SUB_ID, sub_id, _version, rest @ ..] if rest.len() >= 8 => {
+        Some(Self{
+          device: *device,
+          source: Muid::from_bytes(&rest[..4]),
+          destination: Muid::from_bytes(&rest[4..8]),
+          message: CiMessage::parse(*sub_id, &rest[8..])?,
+        })
+      },
+      _ => None
+    }
+  }
+}
+
+fn put_u14(out: &mut Vec<u8>, v: u16) {
+  out.extend([(v & 0x7f) as u8, (v >> 7) as u8 & 0x7f]);
+}
+
+fn put_identity(out: &mut Vec<u8>, identity: &DeviceIdentity, categories: u8, max_sysex: u32) {
+  out.extend(identity.manufacturer);
+  put_u14(out, identity.family);
+  put_u14(out, identity.model);
+  out.extend(identity.version);
+  out.push(categories);
+  out.extend(Muid(max_sysex).to_bytes());
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+  fn take(&mut self, n: usize) -> Option<&'a [u8]> {
+    if self.0.len() < n { return None }
+    let (head, tail) = self.0.split_at(n);
+    self.0 = tail;
+    Some(head)
+  }
+
+  fn byte(&mut self) -> Option<u8> { self.take(1).map(|b| b[0]) }
+
+  fn u14(&mut self) -> Option<u16> {
+    self.take(2).map(|b| u16::from(b[0] & 0x7f) | u16::from(b[1] & 0x7f) << 7)
+  }
+
+  fn profile(&mut self) -> Option<ProfileId> {
+    self.take(5).map(|b| ProfileId([b[0], b[1], b[2], b[3], b[4]]))
+  }
+
+  fn profiles(&mut self) -> Option<Vec<ProfileId>> {
+    let n = self.u14()?;
+    (0..n).map(|_| self.profile()).collect()
+  }
+
+  fn identity(&mut self) -> Option<(DeviceIdentity, u8, u32)> {
+    let manufacturer = self.take(3)?;
+    let identity = DeviceIdentity{
+      manufacturer: [manufacturer[0], manufacturer[1], manufacturer[2]],
+      family: self.u14()?,
+      model: self.u14()?,
+      version: self.take(4)?.try_into().ok()?,
+    };
+    let categories = self.byte()?;
+    let max_sysex = Muid::from_bytes(self.take(4)?).0;
+    Some((identity, categories, max_sysex))
+  }
+}
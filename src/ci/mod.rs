@@ -0,0 +1,600 @@
+pub mod message;
+
+pub use message::{CiMessage, CiPacket, DeviceIdentity, Muid, PeChunk, ProfileId};
+
+use std::collections::HashMap;
+
+use crossbeam_channel::{unbounded, Receiver, Sender};
+
+use crate::{
+  connection::{ConnectionBuilder, Input, Output},
+  consts::{
+    ci::*,
+    message::{SYSEX_BEGIN, SYSEX_END},
+    sysex::NON_REALTIME,
+  },
+  Arc,
+  Mutex,
+};
+
+/// Largest SysEx message assumed for devices that have not said otherwise.
+pub const DEFAULT_MAX_SYSEX: u32 = 512;
+// Bytes of a Property Exchange message that are neither header nor data:
+// F0, 7E, device, 0D, sub-ID, version, two MUIDs, request ID, header
+// length, chunk count, chunk number, data length and F7
+const PE_OVERHEAD: usize = 24;
+
+// Header and data of a Property Exchange message collected so far
+type Assembly = (Vec<u8>, Vec<u8>);
+
+/// A profile and its state on one channel, group or function block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Profile {
+  /// Channel 0 - 15, [`GROUP`] or [`FUNCTION_BLOCK`].
+  pub device: u8,
+  pub id: ProfileId,
+  pub enabled: bool,
+}
+
+/// A MIDI-CI device found through discovery.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteDevice {
+  pub muid: Muid,
+  pub identity: DeviceIdentity,
+  /// Capability Inquiry Category bits, see [`CATEGORY_PROFILES`].
+  pub categories: u8,
+  pub max_sysex: u32,
+  /// Profiles the device has reported, from inquiries and enable/disable reports.
+  pub profiles: Vec<Profile>,
+  /// Number of simultaneous Property Exchange requests, once known.
+  pub pe_requests: Option<u8>,
+}
+
+/// Something the remote side did, reported by [`Endpoint::handle`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CiEvent {
+  Discovered(RemoteDevice),
+  /// A device announced that its MUID is no longer in use.
+  Invalidated(Muid),
+  /// Our own MUID collided with another device and was replaced.
+  MuidChanged { old: Muid, new: Muid },
+  Ack { from: Muid, original: u8, status: u8 },
+  Nak { from: Muid, original: u8, status: u8, text: String },
+  /// A device answered a profile inquiry, see [`RemoteDevice::profiles`].
+  Profiles { from: Muid, device: u8 },
+  /// A device enabled or disabled one of its own profiles.
+  ProfileChanged { from: Muid, profile: Profile },
+  /// A device enabled or disabled one of our profiles.
+  ProfileSet { from: Muid, profile: Profile },
+  PeCapability { from: Muid, requests: u8 },
+  GetReply { from: Muid, request: u8, header: String, data: Vec<u8> },
+  SetReply { from: Muid, request: u8, header: String },
+  /// A device wrote one of our resources.
+  ResourceSet { from: Muid, resource: String, data: Vec<u8> },
+}
+
+/// What [`Endpoint::handle`] made of a message.
+#[derive(Debug, Default)]
+pub struct Outcome {
+  /// Messages to send back, in order.
+  pub replies: Vec<Vec<u8>>,
+  pub events: Vec<CiEvent>,
+}
+
+/// A MIDI-CI endpoint: answers discovery, profile and property exchange
+/// requests on behalf of this application, and builds requests for others.
+///
+/// The endpoint does no I/O of its own. Incoming SysEx goes to
+/// [`Endpoint::handle`], and every returned message has to be sent;
+/// [`Endpoint::attach`] does both over an [`Input`]/[`Output`] pair.
+/// ```
+/// use midi::ci::{CiEvent, DeviceIdentity, Endpoint};
+/// let mut host = Endpoint::new(DeviceIdentity::default());
+/// let mut synth = Endpoint::new(DeviceIdentity{ manufacturer: [0x41, 0, 0], ..Default::default() });
+/// synth.expose("DeviceInfo", br#"{"model":"S-1"}"#.to_vec()).unwrap();
+///
+/// // discovery: the synth replies, and the host learns about it
+/// let reply = synth.handle(&host.discovery()).replies.remove(0);
+/// let found = host.handle(&reply).events.remove(0);
+/// assert!(matches!(found, CiEvent::Discovered(ref d) if d.muid == synth.muid()));
+///
+/// // property exchange
+/// let (request, get) = host.get(synth.muid(), "DeviceInfo").unwrap();
+/// let replies = synth.handle(&get).replies;
+/// let events: Vec<_> = replies.iter().flat_map(|r| host.handle(r).events).collect();
+/// assert_eq!(events, vec![CiEvent::GetReply{
+///   from: synth.muid(),
+///   request,
+///   header: r#"{"status":200}"#.to_owned(),
+///   data: br#"{"model":"S-1"}"#.to_vec(),
+/// }]);
+/// ```
+pub struct Endpoint {
+  muid: Muid,
+  identity: DeviceIdentity,
+  max_sysex: u32,
+  profiles: Vec<Profile>,
+  resources: HashMap<String, Vec<u8>>,
+  remotes: HashMap<Muid, RemoteDevice>,
+  // Property Exchange messages still missing chunks, by sender, kind and request
+  incoming: HashMap<(Muid, u8, u8), Assembly>,
+  next_request: u8,
+}
+
+impl Endpoint {
+  pub fn new(identity: DeviceIdentity) -> Self {
+    Self{
+      muid: Muid::random(),
+      identity,
+      max_sysex: DEFAULT_MAX_SYSEX,
+      profiles: vec![],
+      resources: HashMap::new(),
+      remotes: HashMap::new(),
+      incoming: HashMap::new(),
+      next_request: 0,
+    }
+  }
+
+  /// Sets the largest SysEx message this endpoint accepts, announced during discovery.
+  pub fn max_sysex(mut self, bytes: u32) -> Self {
+    self.max_sysex = bytes.max(128);
+    self
+  }
+
+  pub fn muid(&self) -> Muid { self.muid }
+
+  pub fn identity(&self) -> &DeviceIdentity { &self.identity }
+
+  /// Offers `id` on `device`, which is a channel 0 - 15, [`GROUP`] or [`FUNCTION_BLOCK`].
+  pub fn add_profile(&mut self, device: u8, id: ProfileId, enabled: bool) {
+    match self.profiles.iter_mut().find(|p| p.device == device && p.id == id) {
+      Some(p) => p.enabled = enabled,
+      None => self.profiles.push(Profile{ device, id, enabled }),
+    }
+  }
+
+  pub fn profiles(&self) -> &[Profile] { &self.profiles }
+
+  /// Makes `resource` available to Property Exchange GET and SET requests.
+  /// Both the name and `data` go out in SysEx, so they have to be 7 bit.
+  pub fn expose(&mut self, resource: &str, data: Vec<u8>) -> Result<(), String> {
+    seven_bit(resource.as_bytes(), "resource name")?;
+    seven_bit(&data, "resource data")?;
+    self.resources.insert(resource.to_owned(), data);
+    Ok(())
+  }
+
+  pub fn resource(&self, resource: &str) -> Option<&[u8]> {
+    self.resources.get(resource).map(Vec::as_slice)
+  }
+
+  pub fn remote(&self, muid: Muid) -> Option<&RemoteDevice> { self.remotes.get(&muid) }
+
+  pub fn remotes(&self) -> impl Iterator<Item = &RemoteDevice> { self.remotes.values() }
+
+  /// Returns a broadcast Discovery message.
+  pub fn discovery(&self) -> Vec<u8> {
+    self.packet(FUNCTION_BLOCK, Muid::BROADCAST, CiMessage::Discovery{
+      identity: self.identity,
+      categories: self.categories(),
+      max_sysex: self.max_sysex,
+      output_path: 0,
+    })
+  }
+
+  /// Gives up the current MUID for a new one. Returns the Invalidate MUID
+  /// message for the old one; a new [`Endpoint::discovery`] should follow.
+  pub fn invalidate(&mut self) -> Vec<u8> {
+    let old = self.muid;
+    self.muid = Muid::random();
+    self.remotes.clear();
+    self.incoming.clear();
+    CiPacket{
+      device: FUNCTION_BLOCK,
+      source: old,
+      destination: Muid::BROADCAST,
+      message: CiMessage::InvalidateMuid{ target: old },
+    }.to_sysex()
+  }
+
+  pub fn profile_inquiry(&self, to: Muid, device: u8) -> Vec<u8> {
+    self.packet(device, to, CiMessage::ProfileInquiry)
+  }
+
+  /// Asks `to` to enable or disable `profile` on `device`.
+  pub fn set_profile(&self, to: Muid, device: u8, profile: ProfileId, enabled: bool) -> Vec<u8> {
+    let message = if enabled {
+      CiMessage::ProfileOn{ profile, channels: 1 }
+    } else {
+      CiMessage::ProfileOff{ profile }
+    };
+    self.packet(device, to, message)
+  }
+
+  pub fn pe_capability(&self, to: Muid) -> Vec<u8> {
+    self.packet(FUNCTION_BLOCK, to, CiMessage::PeCapability{ requests: 1 })
+  }
+
+  /// Requests `resource` from `to`. Returns the request ID, which the
+  /// matching [`CiEvent::GetReply`] carries, and the message to send.
+  pub fn get(&mut self, to: Muid, resource: &str) -> Result<(u8, Vec<u8>), String> {
+    seven_bit(resource.as_bytes(), "resource name")?;
+    let request = self.request_id();
+    let chunk = PeChunk{ request, header: resource_header(resource), chunks: 1, chunk: 1, data: vec![] };
+    Ok((request, self.packet(FUNCTION_BLOCK, to, CiMessage::PeGet(chunk))))
+  }
+
+  /// Writes `data` to `resource` on `to`, split into as many messages
+  /// as the receiver's maximum SysEx size requires. Fails if the name
+  /// or `data` is not 7 bit, which SysEx can not carry.
+  /// ```
+  /// use midi::ci::{DeviceIdentity, Endpoint};
+  /// let mut host = Endpoint::new(DeviceIdentity::default());
+  /// let mut synth = Endpoint::new(DeviceIdentity::default()).max_sysex(128);
+  /// let reply = synth.handle(&host.discovery()).replies.remove(0);
+  /// host.handle(&reply);
+  ///
+  /// let (_, messages) = host.set(synth.muid(), "Patch", &[0x11; 1000]).unwrap();
+  /// assert!(messages.len() > 1);
+  /// assert!(messages.iter().all(|m| m.len() <= 128));
+  /// assert_eq!(messages[0].len(), 128);
+  /// assert!(host.set(synth.muid(), "Patch", &[0x80]).is_err());
+  /// ```
+  pub fn set(&mut self, to: Muid, resource: &str, data: &[u8]) -> Result<(u8, Vec<Vec<u8>>), String> {
+    seven_bit(resource.as_bytes(), "resource name")?;
+    seven_bit(data, "resource data")?;
+    let request = self.request_id();
+    let messages = self.chunked(to, request, resource_header(resource), data, CiMessage::PeSet);
+    Ok((request, messages))
+  }
+
+  /// Handles one incoming SysEx message. Anything that is not MIDI-CI,
+  /// or not addressed to this endpoint, is ignored.
+  ///
+  /// Discovery from another device using our MUID makes the endpoint
+  /// pick a new one. Our own discovery, echoed back by a loop in the
+  /// MIDI setup, is told apart by carrying our identity, and ignored.
+  /// ```
+  /// use midi::ci::{CiEvent, CiMessage, CiPacket, DeviceIdentity, Endpoint, Muid};
+  /// let mut host = Endpoint::new(DeviceIdentity::default());
+  /// let echo = host.handle(&host.discovery());
+  /// assert!(echo.replies.is_empty() && echo.events.is_empty());
+  ///
+  /// let old = host.muid();
+  /// let other = CiPacket{
+  ///   device: 0x7F,
+  ///   source: old,
+  ///   destination: Muid::BROADCAST,
+  ///   message: CiMessage::Discovery{
+  ///     identity: DeviceIdentity{ manufacturer: [0x41, 0, 0], ..Default::default() },
+  ///     categories: 0,
+  ///     max_sysex: 512,
+  ///     output_path: 0,
+  ///   },
+  /// };
+  /// let collision = host.handle(&other.to_sysex());
+  /// assert_eq!(collision.events, vec![CiEvent::MuidChanged{ old, new: host.muid() }]);
+  /// ```
+  pub fn handle(&mut self, sysex: &[u8]) -> Outcome {
+    let mut out = Outcome::default();
+    let Some(packet) = CiPacket::parse(sysex) else { return out };
+    let from = packet.source;
+
+    if from == self.muid {
+      // Someone else picked our MUID, unless this is our own message echoed back.
+      let collision = match packet.message {
+        CiMessage::Discovery{ identity, categories, max_sysex, .. }
+        | CiMessage::DiscoveryReply{ identity, categories, max_sysex, .. } => {
+          (identity, categories, max_sysex) != (self.identity, self.categories(), self.max_sysex)
+        },
+        _ => false,
+      };
+      if collision {
+        out.replies.push(self.invalidate());
+        out.events.push(CiEvent::MuidChanged{ old: from, new: self.muid });
+        out.replies.push(self.discovery());
+      }
+      return out
+    }
+    if packet.destination != self.muid && !packet.destination.is_broadcast() {
+      return out
+    }
+
+    match packet.message {
+      CiMessage::Discovery{ identity, categories, max_sysex, output_path } => {
+        out.events.push(self.discovered(from, identity, categories, max_sysex));
+        out.replies.push(self.packet(FUNCTION_BLOCK, from, CiMessage::DiscoveryReply{
+          identity: self.identity,
+          categories: self.categories(),
+          max_sysex: self.max_sysex,
+          output_path,
+          function_block: FUNCTION_BLOCK,
+        }));
+      },
+      CiMessage::DiscoveryReply{ identity, categories, max_sysex, .. } => {
+        out.events.push(self.discovered(from, identity, categories, max_sysex));
+      },
+      CiMessage::InvalidateMuid{ target } => {
+        self.remotes.remove(&target);
+        self.incoming.retain(|k, _| k.0 != target);
+        out.events.push(CiEvent::Invalidated(target));
+      },
+      CiMessage::Ack{ original, status, .. } => out.events.push(CiEvent::Ack{ from, original, status }),
+      CiMessage::Nak{ original, status, text, .. } => {
+        let text = String::from_utf8_lossy(&text).into_owned();
+        out.events.push(CiEvent::Nak{ from, original, status, text });
+      },
+      CiMessage::ProfileInquiry => {
+        let (enabled, disabled) = self.profiles
+          .iter()
+          .filter(|p| p.device == packet.device)
+          .partition::<Vec<&Profile>, _>(|p| p.enabled);
+        out.replies.push(self.packet(packet.device, from, CiMessage::ProfileReply{
+          enabled: enabled.iter().map(|p| p.id).collect(),
+          disabled: disabled.iter().map(|p| p.id).collect(),
+        }));
+      },
+      CiMessage::ProfileReply{ enabled, disabled } => {
+        if let Some(remote) = self.remotes.get_mut(&from) {
+          remote.profiles.retain(|p| p.device != packet.device);
+          let device = packet.device;
+          remote.profiles.extend(enabled.into_iter().map(|id| Profile{ device, id, enabled: true }));
+          remote.profiles.extend(disabled.into_iter().map(|id| Profile{ device, id, enabled: false }));
+        }
+        out.events.push(CiEvent::Profiles{ from, device: packet.device });
+      },
+      CiMessage::ProfileOn{ profile, .. } | CiMessage::ProfileOff{ profile } => {
+        let enabled = matches!(packet.message, CiMessage::ProfileOn{ .. });
+        let Some(local) = self.profiles.iter_mut().find(|p| p.device == packet.device && p.id == profile) else {
+          out.replies.push(self.nak(packet.device, from, packet.message.sub_id()));
+          return out
+        };
+        local.enabled = enabled;
+        let local = *local;
+        let report = if enabled {
+          CiMessage::ProfileEnabled{ profile, channels: 1 }
+        } else {
+          CiMessage::ProfileDisabled{ profile, channels: 1 }
+        };
+        out.replies.push(self.packet(packet.device, Muid::BROADCAST, report));
+        out.events.push(CiEvent::ProfileSet{ from, profile: local });
+      },
+      CiMessage::ProfileEnabled{ profile, .. } | CiMessage::ProfileDisabled{ profile, .. } => {
+        let profile = Profile{
+          device: packet.device,
+          id: profile,
+          enabled: matches!(packet.message, CiMessage::ProfileEnabled{ .. }),
+        };
+        if let Some(remote) = self.remotes.get_mut(&from) {
+          match remote.profiles.iter_mut().find(|p| p.device == profile.device && p.id == profile.id) {
+            Some(p) => p.enabled = profile.enabled,
+            None => remote.profiles.push(profile),
+          }
+        }
+        out.events.push(CiEvent::ProfileChanged{ from, profile });
+      },
+      CiMessage::PeCapability{ requests } => {
+        if let Some(remote) = self.remotes.get_mut(&from) { remote.pe_requests = Some(requests) }
+        out.replies.push(self.packet(FUNCTION_BLOCK, from, CiMessage::PeCapabilityReply{ requests: 1 }));
+      },
+      CiMessage::PeCapabilityReply{ requests } => {
+        if let Some(remote) = self.remotes.get_mut(&from) { remote.pe_requests = Some(requests) }
+        out.events.push(CiEvent::PeCapability{ from, requests });
+      },
+      CiMessage::PeGet(chunk) => {
+        let Some((header, _)) = self.assemble(from, PE_GET, chunk.clone()) else { return out };
+        let (status, data) = match json_field(&header, "resource").and_then(|r| self.resources.get(&r)) {
+          Some(data) => (200, data.clone()),
+          None => (404, vec![]),
+        };
+        out.replies = self.chunked(from, chunk.request, status_header(status), &data, CiMessage::PeGetReply);
+      },
+      CiMessage::PeSet(chunk) => {
+        let request = chunk.request;
+        let Some((header, data)) = self.assemble(from, PE_SET, chunk) else { return out };
+        let resource = json_field(&header, "resource").filter(|r| self.resources.contains_key(r));
+        let status = match resource {
+          Some(resource) => {
+            self.resources.insert(resource.clone(), data.clone());
+            out.events.push(CiEvent::ResourceSet{ from, resource, data });
+            200
+          },
+          None => 404,
+        };
+        let reply = PeChunk{ request, header: status_header(status), chunks: 1, chunk: 1, data: vec![] };
+        out.replies.push(self.packet(FUNCTION_BLOCK, from, CiMessage::PeSetReply(reply)));
+      },
+      CiMessage::PeGetReply(chunk) => {
+        let request = chunk.request;
+        if let Some((header, data)) = self.assemble(from, PE_GET_REPLY, chunk) {
+          let header = String::from_utf8_lossy(&header).into_owned();
+          out.events.push(CiEvent::GetReply{ from, request, header, data });
+        }
+      },
+      CiMessage::PeSetReply(chunk) => {
+        let request = chunk.request;
+        if let Some((header, _)) = self.assemble(from, PE_SET_REPLY, chunk) {
+          let header = String::from_utf8_lossy(&header).into_owned();
+          out.events.push(CiEvent::SetReply{ from, request, header });
+        }
+      },
+      CiMessage::Other{ sub_id, .. } if packet.destination == self.muid => {
+        out.replies.push(self.nak(packet.device, from, sub_id));
+      },
+      CiMessage::Other{ .. } => (),
+    }
+    out
+  }
+
+  /// Runs the endpoint over a device pair: SysEx from `input` is handled,
+  /// replies go out on `output`, and events arrive on the returned channel.
+  /// The endpoint itself is shared, to build requests with.
+  /// ```no_run
+  /// use midi::{ci::{DeviceIdentity, Endpoint, send_all}, connection::{ConnectionBuilder, Output}};
+  /// let output = Output::new("IAC Driver Bus 1", |_| {}).unwrap();
+  /// let endpoint = Endpoint::new(DeviceIdentity::default());
+  /// let (_input, endpoint, events) = endpoint
+  ///   .attach(ConnectionBuilder::new("IAC Driver Bus 1"), output.clone())
+  ///   .unwrap();
+  /// send_all(&output, &[endpoint.lock().unwrap().discovery()]).unwrap();
+  /// while let Ok(event) = events.recv() {
+  ///   println!("{event:?}");
+  /// }
+  /// ```
+  pub fn attach(
+    self,
+    input: ConnectionBuilder,
+    output: Arc<Mutex<Output>>
+  ) -> Result<(CiInput, SharedEndpoint, Receiver<CiEvent>), String> {
+    let endpoint = Arc::new(Mutex::new(self));
+    let (events, rx) = unbounded();
+    let link = CiLink{ endpoint: endpoint.clone(), output, events };
+    let input = input.input(link, respond as CiCallback)?;
+    Ok((input, endpoint, rx))
+  }
+
+  fn categories(&self) -> u8 {
+    let profiles = if self.profiles.is_empty() { 0 } else { CATEGORY_PROFILES };
+    profiles | CATEGORY_PROPERTY_EXCHANGE
+  }
+
+  fn packet(&self, device: u8, destination: Muid, message: CiMessage) -> Vec<u8> {
+    CiPacket{ device, source: self.muid, destination, message }.to_sysex()
+  }
+
+  fn nak(&self, device: u8, to: Muid, original: u8) -> Vec<u8> {
+    // status 0x01: message not supported
+    self.packet(device, to, CiMessage::Nak{ original, status: 0x01, status_data: 0, details: [0; 5], text: vec![] })
+  }
+
+  fn request_id(&mut self) -> u8 {
+    let id = self.next_request;
+    self.next_request = (id + 1) & 0x7f;
+    id
+  }
+
+  fn discovered(&mut self, muid: Muid, identity: DeviceIdentity, categories: u8, max_sysex: u32) -> CiEvent {
+    let remote = RemoteDevice{ muid, identity, categories, max_sysex, profiles: vec![], pe_requests: None };
+    self.remotes.insert(muid, remote.clone());
+    CiEvent::Discovered(remote)
+  }
+
+  /// Splits `data` into Property Exchange chunks that fit the receiver.
+  /// Header and data are 7 bit, checked by [`Endpoint::expose`] and
+  /// [`Endpoint::set`], or taken from SysEx that came in.
+  fn chunked(
+    &self,
+    to: Muid,
+    request: u8,
+    header: Vec<u8>,
+    data: &[u8],
+    kind: fn(PeChunk) -> CiMessage
+  ) -> Vec<Vec<u8>> {
+    let max_sysex = self.remotes.get(&to).map_or(DEFAULT_MAX_SYSEX, |r| r.max_sysex) as usize;
+    let size = max_sysex.saturating_sub(PE_OVERHEAD + header.len()).max(1);
+    let pieces: Vec<&[u8]> = if data.is_empty() { vec![&[]] } else { data.chunks(size).collect() };
+    let chunks = pieces.len() as u16;
+    pieces
+      .into_iter()
+      .enumerate()
+      .map(|(i, piece)| {
+        let header = if i == 0 { header.clone() } else { vec![] };
+        let chunk = PeChunk{ request, header, chunks, chunk: i as u16 + 1, data: piece.to_vec() };
+        self.packet(FUNCTION_BLOCK, to, kind(chunk))
+      })
+      .collect()
+  }
+
+  /// Collects chunks until the last one arrives, then returns header and data.
+  fn assemble(&mut self, from: Muid, kind: u8, chunk: PeChunk) -> Option<(Vec<u8>, Vec<u8>)> {
+    let key = (from, kind, chunk.request);
+    let entry = self.incoming.entry(key).or_default();
+    if chunk.chunk <= 1 {
+      *entry = (chunk.header, vec![]);
+    }
+    entry.1.extend(chunk.data);
+    if chunk.chunks != 0 && chunk.chunk < chunk.chunks { return None }
+    self.incoming.remove(&key)
+  }
+}
+
+pub type SharedEndpoint = Arc<Mutex<Endpoint>>;
+
+/// The user data of a [`CiInput`].
+pub struct CiLink {
+  endpoint: SharedEndpoint,
+  output: Arc<Mutex<Output>>,
+  events: Sender<CiEvent>,
+}
+
+pub type CiCallback = fn(u64, &[u8], &mut CiLink);
+
+/// An [`Input`] feeding a MIDI-CI [`Endpoint`], see [`Endpoint::attach`].
+pub type CiInput = Input<CiLink, CiCallback>;
+
+fn respond(_timestamp: u64, message: &[u8], link: &mut CiLink) {
+  if message.first() != Some(&SYSEX_BEGIN) { return }
+  let outcome = match link.endpoint.lock() {
+    Ok(mut endpoint) => endpoint.handle(message),
+    Err(_) => return
+  };
+  // nobody to tell on the input's thread, the remote side will ask again
+  let _ = send_all(&link.output, &outcome.replies);
+  for event in outcome.events {
+    let _ = link.events.send(event);
+  }
+}
+
+/// Sends every message in `messages`, waiting for the port if it is busy,
+/// since a lost chunk would leave the receiver waiting. Stops at the
+/// first message that fails.
+pub fn send_all(port: &Arc<Mutex<Output>>, messages: &[Vec<u8>]) -> Result<(), String> {
+  messages.iter().try_for_each(|message| Output::send_blocking(port, message))
+}
+
+fn seven_bit(bytes: &[u8], what: &str) -> Result<(), String> {
+  match bytes.iter().position(|b| b & 0x80 != 0) {
+    Some(i) => Err(format!("Byte {i} of the {what} is not 7 bit: {:#04x}", bytes[i])),
+    None => Ok(())
+  }
+}
+
+fn resource_header(resource: &str) -> Vec<u8> {
+  format!(r#"{{"resource":"{}"}}"#, resource.replace('\\', "\\\\").replace('"', "\\\"")).into_bytes()
+}
+
+fn status_header(status: u16) -> Vec<u8> {
+  format!(r#"{{"status":{status}}}"#).into_bytes()
+}
+
+/// Returns the value of `key` in a flat JSON header, without quotes.
+/// Good enough for the `resource` and `status` fields this module reads;
+/// anything more involved should go through a JSON parser.
+/// ```
+/// use midi::ci::json_field;
+/// assert_eq!(json_field(br#"{"resource": "ProgramList"}"#, "resource").as_deref(), Some("ProgramList"));
+/// assert_eq!(json_field(br#"{"status":200}"#, "status").as_deref(), Some("200"));
+/// ```
+pub fn json_field(header: &[u8], key: &str) -> Option<String> {
+  let header = std::str::from_utf8(header).ok()?;
+  let start = header.find(&format!("\"{key}\""))? + key.len() + 2;
+  let rest = header[start..].trim_start().strip_prefix(':')?.trim_start();
+  match rest.strip_prefix('"') {
+    Some(quoted) => {
+      let mut value = String::new();
+      let mut chars = quoted.chars();
+      while let Some(c) = chars.next() {
+        match c {
+          '"' => return Some(value),
+          '\\' => value.push(chars.next()?),
+          c => value.push(c),
+        }
+      }
+      None
+    },
+    None => {
+      let end = rest.find([',', '}']).unwrap_or(rest.len());
+      Some(rest[..end].trim().to_owned())
+    }
+  }
+}
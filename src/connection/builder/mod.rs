@@ -0,0 +1,242 @@
+use super::*;
+use super::receiver::{forward, EventCallback, EventInput, EventSender, Overflow, Receiver};
+use crate::message::event::TimedEvent;
+
+/// How a [`ConnectionBuilder`] picks a port among the ones the system offers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PortSelector {
+  /// The port whose name is exactly this.
+  Exact(String),
+  /// The first port whose name contains this.
+  Contains(String),
+  /// The port at this position in the system's port list.
+  Index(usize),
+  /// Whichever port is listed first.
+  First,
+}
+
+impl PortSelector {
+  /// Returns the position of the selected port in `names`, if any.
+  pub fn select(&self, names: &[String]) -> Option<usize> {
+    match self {
+      Self::Exact(s) => names.iter().position(|n| n == s),
+      Self::Contains(s) => names.iter().position(|n| n.contains(s.as_str())),
+      Self::Index(i) => (*i < names.len()).then_some(*i),
+      Self::First => (!names.is_empty()).then_some(0),
+    }
+  }
+
+  /// Returns `true` if a port named `name` could be picked by this selector.
+  /// [`PortSelector::Index`] and [`PortSelector::First`] match any name.
+  pub fn matches(&self, name: &str) -> bool {
+    match self {
+      Self::Exact(s) => name == s,
+      Self::Contains(s) => name.contains(s.as_str()),
+      Self::Index(_) | Self::First => true,
+    }
+  }
+}
+
+impl From<&str> for PortSelector {
+  fn from(value: &str) -> Self { Self::Exact(value.to_owned()) }
+}
+
+impl From<String> for PortSelector {
+  fn from(value: String) -> Self { Self::Exact(value) }
+}
+
+impl From<usize> for PortSelector {
+  fn from(value: usize) -> Self { Self::Index(value) }
+}
+
+/// Configures and opens [`Output`] and [`Input`] connections.
+/// ```
+/// use midi::connection::{ConnectionBuilder, PortSelector, Ignore};
+/// let port = ConnectionBuilder::new(PortSelector::Contains("IAC".into()))
+///   .client_name("sequencer")
+///   .port_name("out 1")
+///   .output(|_| {});
+///
+/// let input = ConnectionBuilder::new("IAC Driver Bus 1")
+///   .client_name("sequencer")
+///   .ignore(Ignore::ActiveSense)
+///   .input((), |timecode, message, _| println!("{timecode}: {message:?}"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct ConnectionBuilder {
+  pub(super) client_name: String,
+  pub(super) port_name: Option<String>,
+  pub(super) selector: PortSelector,
+  pub(super) ignore: Ignore,
+  pub(super) reconnect: Option<Duration>,
+  pub(super) offline: OfflinePolicy,
+  pub(super) throttle: Option<Throttle>,
+  pub(super) running_status: bool,
+}
+
+impl ConnectionBuilder {
+  pub fn new(selector: impl Into<PortSelector>) -> Self {
+    Self {
+      client_name: DEFAULT_CLIENT_NAME.to_owned(),
+      port_name: None,
+      selector: selector.into(),
+      ignore: Ignore::None,
+      reconnect: None,
+      offline: OfflinePolicy::Discard,
+      throttle: None,
+      running_status: false,
+    }
+  }
+
+  /// Name of the MIDI client, as it shows up in `aconnect` or a DAW.
+  /// Defaults to [`DEFAULT_CLIENT_NAME`].
+  pub fn client_name(mut self, name: impl Into<String>) -> Self {
+    self.client_name = name.into();
+    self
+  }
+
+  /// Name of this side of the connection.
+  /// Defaults to the name of the port that was connected to.
+  pub fn port_name(mut self, name: impl Into<String>) -> Self {
+    self.port_name = Some(name.into());
+    self
+  }
+
+  pub fn selector(mut self, selector: impl Into<PortSelector>) -> Self {
+    self.selector = selector.into();
+    self
+  }
+
+  /// Message types to filter out on input, in midir's terms.
+  /// Has no effect on outputs. Defaults to [`Ignore::None`].
+  pub fn ignore(mut self, ignore: Ignore) -> Self {
+    self.ignore = ignore;
+    self
+  }
+
+  /// Message types to receive on input. Has no effect on outputs.
+  /// Defaults to [`InputFilter::ALL`].
+  pub fn filter(mut self, filter: InputFilter) -> Self {
+    self.ignore = filter.into();
+    self
+  }
+
+  /// Keep the connection alive across unplugging, checking for the device
+  /// every `interval`. See [`Output::new_reconnecting`].
+  pub fn reconnect(mut self, interval: Duration) -> Self {
+    self.reconnect = Some(interval);
+    self
+  }
+
+  /// What a reconnecting [`Output`] does with messages while offline.
+  pub fn offline(mut self, policy: OfflinePolicy) -> Self {
+    self.offline = policy;
+    self
+  }
+
+  /// Paces the [`Output`] to a slow device, see [`Throttle`].
+  /// Has no effect on inputs.
+  pub fn throttle(mut self, throttle: Throttle) -> Self {
+    self.throttle = Some(throttle);
+    self
+  }
+
+  /// Leave out repeated status bytes on output, see [`RunningStatus`].
+  /// Defaults to `false`. Has no effect on inputs.
+  ///
+  /// None of midir's backends can take it, see [`RunningStatus`]: the
+  /// status is carried over from one send to the next, and CoreMIDI,
+  /// ALSA and WinMM ports expect every send to be a complete message.
+  /// Leave it off unless the port is a raw serial link passing bytes
+  /// through as they are.
+  pub fn running_status(mut self, on: bool) -> Self {
+    self.running_status = on;
+    self
+  }
+
+  /// Opens an [`Output`], then runs `callback` with it, like [`Output::new`].
+  pub fn output<F>(self, mut callback: F) -> Result<Arc<Mutex<Output>>, String>
+    where F: FnMut(Arc<Mutex<Output>>),
+  {
+    let Some(interval) = self.reconnect else {
+      let conn = Output::init(&self)?;
+      let throttle = self.throttle;
+      let running_status = self.running_status.then(RunningStatus::new);
+      let output = Output{ conn: Some(conn), settings: self, offline: None, watcher: None, pacer: None, running_status };
+      let arc_output = Arc::new(Mutex::new(output));
+      if throttle.is_some() { Output::set_throttle(&arc_output, throttle) }
+      callback(arc_output.clone());
+      return Ok(arc_output)
+    };
+
+    let output = Output{
+      conn: Output::init(&self).ok(),
+      offline: Some(Offline{ policy: self.offline, buffer: VecDeque::new(), retry_at: Instant::now() }),
+      watcher: None,
+      pacer: None,
+      running_status: self.running_status.then(RunningStatus::new),
+      settings: self,
+    };
+    let client_name = output.settings.client_name.clone();
+    let throttle = output.settings.throttle;
+    let arc_output = Arc::new(Mutex::new(output));
+    if throttle.is_some() { Output::set_throttle(&arc_output, throttle) }
+    let weak = Arc::downgrade(&arc_output);
+    let watcher = PortWatcher::with_client(&client_name, interval, move |event| {
+      Output::on_port_event(&weak, event)
+    })?;
+    if let Ok(mut o) = arc_output.lock() {
+      o.watcher = Some(watcher);
+    }
+    callback(arc_output.clone());
+    Ok(arc_output)
+  }
+
+  /// Opens an [`Input`] that calls `callback` for every incoming message.
+  /// Reconnection settings are ignored, see [`ConnectionBuilder::reconnecting_input`].
+  pub fn input<T, F>(self, data: T, callback: F) -> Result<Input<T, F>, String>
+    where 
+      T: Send + 'static,
+      F: FnMut(u64, &[u8], &mut T) + Send + 'static,
+  {
+    let handler = Arc::new(Mutex::new(Handler{ data, callback }));
+    let conn = Input::init(&self, handler.clone())?;
+    Ok(Input{ conn: Some(conn), handler, settings: self, watcher: None })
+  }
+
+  /// Opens an [`Input`] that queues parsed messages on a bounded channel
+  /// instead of calling back, see [`EventInput::receiver`](super::receiver::EventInput::receiver).
+  pub fn receiver(self, capacity: usize, overflow: Overflow) -> Result<(EventInput, Receiver<TimedEvent>), String> {
+    let (sender, rx) = EventSender::new(capacity, overflow);
+    let input = self.input(sender, forward as EventCallback)?;
+    Ok((input, rx))
+  }
+
+  /// Opens an [`Input`] that reconnects when its device comes back,
+  /// like [`Input::new_reconnecting`]. Checks every [`DEFAULT_INTERVAL`]
+  /// unless [`ConnectionBuilder::reconnect`] says otherwise.
+  pub fn reconnecting_input<T, F>(self, data: T, callback: F) -> Result<Arc<Mutex<Input<T, F>>>, String>
+    where 
+      T: Send + 'static,
+      F: FnMut(u64, &[u8], &mut T) + Send + 'static,
+  {
+    let interval = self.reconnect.unwrap_or(DEFAULT_INTERVAL);
+    let handler = Arc::new(Mutex::new(Handler{ data, callback }));
+    let input = Input{
+      conn: Input::init(&self, handler.clone()).ok(),
+      handler,
+      watcher: None,
+      settings: self,
+    };
+    let client_name = input.settings.client_name.clone();
+    let arc_input = Arc::new(Mutex::new(input));
+    let weak = Arc::downgrade(&arc_input);
+    let watcher = PortWatcher::with_client(&client_name, interval, move |event| {
+      Input::on_port_event(&weak, event)
+    })?;
+    if let Ok(mut i) = arc_input.lock() {
+      i.watcher = Some(watcher);
+    }
+    Ok(arc_input)
+  }
+}
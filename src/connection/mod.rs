@@ -1,19 +1,117 @@
-use std::{marker::PhantomData};
+pub mod watch;
+pub mod builder;
+pub mod receiver;
+pub mod running_status;
+pub mod throttle;
+#[cfg(feature = "async")]
+pub mod stream;
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
 
 use crate::{
   MidiOutputConnection,
 };
 
 use midir::{
-  MidiInput, MidiInputConnection, MidiInputPort, MidiOutput, MidiOutputPort
+  MidiInput, MidiInputConnection, MidiInputPort, MidiOutput, MidiOutputPort, SendError
 };
 
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, Weak};
+
+use watch::{port_names, PortDirection, PortEvent, PortWatcher, DEFAULT_INTERVAL};
+use running_status::RunningStatus;
+use throttle::{Pacer, Throttle};
+
+pub use builder::{ConnectionBuilder, PortSelector};
+pub use midir::Ignore;
+
+/// Client name used when none is given to a [`ConnectionBuilder`].
+pub const DEFAULT_CLIENT_NAME: &str = "cpu";
+
+/// Decides what happens to messages sent to a reconnecting [`Output`]
+/// while its device is unplugged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OfflinePolicy {
+  /// Messages are dropped until the device is back.
+  Discard,
+  /// Up to `n` messages are kept and sent, in order, once the device is back.
+  /// When full, the oldest message is dropped.
+  Buffer(usize),
+}
+
+/// Which system messages an [`Input`] lets through, `true` meaning received.
+///
+/// The crate default, [`InputFilter::ALL`], receives everything, including
+/// SysEx, timing and active sensing. This matches midir, which filters
+/// nothing unless told to (unlike RtMidi, which drops all three by default).
+/// ```
+/// use midi::connection::{ConnectionBuilder, InputFilter};
+/// // follow an external clock, but skip active sensing and SysEx
+/// let input = ConnectionBuilder::new("IAC Driver Bus 1")
+///   .filter(InputFilter{ sysex: false, timing: true, active_sensing: false })
+///   .input((), |_, message, _| println!("{message:?}"));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InputFilter {
+  /// System Exclusive messages, `0xF0 .. 0xF7`.
+  pub sysex: bool,
+  /// Timing Clock (`0xF8`) and MIDI Time Code quarter frames (`0xF1`).
+  pub timing: bool,
+  /// Active Sensing, `0xFE`.
+  pub active_sensing: bool,
+}
+
+impl InputFilter {
+  pub const ALL: Self = Self{ sysex: true, timing: true, active_sensing: true };
+  pub const NONE: Self = Self{ sysex: false, timing: false, active_sensing: false };
+}
+
+impl Default for InputFilter {
+  fn default() -> Self { Self::ALL }
+}
+
+impl From<InputFilter> for Ignore {
+  fn from(value: InputFilter) -> Self {
+    let mut ignore = Ignore::None;
+    if !value.sysex { ignore = ignore | Ignore::Sysex }
+    if !value.timing { ignore = ignore | Ignore::Time }
+    if !value.active_sensing { ignore = ignore | Ignore::ActiveSense }
+    ignore
+  }
+}
+
+impl From<Ignore> for InputFilter {
+  fn from(value: Ignore) -> Self {
+    Self{
+      sysex: !matches!(value, Ignore::Sysex | Ignore::SysexAndTime | Ignore::SysexAndActiveSense | Ignore::All),
+      timing: !matches!(value, Ignore::Time | Ignore::SysexAndTime | Ignore::TimeAndActiveSense | Ignore::All),
+      active_sensing: !matches!(value, Ignore::ActiveSense | Ignore::SysexAndActiveSense | Ignore::TimeAndActiveSense | Ignore::All),
+    }
+  }
+}
+
+struct Offline {
+  policy: OfflinePolicy,
+  buffer: VecDeque<Vec<u8>>,
+  /// Earliest a send may try to reconnect, see [`Output::retry`].
+  retry_at: Instant,
+}
+
+impl Offline {
+  fn hold(&mut self, message: &[u8]) {
+    if let OfflinePolicy::Buffer(n) = self.policy {
+      if n == 0 { return }
+      if self.buffer.len() == n { self.buffer.pop_front(); }
+      self.buffer.push_back(message.to_vec());
+    }
+  }
+}
 
 /// Convenience struct for creating a Midi Output connection.
 /// Provides the option to create a Midi runner callback closure. 
 /// ```
-/// use std::time::Duration;
+/// use std::time::{Duration, Instant};
 /// use midi::{note::{note_on, note_off}, transport::sleep};
 /// let port = midi::connection::Output::new("IAC Driver Bus 1", |output| {
 ///    for _ in 0..1 {
@@ -25,7 +123,12 @@ use std::sync::{Arc, Mutex};
 /// });
 /// ```
 pub struct Output { 
-  conn: MidiOutputConnection,
+  conn: Option<(MidiOutputConnection, String)>,
+  settings: ConnectionBuilder,
+  offline: Option<Offline>,
+  watcher: Option<PortWatcher>,
+  pacer: Option<Pacer>,
+  running_status: Option<RunningStatus>,
 }
 
 impl Output {
@@ -33,30 +136,159 @@ impl Output {
   ///
   /// If no closure is passed to the constructor, the `Self` is returned,
   /// otherwise it will return after the callback has finished. 
-  pub fn new<F>(device: &'static str, mut callback: F) -> Result<Arc<Mutex<Self>>, String>
+  pub fn new<F>(device: &'static str, callback: F) -> Result<Arc<Mutex<Self>>, String>
     where F: FnMut(Arc<Mutex<Output>>),
   {
-    match Self::init(device) {
-      Ok(c) => {
-        let output = Self{ conn: c };
-        let arc_output = Arc::new(Mutex::new(output));
-        callback(arc_output.clone());
-        Ok(arc_output)
-      },
-      Err(e) => Err(e)
-    }
-
+    ConnectionBuilder::new(device).output(callback)
+  }
 
+  /// Same as [`Output::new`], but the connection survives the device being
+  /// unplugged. A background [`PortWatcher`] polls every `interval` and
+  /// reconnects to `device` when it reappears. Messages sent in the meantime
+  /// are handled according to `policy`, and never cause a send error. They
+  /// also try to reconnect, at most once every `interval`, in case a send
+  /// failed while the device stayed in the port list.
+  ///
+  /// Unlike [`Output::new`], this does not fail if the device is missing
+  /// at startup; the output simply starts offline.
+  pub fn new_reconnecting<F>(
+    device: &'static str,
+    policy: OfflinePolicy,
+    interval: Duration,
+    callback: F
+  ) -> Result<Arc<Mutex<Self>>, String>
+    where F: FnMut(Arc<Mutex<Output>>),
+  {
+    ConnectionBuilder::new(device)
+      .reconnect(interval)
+      .offline(policy)
+      .output(callback)
   }
 
   // pub fn get_conn(&mut self) -> Arc<Mutex<MidiOutputConnection>> { self.conn }
+  /// Sends `message`, or queues it if the output is throttled.
   pub fn send(&mut self, message: &[u8]) -> Result<(), midir::SendError> {
-    self.conn.send(message)
+    if let Some(pacer) = self.pacer.as_ref() {
+      if !Pacer::is_realtime(message) { return pacer.push(message) }
+      pacer.spend(message);
+    }
+    self.write(message)
+  }
+
+  /// Sends `bytes`, waiting for `port` if it is busy, for messages that
+  /// must not be dropped. Every message in `bytes` goes out with its own
+  /// send, as midir's backends take one message at a time, see
+  /// [`split`](crate::message::event::split).
+  pub fn send_blocking(port: &Arc<Mutex<Self>>, bytes: &[u8]) -> Result<(), String> {
+    let mut port = port.lock().map_err(|_| "output lock is poisoned".to_owned())?;
+    for message in crate::message::event::split(bytes) {
+      port.send(message).map_err(|e| format!("could not send: {e}"))?;
+    }
+    Ok(())
+  }
+
+  /// Paces everything sent to `output` from now on, or stops pacing it
+  /// with `None`. Sends still waiting when the throttle is changed are dropped.
+  pub fn set_throttle(output: &Arc<Mutex<Self>>, throttle: Option<Throttle>) {
+    let pacer = throttle.map(|t| Pacer::new(t, Arc::downgrade(output)));
+    if let Ok(mut o) = output.lock() { o.pacer = pacer }
+  }
+
+  pub fn throttle(&self) -> Option<Throttle> {
+    self.pacer.as_ref().map(Pacer::throttle)
+  }
+
+  /// Number of sends waiting for a throttled output.
+  pub fn queued(&self) -> usize {
+    self.pacer.as_ref().map_or(0, Pacer::queued)
+  }
+
+  /// Leaves out repeated status bytes from now on, see [`RunningStatus`].
+  /// Off by default, as some devices and drivers expect every message in full.
+  /// Only for raw serial or DIN links, see [`ConnectionBuilder::running_status`].
+  pub fn set_running_status(&mut self, on: bool) {
+    self.running_status = on.then(RunningStatus::new);
+  }
+
+  pub fn running_status(&self) -> bool { self.running_status.is_some() }
+
+  fn write(&mut self, message: &[u8]) -> Result<(), midir::SendError> {
+    if self.offline.is_none() {
+      return match self.conn.as_mut() {
+        Some((conn, _)) => Self::send_encoded(conn, &mut self.running_status, message),
+        None => Err(SendError::Other("output is not connected"))
+      }
+    }
+    if self.conn.is_none() { self.retry() }
+    if let Some((conn, _)) = self.conn.as_mut() {
+      if Self::send_encoded(conn, &mut self.running_status, message).is_ok() { return Ok(()) }
+      // The device most likely went away, wait for the watcher to bring it back.
+      self.conn = None;
+    }
+    if let Some(offline) = self.offline.as_mut() { offline.hold(message) }
+    Ok(())
   }
 
-  fn connect(output: MidiOutput, port: &MidiOutputPort, device: &'static str) -> Result<MidiOutputConnection, String> 
+  /// Reconnects from a send, at most once every reconnect interval. The
+  /// watcher only reconnects when the port comes back, which it never
+  /// does if a send failed without the device going away.
+  fn retry(&mut self) {
+    let now = Instant::now();
+    let Some(offline) = self.offline.as_mut() else { return };
+    if now < offline.retry_at { return }
+    offline.retry_at = now + self.settings.reconnect.unwrap_or(DEFAULT_INTERVAL);
+    self.reconnect();
+  }
+
+  /// Sends through the running status encoder, if there is one. After a
+  /// failed send the receiver's running status is unknown, so it starts over.
+  fn send_encoded(
+    conn: &mut MidiOutputConnection,
+    encoder: &mut Option<RunningStatus>,
+    message: &[u8]
+  ) -> Result<(), SendError> {
+    let Some(encoder) = encoder.as_mut() else { return conn.send(message) };
+    let result = conn.send(&encoder.encode(message));
+    if result.is_err() { encoder.reset() }
+    result
+  }
+
+  /// Returns `true` if the output currently has a live connection.
+  pub fn is_connected(&self) -> bool { self.conn.is_some() }
+
+  fn on_port_event(output: &Weak<Mutex<Self>>, event: &PortEvent) {
+    if event.direction() != PortDirection::Output { return }
+    let Some(output) = output.upgrade() else { return };
+    let Ok(mut output) = output.lock() else { return };
+    match event {
+      PortEvent::Disconnected(_, name) => {
+        if output.conn.as_ref().is_some_and(|(_, n)| n == name) { output.conn = None }
+      },
+      PortEvent::Connected(_, name) => {
+        if output.settings.selector.matches(name) { output.reconnect() }
+      },
+    }
+  }
+
+  fn reconnect(&mut self) {
+    if self.conn.is_some() { return }
+    let Ok((mut conn, name)) = Self::init(&self.settings) else { return };
+    // a new connection knows nothing of the running status before it
+    if let Some(encoder) = self.running_status.as_mut() { encoder.reset() }
+    if let Some(offline) = self.offline.as_mut() {
+      while let Some(message) = offline.buffer.pop_front() {
+        if conn.send(&message).is_err() {
+          offline.buffer.push_front(message);
+          return
+        }
+      }
+    }
+    self.conn = Some((conn, name));
+  }
+
+  fn connect(output: MidiOutput, port: &MidiOutputPort, port_name: &str) -> Result<MidiOutputConnection, String> 
   {
-    match output.connect(port, device) {
+    match output.connect(port, port_name) {
       Ok(conn) => {
         Ok(conn)
       },
@@ -64,17 +296,27 @@ impl Output {
     }
   } 
 
-  fn init(device: &'static str) -> Result<MidiOutputConnection, String> {
+  fn init(settings: &ConnectionBuilder) -> Result<(MidiOutputConnection, String), String> {
     // Setup new MIDI output client, (should not fail).
-    let output = Self::init_client()?;
+    let output = Self::init_client(&settings.client_name)?;
     // See if there is a device that corresponds to requested port.
-    let port = Self::validate_port(&output, device, output.ports())?;
+    let (port, name) = Self::validate_port(&output, &settings.selector, output.ports())?;
     // create output connection
-    Self::connect(output, &port, device)
+    let conn = Self::connect(output, &port, settings.port_name.as_deref().unwrap_or(&name))?;
+    Ok((conn, name))
   }
 }
 
 
+/// The user data and callback of an [`Input`], shared with the underlying
+/// connection so that they outlive it when reconnecting.
+struct Handler<T, F> {
+  data: T,
+  callback: F,
+}
+
+type SharedHandler<T, F> = Arc<Mutex<Handler<T, F>>>;
+
 /// Convenience struct for creating a Midi Input connection
 /// ```
 /// use std::collections::VecDeque;
@@ -96,8 +338,10 @@ pub struct Input<T, F>
     T: Send + 'static,
     F: FnMut(u64, &[u8], &mut T) + Send + 'static,
 {
-  pub conn: MidiInputConnection<T>,
-  _marker: PhantomData<F>
+  conn: Option<(MidiInputConnection<SharedHandler<T, F>>, String)>,
+  handler: SharedHandler<T, F>,
+  settings: ConnectionBuilder,
+  watcher: Option<PortWatcher>,
 }
 
 impl<T, F> Input<T, F>
@@ -107,28 +351,98 @@ impl<T, F> Input<T, F>
 {
   pub fn new(device: &'static str, data: T, callback: F) -> Result<Self, String>
   {
-    match Self::init(device, data, callback) {
-      Ok(c) => Ok(Self{
-        conn: c,
-        _marker: PhantomData
-      }),
-      Err(e) => Err(e)
+    ConnectionBuilder::new(device).input(data, callback)
+  }
+
+  /// Same as [`Input::new`], but reconnects to `device` when it is
+  /// unplugged and plugged back in. A background [`PortWatcher`] polls
+  /// every `interval`. The same `data` and `callback` keep receiving
+  /// messages after a reconnect.
+  ///
+  /// Does not fail if the device is missing at startup.
+  pub fn new_reconnecting(
+    device: &'static str,
+    data: T,
+    callback: F,
+    interval: Duration
+  ) -> Result<Arc<Mutex<Self>>, String> {
+    ConnectionBuilder::new(device)
+      .reconnect(interval)
+      .reconnecting_input(data, callback)
+  }
+
+  /// Returns `true` if the input currently has a live connection.
+  pub fn is_connected(&self) -> bool { self.conn.is_some() }
+
+  /// Returns the name of the port the input is connected to, if any.
+  pub fn port_name(&self) -> Option<&str> { self.conn.as_ref().map(|(_, name)| name.as_str()) }
+
+  /// Runs `f` on the user data, as the callback sees it. Returns `None` if
+  /// the data is poisoned by a panicking callback.
+  pub fn with_data<R>(&self, f: impl FnOnce(&mut T) -> R) -> Option<R> {
+    self.handler.lock().ok().map(|mut h| f(&mut h.data))
+  }
+
+  /// Returns the message types this input currently receives.
+  pub fn filter(&self) -> InputFilter { self.settings.ignore.into() }
+
+  /// Changes which message types this input receives.
+  ///
+  /// midir only applies filters when connecting, so the port is
+  /// reopened. Messages arriving during the switch may be lost.
+  pub fn set_filter(&mut self, filter: InputFilter) -> Result<(), String> {
+    self.settings.ignore = filter.into();
+    if self.conn.take().is_none() { return Ok(()) }
+    self.conn = Some(Self::init(&self.settings, self.handler.clone())?);
+    Ok(())
+  }
+
+  /// Closes the connection and hands back the user data.
+  pub fn close(mut self) -> Option<T> {
+    self.watcher = None;
+    self.conn = None;
+    match Arc::try_unwrap(self.handler) {
+      Ok(handler) => handler.into_inner().ok().map(|h| h.data),
+      Err(_) => None
+    }
+  }
+
+  fn on_port_event(input: &Weak<Mutex<Self>>, event: &PortEvent) {
+    if event.direction() != PortDirection::Input { return }
+    let Some(input) = input.upgrade() else { return };
+    let Ok(mut input) = input.lock() else { return };
+    match event {
+      PortEvent::Disconnected(_, name) => {
+        if input.conn.as_ref().is_some_and(|(_, n)| n == name) { input.conn = None }
+      },
+      PortEvent::Connected(_, name) => {
+        if input.conn.is_some() || !input.settings.selector.matches(name) { return }
+        input.conn = Self::init(&input.settings, input.handler.clone()).ok();
+      }
     }
   }
 
   #[inline]
-  fn connect(input: MidiInput, port: &MidiInputPort, device: &'static str, data: T, callback: F) -> Result<MidiInputConnection<T>, String> {
-    match input.connect(port, device, callback, data) {
+  fn connect(input: MidiInput, port: &MidiInputPort, port_name: &str, handler: SharedHandler<T, F>) -> Result<MidiInputConnection<SharedHandler<T, F>>, String> {
+    let forward = |timecode: u64, message: &[u8], handler: &mut SharedHandler<T, F>| {
+      if let Ok(mut h) = handler.lock() {
+        let Handler { data, callback } = &mut *h;
+        callback(timecode, message, data)
+      }
+    };
+    match input.connect(port, port_name, forward, handler) {
       Ok(conn) => Ok(conn),
-      Err(e) => Err(format!("could not connect to output port: {}", e))
+      Err(e) => Err(format!("could not connect to input port: {}", e))
     }
   } 
 
   #[inline]
-  fn init(device: &'static str, data: T, callback: F) -> Result<MidiInputConnection<T>, String> {
-    let input = Self::init_client()?;
-    let port = Self::validate_port(&input, device, input.ports())?;
-    Self::connect(input, &port, device, data, callback)
+  fn init(settings: &ConnectionBuilder, handler: SharedHandler<T, F>) -> Result<(MidiInputConnection<SharedHandler<T, F>>, String), String> {
+    let mut input = Self::init_client(&settings.client_name)?;
+    input.ignore(settings.ignore);
+    let (port, name) = Self::validate_port(&input, &settings.selector, input.ports())?;
+    let conn = Self::connect(input, &port, settings.port_name.as_deref().unwrap_or(&name), handler)?;
+    Ok((conn, name))
   }
 }
 
@@ -136,26 +450,27 @@ impl<T, F> Input<T, F>
 trait MidiConnection {
   type MidiType;
   type MidiPort;
-  fn validate_port(input: &Self::MidiType, device: &'static str, ports: Vec<Self::MidiPort>) -> Result<Self::MidiPort, String>;
-  fn init_client() -> Result<Self::MidiType, String>;
+  fn validate_port(input: &Self::MidiType, selector: &PortSelector, ports: Vec<Self::MidiPort>) -> Result<(Self::MidiPort, String), String>;
+  fn init_client(name: &str) -> Result<Self::MidiType, String>;
 }
 
 
 impl MidiConnection for Output {
   type MidiType = MidiOutput;
   type MidiPort = MidiOutputPort;
-  fn validate_port(output: &Self::MidiType, device: &'static str, ports: Vec<Self::MidiPort>) -> Result<Self::MidiPort, String> {
-    match ports
+  fn validate_port(output: &Self::MidiType, selector: &PortSelector, ports: Vec<Self::MidiPort>) -> Result<(Self::MidiPort, String), String> {
+    let names: Vec<String> = ports
       .iter()
-      .find(|p| 
-        Some(device) == output.port_name(p).ok().as_deref()) {
-      Some(p) => Ok(p.clone()),
+      .map(|p| output.port_name(p).unwrap_or_default())
+      .collect();
+    match selector.select(&names) {
+      Some(i) => Ok((ports[i].clone(), names[i].clone())),
       None => Err("could not find output port".to_owned())
     }
   }
   
-  fn init_client() -> Result<Self::MidiType, String> {
-    match Self::MidiType::new("cpu") {
+  fn init_client(name: &str) -> Result<Self::MidiType, String> {
+    match Self::MidiType::new(name) {
       Ok(output) => Ok(output),
       Err(e) => Err(format!("could not create MIDI output: {}", e))
     }
@@ -170,18 +485,19 @@ impl<T, F> MidiConnection for Input<T, F>
 {
   type MidiType = MidiInput;
   type MidiPort = MidiInputPort;
-  fn validate_port(input: &Self::MidiType, device: &'static str, ports: Vec<Self::MidiPort>) -> Result<Self::MidiPort, String> {
-    match ports
+  fn validate_port(input: &Self::MidiType, selector: &PortSelector, ports: Vec<Self::MidiPort>) -> Result<(Self::MidiPort, String), String> {
+    let names: Vec<String> = ports
       .iter()
-      .find(|p| 
-        Some(device) == input.port_name(p).ok().as_deref()) {
-      Some(p) => Ok(p.clone()),
+      .map(|p| input.port_name(p).unwrap_or_default())
+      .collect();
+    match selector.select(&names) {
+      Some(i) => Ok((ports[i].clone(), names[i].clone())),
       None => Err("could not find input port".to_owned())
     }
   }
   
-  fn init_client() -> Result<Self::MidiType, String> {
-    match Self::MidiType::new("cpu") {
+  fn init_client(name: &str) -> Result<Self::MidiType, String> {
+    match Self::MidiType::new(name) {
       Ok(input) => Ok(input),
       Err(e) => Err(format!("could not create MIDI input: {}", e))
     }
@@ -191,16 +507,18 @@ impl<T, F> MidiConnection for Input<T, F>
 pub struct InputPorts ();
 impl InputPorts {
   pub fn ports() -> Option<Vec<String>> {
-    if let Ok(input) = MidiInput::new("hello") {
-      return Some(
-        input
-        .ports()
-        .iter()
-        .map(|p| 
-          input
-          .port_name(p)
-          .expect("a device was unplugged during validation"))
-        .collect())
+    if let Ok(input) = MidiInput::new(DEFAULT_CLIENT_NAME) {
+      return Some(port_names(&input))
+    }
+    None
+  }
+}
+
+pub struct OutputPorts ();
+impl OutputPorts {
+  pub fn ports() -> Option<Vec<String>> {
+    if let Ok(output) = MidiOutput::new(DEFAULT_CLIENT_NAME) {
+      return Some(port_names(&output))
     }
     None
   }
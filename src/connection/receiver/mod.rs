@@ -0,0 +1,71 @@
+use super::*;
+
+use crossbeam_channel::{bounded, Sender, TrySendError};
+pub use crossbeam_channel::Receiver;
+
+use crate::message::event::{MidiEvent, TimedEvent};
+
+/// What a bounded receiver does with new events when it is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Overflow {
+  /// Keep the queued events and drop the incoming one.
+  DropNewest,
+  /// Drop the oldest queued event to make room for the incoming one.
+  DropOldest,
+}
+
+/// The sending half of an [`EventInput`], used as its user data.
+pub struct EventSender {
+  tx: Sender<TimedEvent>,
+  // Kept to make room in the queue when dropping the oldest event.
+  rx: Receiver<TimedEvent>,
+  overflow: Overflow,
+}
+
+impl EventSender {
+  pub(crate) fn new(capacity: usize, overflow: Overflow) -> (Self, Receiver<TimedEvent>) {
+    let (tx, rx) = bounded(capacity.max(1));
+    (Self{ tx, rx: rx.clone(), overflow }, rx)
+  }
+
+  pub(crate) fn push(&mut self, event: TimedEvent) {
+    match self.tx.try_send(event) {
+      Err(TrySendError::Full(event)) if self.overflow == Overflow::DropOldest => {
+        let _ = self.rx.try_recv();
+        let _ = self.tx.try_send(event);
+      },
+      _ => ()
+    }
+  }
+}
+
+pub type EventCallback = fn(u64, &[u8], &mut EventSender);
+
+/// An [`Input`] that parses incoming messages and queues them on a channel.
+pub type EventInput = Input<EventSender, EventCallback>;
+
+pub(crate) fn forward(timestamp: u64, message: &[u8], sender: &mut EventSender) {
+  for event in MidiEvent::parse_all(message) {
+    sender.push(TimedEvent{ timestamp, event })
+  }
+}
+
+impl EventInput {
+  /// Opens `device` and returns the connection together with a [`Receiver`]
+  /// of parsed, timestamped messages. At most `capacity` events are queued;
+  /// beyond that `overflow` decides which ones are lost.
+  ///
+  /// The connection stays open for as long as the returned [`EventInput`] lives.
+  /// ```
+  /// use std::time::Duration;
+  /// use midi::connection::{Input, receiver::Overflow};
+  /// if let Ok((_input, events)) = Input::receiver("IAC Driver Bus 1", 256, Overflow::DropOldest) {
+  ///   while let Ok(timed) = events.recv_timeout(Duration::from_secs(1)) {
+  ///     println!("{}: {:?}", timed.timestamp, timed.event);
+  ///   }
+  /// }
+  /// ```
+  pub fn receiver(device: &'static str, capacity: usize, overflow: Overflow) -> Result<(Self, Receiver<TimedEvent>), String> {
+    ConnectionBuilder::new(device).receiver(capacity, overflow)
+  }
+}
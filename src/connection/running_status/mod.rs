@@ -0,0 +1,58 @@
+use crate::consts::message::SYSEX_BEGIN;
+
+/// Leaves out channel status bytes that repeat the one sent before, as
+/// MIDI 1.0 running status allows. A CC sequence such as an NRPN then
+/// costs 13 bytes instead of 18.
+///
+/// SysEx and system common messages end running status, so the next
+/// channel message gets its status byte again. Realtime messages, which
+/// may come in between, leave it alone.
+///
+/// None of midir's backends can take running status: CoreMIDI, ALSA
+/// and WinMM all expect every send to be a complete message. The
+/// encoder is for bytes written to a raw serial link by other means,
+/// and for counting what a DIN cable carries, as [`Throttle`] does.
+///
+/// [`Throttle`]: super::throttle::Throttle
+/// ```
+/// use midi::connection::running_status::RunningStatus;
+/// let mut encoder = RunningStatus::new();
+/// assert_eq!(encoder.encode(&[0xB0, 99, 0, 0xB0, 98, 72]), vec![0xB0, 99, 0, 98, 72]);
+/// assert_eq!(encoder.encode(&[0xF8, 0xB0, 6, 1]), vec![0xF8, 6, 1]);
+/// assert_eq!(encoder.encode(&[0xF0, 0x7E, 0xF7]), vec![0xF0, 0x7E, 0xF7]);
+/// assert_eq!(encoder.encode(&[0xB0, 38, 0]), vec![0xB0, 38, 0]);
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RunningStatus {
+  status: Option<u8>,
+}
+
+impl RunningStatus {
+  pub fn new() -> Self { Self::default() }
+
+  /// The status byte the receiver is holding on to, if any.
+  pub fn status(&self) -> Option<u8> { self.status }
+
+  /// Forgets the running status, so that the next channel message is
+  /// sent in full. Needed whenever the receiver may have lost track,
+  /// e.g. after a failed send or a reconnect.
+  pub fn reset(&mut self) { self.status = None }
+
+  /// `message` with repeated status bytes left out.
+  pub fn encode(&mut self, message: &[u8]) -> Vec<u8> {
+    message.iter().copied().filter(|b| self.keep(*b)).collect()
+  }
+
+  /// Whether byte `b` has to be sent, updating the running status.
+  pub(crate) fn keep(&mut self, b: u8) -> bool {
+    match b {
+      0xF8.. => true,
+      SYSEX_BEGIN..=0xF7 => {
+        self.status = None;
+        true
+      },
+      0x80..=0xEF => self.status.replace(b) != Some(b),
+      _ => true,
+    }
+  }
+}
@@ -0,0 +1,183 @@
+use super::*;
+
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::task::{Context, Poll};
+use std::thread;
+
+use crossbeam_channel::TryRecvError;
+use futures::{
+  channel::{mpsc, oneshot},
+  executor::block_on,
+  future::FutureExt,
+  task::AtomicWaker,
+  Sink, Stream, StreamExt,
+};
+
+use super::receiver::{forward, EventSender, Overflow, Receiver};
+use crate::message::event::{MidiEvent, TimedEvent};
+
+/// User data of the [`Input`] behind a [`MidiStream`].
+pub struct StreamSender {
+  sender: EventSender,
+  waker: Arc<AtomicWaker>,
+}
+
+pub type StreamCallback = fn(u64, &[u8], &mut StreamSender);
+
+fn forward_and_wake(timestamp: u64, message: &[u8], s: &mut StreamSender) {
+  forward(timestamp, message, &mut s.sender);
+  s.waker.wake();
+}
+
+/// An async [`Stream`] of parsed, timestamped messages from an [`Input`].
+///
+/// Incoming messages are queued, up to `capacity`, until polled.
+/// Dropping the stream closes the input. A message is only taken off
+/// the queue when it is returned, so dropping a pending `next()` loses nothing.
+/// ```
+/// use futures::StreamExt;
+/// use midi::connection::{ConnectionBuilder, receiver::Overflow, stream::MidiStream};
+/// # futures::executor::block_on(async {
+/// if let Ok(mut stream) = MidiStream::new(ConnectionBuilder::new("IAC Driver Bus 1"), 256, Overflow::DropOldest) {
+///   while let Some(timed) = stream.next().await {
+///     println!("{}: {:?}", timed.timestamp, timed.event);
+///   }
+/// }
+/// # });
+/// ```
+pub struct MidiStream {
+  _input: Input<StreamSender, StreamCallback>,
+  events: Receiver<TimedEvent>,
+  waker: Arc<AtomicWaker>,
+}
+
+impl MidiStream {
+  pub fn new(builder: ConnectionBuilder, capacity: usize, overflow: Overflow) -> Result<Self, String> {
+    let (sender, events) = EventSender::new(capacity, overflow);
+    let waker = Arc::new(AtomicWaker::new());
+    let data = StreamSender{ sender, waker: waker.clone() };
+    let input = builder.input(data, forward_and_wake as StreamCallback)?;
+    Ok(Self{ _input: input, events, waker })
+  }
+}
+
+impl Stream for MidiStream {
+  type Item = TimedEvent;
+
+  fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+    match self.events.try_recv() {
+      Ok(event) => return Poll::Ready(Some(event)),
+      Err(TryRecvError::Disconnected) => return Poll::Ready(None),
+      Err(TryRecvError::Empty) => ()
+    }
+    // Register before checking again, so a message arriving in between still wakes us.
+    self.waker.register(cx.waker());
+    match self.events.try_recv() {
+      Ok(event) => Poll::Ready(Some(event)),
+      Err(TryRecvError::Disconnected) => Poll::Ready(None),
+      Err(TryRecvError::Empty) => Poll::Pending
+    }
+  }
+}
+
+/// An async [`Sink`] of messages to an [`Output`].
+///
+/// Messages are handed to a writer thread through a queue of `capacity`
+/// messages; when it is full, `poll_ready` is pending until there is room.
+/// Flushing or closing the sink waits until every queued message has been
+/// sent to the output. A throttled output may still hold them in its own
+/// queue, see [`Output::queued`].
+/// ```
+/// use futures::SinkExt;
+/// use midi::{connection::{Output, stream::MidiSink}, message::event::MidiEvent, util::Channel};
+/// # futures::executor::block_on(async {
+/// if let Ok(port) = Output::new("IAC Driver Bus 1", |_| {}) {
+///   let mut sink = MidiSink::new(port, 64);
+///   sink.send(MidiEvent::NoteOn{ ch: Channel(0), note: 60, velo: 100 }).await.unwrap();
+///   sink.close().await.unwrap();
+/// }
+/// # });
+/// ```
+pub struct MidiSink {
+  tx: mpsc::Sender<MidiEvent>,
+  done: oneshot::Receiver<()>,
+  error: Arc<Mutex<Option<String>>>,
+  /// Messages handed to the writer thread and not yet sent.
+  pending: Arc<AtomicUsize>,
+  sent: Arc<AtomicWaker>,
+}
+
+impl MidiSink {
+  pub fn new(port: Arc<Mutex<Output>>, capacity: usize) -> Self {
+    let (tx, mut rx) = mpsc::channel::<MidiEvent>(capacity);
+    let (done_tx, done) = oneshot::channel();
+    let error = Arc::new(Mutex::new(None));
+    let writer_error = error.clone();
+    let pending = Arc::new(AtomicUsize::new(0));
+    let sent = Arc::new(AtomicWaker::new());
+    let (writer_pending, writer_sent) = (pending.clone(), sent.clone());
+    thread::spawn(move || {
+      block_on(async {
+        while let Some(event) = rx.next().await {
+          let result = Output::send_blocking(&port, &event.to_bytes());
+          if let (Err(e), Ok(mut slot)) = (result, writer_error.lock()) {
+            slot.get_or_insert(e);
+          }
+          writer_pending.fetch_sub(1, Ordering::AcqRel);
+          writer_sent.wake();
+        }
+      });
+      let _ = done_tx.send(());
+    });
+    Self{ tx, done, error, pending, sent }
+  }
+
+  /// Returns the first send error reported by the writer thread, if any.
+  fn take_error(&self) -> Result<(), String> {
+    match self.error.lock().ok().and_then(|mut e| e.take()) {
+      Some(e) => Err(e),
+      None => Ok(())
+    }
+  }
+}
+
+impl Sink<MidiEvent> for MidiSink {
+  type Error = String;
+
+  fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+    self.take_error()?;
+    self.tx.poll_ready(cx).map_err(|e| e.to_string())
+  }
+
+  fn start_send(mut self: Pin<&mut Self>, item: MidiEvent) -> Result<(), Self::Error> {
+    // counted first, so the writer never takes it off before it is on
+    self.pending.fetch_add(1, Ordering::AcqRel);
+    self.tx.start_send(item).map_err(|e| {
+      self.pending.fetch_sub(1, Ordering::AcqRel);
+      e.to_string()
+    })
+  }
+
+  /// Resolves once every queued message has been sent to the output.
+  fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+    self.take_error()?;
+    if Pin::new(&mut self.tx).poll_flush(cx).map_err(|e| e.to_string())?.is_pending() {
+      return Poll::Pending
+    }
+    self.sent.register(cx.waker());
+    match self.pending.load(Ordering::Acquire) {
+      0 => Poll::Ready(self.take_error()),
+      _ => Poll::Pending
+    }
+  }
+
+  /// Resolves once every queued message has been sent to the output.
+  fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+    self.tx.close_channel();
+    match self.done.poll_unpin(cx) {
+      Poll::Ready(_) => Poll::Ready(self.take_error()),
+      Poll::Pending => Poll::Pending
+    }
+  }
+}
@@ -0,0 +1,216 @@
+use super::*;
+
+use std::{
+  sync::Condvar,
+  thread,
+  time::Instant,
+};
+
+use crate::consts::message::{CC, NRPN_VAL_LSB, NRPN_VAL_MSB};
+use super::running_status::RunningStatus;
+
+/// Controllers that are never coalesced, as their order matters: bank
+/// select, data entry, data increment and decrement, and NRPN and RPN
+/// selection. Channel mode messages, 120 and up, are left alone as well.
+const ORDERED: [u8; 10] = [0, 32, NRPN_VAL_MSB, NRPN_VAL_LSB, 96, 97, 98, 99, 100, 101];
+
+/// Paces an [`Output`] to what a serial MIDI cable, or a device behind
+/// it, can take. Sends are queued and written out no faster than
+/// `bytes_per_second`, counting the bytes running status saves.
+///
+/// While waiting in line, a CC is replaced by a newer value for the same
+/// controller on the same channel, so that automation catches up instead
+/// of falling behind, see [`coalesce`]. Realtime messages, such as
+/// clock, skip the line.
+///
+/// The pacing thread holds the lock on the [`Output`] while it writes a
+/// send out. Senders that give up when the lock is taken, such as
+/// [`Message::send`](crate::message::Message::send), may then drop their
+/// message; [`Output::send_blocking`] waits for the lock instead.
+/// ```
+/// use midi::connection::{ConnectionBuilder, throttle::Throttle};
+/// let port = ConnectionBuilder::new("USB MIDI Interface")
+///   .throttle(Throttle::din())
+///   .output(|_| {});
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Throttle {
+  pub bytes_per_second: u32,
+  /// Whether the wire carries running status, so that a repeated status
+  /// byte costs nothing. Either the output encodes it, see
+  /// [`ConnectionBuilder::running_status`], or the interface does.
+  pub running_status: bool,
+  /// Let a newer CC value replace one still waiting to be sent.
+  pub coalesce: bool,
+  /// Most sends waiting at once. When the line is full the oldest CC
+  /// waiting is dropped to make room, or the oldest send if there is none.
+  pub capacity: usize,
+}
+
+impl Throttle {
+  /// 31250 baud, 10 bits to the byte.
+  pub const DIN_BYTES_PER_SECOND: u32 = 3125;
+
+  /// A 5 pin DIN cable, with running status.
+  pub fn din() -> Self {
+    Self{ bytes_per_second: Self::DIN_BYTES_PER_SECOND, running_status: true, coalesce: true, capacity: 4096 }
+  }
+
+  /// Seconds `message` keeps the wire busy. `status` is the running
+  /// status before it, and is updated to the one after.
+  fn cost(&self, message: &[u8], status: &mut RunningStatus) -> f64 {
+    let bytes = match self.running_status {
+      true => message.iter().filter(|b| status.keep(**b)).count(),
+      false => message.len(),
+    };
+    bytes as f64 / f64::from(self.bytes_per_second.max(1))
+  }
+}
+
+impl Default for Throttle {
+  fn default() -> Self { Self::din() }
+}
+
+/// The channels and controllers of a send made up of coalescable CCs only.
+fn cc_keys(message: &[u8]) -> Option<Vec<(u8, u8)>> {
+  if message.is_empty() || !message.len().is_multiple_of(3) { return None }
+  message
+    .chunks(3)
+    .map(|cc| {
+      let coalescable = cc[0] & 0xf0 == CC && cc[1] < 120 && !ORDERED.contains(&cc[1]);
+      coalescable.then_some((cc[0], cc[1]))
+    })
+    .collect()
+}
+
+/// Replaces a CC waiting in `sends` with `message`, a newer value for
+/// the same controllers on the same channels. Only CCs at the back of the
+/// line are looked at, so that nothing queued after them, such as a note
+/// played while the sustain pedal is down, is reordered. Returns whether
+/// `message` was merged, or has to be queued.
+/// ```
+/// use std::collections::VecDeque;
+/// use midi::connection::throttle::coalesce;
+/// let mut sends = VecDeque::from([vec![0xB0, 64, 127], vec![0x90, 60, 100]]);
+/// // the pedal comes up after the note, and must stay there
+/// assert!(!coalesce(&mut sends, &[0xB0, 64, 0]));
+/// sends.push_back(vec![0xB0, 64, 0]);
+/// sends.push_back(vec![0xB0, 1, 20]);
+/// assert!(coalesce(&mut sends, &[0xB0, 64, 30]));
+/// assert_eq!(sends, [vec![0xB0, 64, 127], vec![0x90, 60, 100], vec![0xB0, 64, 30], vec![0xB0, 1, 20]]);
+/// ```
+pub fn coalesce(sends: &mut VecDeque<Vec<u8>>, message: &[u8]) -> bool {
+  let Some(keys) = cc_keys(message) else { return false };
+  for queued in sends.iter_mut().rev() {
+    match cc_keys(queued) {
+      Some(k) if k == keys => {
+        *queued = message.to_vec();
+        return true
+      },
+      Some(_) => continue,
+      None => return false,
+    }
+  }
+  false
+}
+
+struct Queue {
+  sends: VecDeque<Vec<u8>>,
+  /// When the wire is free for the next send.
+  free_at: Instant,
+  status: RunningStatus,
+  shutdown: bool,
+}
+
+struct Shared {
+  throttle: Throttle,
+  queue: Mutex<Queue>,
+  wake: Condvar,
+}
+
+/// The line of sends of a throttled [`Output`], and the thread writing them out.
+pub(super) struct Pacer {
+  shared: Arc<Shared>,
+}
+
+impl Pacer {
+  pub(super) fn new(throttle: Throttle, output: Weak<Mutex<Output>>) -> Self {
+    let queue = Queue{ sends: VecDeque::new(), free_at: Instant::now(), status: RunningStatus::new(), shutdown: false };
+    let shared = Arc::new(Shared{ throttle, queue: Mutex::new(queue), wake: Condvar::new() });
+    let pacing = shared.clone();
+    // not joined on drop: the thread may be holding the last reference to the output
+    thread::spawn(move || pacing.run(&output));
+    Self{ shared }
+  }
+
+  pub(super) fn throttle(&self) -> Throttle { self.shared.throttle }
+
+  pub(super) fn queued(&self) -> usize {
+    self.shared.queue.lock().map_or(0, |q| q.sends.len())
+  }
+
+  /// Realtime messages are sent by the caller right away, but still take up the wire.
+  pub(super) fn is_realtime(message: &[u8]) -> bool {
+    !message.is_empty() && message.iter().all(|b| *b >= 0xF8)
+  }
+
+  pub(super) fn spend(&self, message: &[u8]) {
+    let Ok(mut queue) = self.shared.queue.lock() else { return };
+    let mut status = queue.status;
+    let cost = Duration::from_secs_f64(self.shared.throttle.cost(message, &mut status));
+    queue.free_at = queue.free_at.max(Instant::now()) + cost;
+  }
+
+  pub(super) fn push(&self, message: &[u8]) -> Result<(), SendError> {
+    let Ok(mut queue) = self.shared.queue.lock() else { return Err(SendError::Other("throttle queue is poisoned")) };
+    if self.shared.throttle.coalesce && coalesce(&mut queue.sends, message) { return Ok(()) }
+    if queue.sends.len() >= self.shared.throttle.capacity.max(1) {
+      // automation is the first to go, as a newer value follows anyway
+      let oldest = queue.sends.iter().position(|s| cc_keys(s).is_some()).unwrap_or(0);
+      queue.sends.remove(oldest);
+    }
+    queue.sends.push_back(message.to_vec());
+    self.shared.wake.notify_one();
+    Ok(())
+  }
+}
+
+impl Drop for Pacer {
+  fn drop(&mut self) {
+    if let Ok(mut queue) = self.shared.queue.lock() { queue.shutdown = true }
+    self.shared.wake.notify_one();
+  }
+}
+
+impl Shared {
+  fn run(&self, output: &Weak<Mutex<Output>>) {
+    let Ok(mut queue) = self.queue.lock() else { return };
+    loop {
+      if queue.shutdown { return }
+      if queue.sends.is_empty() {
+        let Ok(q) = self.wake.wait(queue) else { return };
+        queue = q;
+        continue
+      }
+      // the send stays in line until the wire is free, so it can still be coalesced
+      let now = Instant::now();
+      if queue.free_at > now {
+        let wait = queue.free_at - now;
+        let Ok((q, _)) = self.wake.wait_timeout(queue, wait) else { return };
+        queue = q;
+        continue
+      }
+      let Some(message) = queue.sends.pop_front() else { continue };
+      let cost = Duration::from_secs_f64(self.throttle.cost(&message, &mut queue.status));
+      queue.free_at = now + cost;
+      drop(queue);
+
+      let Some(output) = output.upgrade() else { return };
+      // a failed send is dropped, the output is offline until it reconnects
+      if let Ok(mut o) = output.lock() { let _ = o.write(&message); }
+      drop(output);
+      let Ok(q) = self.queue.lock() else { return };
+      queue = q;
+    }
+  }
+}
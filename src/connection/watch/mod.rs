@@ -0,0 +1,157 @@
+use std::collections::BTreeSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, sync_channel, Receiver};
+use std::thread;
+use std::time::Duration;
+
+use midir::{MidiIO, MidiInput, MidiOutput};
+
+use crate::Arc;
+use super::DEFAULT_CLIENT_NAME;
+
+/// How often the port lists are polled if nothing else is asked for.
+pub const DEFAULT_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PortDirection {
+  Input,
+  Output,
+}
+
+/// A change in the set of MIDI ports visible to the system.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PortEvent {
+  Connected(PortDirection, String),
+  Disconnected(PortDirection, String),
+}
+
+impl PortEvent {
+  pub fn direction(&self) -> PortDirection {
+    match self {
+      Self::Connected(d, _) | Self::Disconnected(d, _) => *d
+    }
+  }
+
+  pub fn name(&self) -> &str {
+    match self {
+      Self::Connected(_, n) | Self::Disconnected(_, n) => n
+    }
+  }
+}
+
+/// Polls the input and output port lists on a background thread
+/// and reports every port that appears or disappears.
+///
+/// The ports present when the watcher starts are taken as the baseline
+/// and are not reported. Dropping the watcher stops the thread.
+/// ```
+/// use midi::connection::watch::{PortWatcher, DEFAULT_INTERVAL};
+/// if let Ok((_watcher, events)) = PortWatcher::channel(DEFAULT_INTERVAL) {
+///   while let Ok(event) = events.recv_timeout(DEFAULT_INTERVAL) {
+///     println!("{event:?}");
+///   }
+/// }
+/// ```
+pub struct PortWatcher {
+  run: Arc<AtomicBool>,
+}
+
+impl PortWatcher {
+  /// Starts polling every `interval`, calling `callback` for each change.
+  pub fn new<F>(interval: Duration, callback: F) -> Result<Self, String>
+    where F: FnMut(&PortEvent) + Send + 'static,
+  {
+    Self::with_client(DEFAULT_CLIENT_NAME, interval, callback)
+  }
+
+  /// Same as [`PortWatcher::new`], with the polling clients named `client_name`.
+  pub fn with_client<F>(client_name: &str, interval: Duration, mut callback: F) -> Result<Self, String>
+    where F: FnMut(&PortEvent) + Send + 'static,
+  {
+    let client_name = client_name.to_owned();
+    let run = Arc::new(AtomicBool::new(true));
+    let running = run.clone();
+    let (ready_tx, ready_rx) = sync_channel::<Result<(), String>>(1);
+
+    thread::spawn(move || {
+      let clients = match (MidiInput::new(&client_name), MidiOutput::new(&client_name)) {
+        (Ok(i), Ok(o)) => { let _ = ready_tx.send(Ok(())); (i, o) },
+        (Err(e), _) | (_, Err(e)) => {
+          let _ = ready_tx.send(Err(format!("could not create MIDI client: {}", e)));
+          return
+        }
+      };
+      let (input, output) = clients;
+      let mut inputs = port_set(&input);
+      let mut outputs = port_set(&output);
+
+      while running.load(Ordering::Acquire) {
+        thread::sleep(interval);
+        let now_inputs = port_set(&input);
+        let now_outputs = port_set(&output);
+        for event in diff(PortDirection::Input, &inputs, &now_inputs)
+          .chain(diff(PortDirection::Output, &outputs, &now_outputs)) {
+          if !running.load(Ordering::Acquire) { return }
+          callback(&event);
+        }
+        inputs = now_inputs;
+        outputs = now_outputs;
+      }
+    });
+
+    match ready_rx.recv() {
+      Ok(Ok(())) => Ok(Self{ run }),
+      Ok(Err(e)) => Err(e),
+      Err(_) => Err("port watcher thread exited during startup".to_owned())
+    }
+  }
+
+  /// Starts polling every `interval` and delivers the changes on a channel.
+  pub fn channel(interval: Duration) -> Result<(Self, Receiver<PortEvent>), String> {
+    let (tx, rx) = channel();
+    let watcher = Self::new(interval, move |event| { let _ = tx.send(event.clone()); })?;
+    Ok((watcher, rx))
+  }
+
+  /// Stops the background thread after its current poll.
+  pub fn stop(&self) {
+    self.run.store(false, Ordering::Release);
+  }
+}
+
+impl Drop for PortWatcher {
+  fn drop(&mut self) {
+    // The thread is not joined, since the watcher may be dropped
+    // from within its own callback.
+    self.stop();
+  }
+}
+
+/// Returns the names of all ports currently visible to `io`.
+/// Ports that vanish while being listed are skipped.
+pub(crate) fn port_names<M: MidiIO>(io: &M) -> Vec<String> {
+  io
+    .ports()
+    .iter()
+    .filter_map(|p| io.port_name(p).ok())
+    .collect()
+}
+
+fn port_set<M: MidiIO>(io: &M) -> BTreeSet<String> {
+  port_names(io).into_iter().collect()
+}
+
+fn diff<'a>(
+  dir: PortDirection,
+  before: &'a BTreeSet<String>,
+  after: &'a BTreeSet<String>
+) -> impl Iterator<Item = PortEvent> + 'a {
+  before
+    .difference(after)
+    .map(move |n| PortEvent::Disconnected(dir, n.clone()))
+    .chain(
+      after
+        .difference(before)
+        .map(move |n| PortEvent::Connected(dir, n.clone()))
+    )
+}
@@ -23,6 +23,10 @@ pub mod transport {
   pub const STOP:             u8 = 0b11111100;
   pub const CONTINUE:         u8 = 0b11111011;
   pub const CLOCK:            u8 = 0b11111000;
+  pub const ACTIVE_SENSING:   u8 = 0b11111110;
+  pub const RESET:            u8 = 0b11111111;
+  /// MIDI clocks per quarter note.
+  pub const PPQ:              u8 = 24;
 }
 
 pub mod message {
@@ -44,11 +48,121 @@ pub mod message {
   pub const RPN_VAL_LSB:      u8 = NRPN_VAL_LSB;
   // Control Change
   pub const CC:               u8 = 0xB0;
+  // Polyphonic Key Pressure (Aftertouch)
+  pub const POLY_PRESSURE:    u8 = 0xA0;
+  // Program Change
+  pub const PROGRAM_CHANGE:   u8 = 0xC0;
+  // Channel Pressure (Aftertouch)
+  pub const CHANNEL_PRESSURE: u8 = 0xD0;
   /// Pitchbend
   pub const PB:               u8 = 0xE0;
+  // MIDI Time Code Quarter Frame
+  pub const TIME_CODE:        u8 = 0xF1;
+  // Song Position Pointer
+  pub const SONG_POSITION:    u8 = 0xF2;
+  // Song Select
+  pub const SONG_SELECT:      u8 = 0xF3;
+  // Tune Request
+  pub const TUNE_REQUEST:     u8 = 0xF6;
   // SysEx Begin sequence
   pub const SYSEX_BEGIN:      u8 = 0xF0;
   // SysEx End sequence
   pub const SYSEX_END:        u8 = 0xF7;
 }
 
+pub mod channel_mode {
+  // Mutes everything at once, release and reverb tails included
+  pub const ALL_SOUND_OFF:    u8 = 120;
+  pub const RESET_ALL_CONTROLLERS: u8 = 121;
+  // Value 0 disconnects the keyboard from the sound engine, 127 reconnects it
+  pub const LOCAL_CONTROL:    u8 = 122;
+  pub const ALL_NOTES_OFF:    u8 = 123;
+  pub const OMNI_OFF:         u8 = 124;
+  pub const OMNI_ON:          u8 = 125;
+  // Value is the number of channels, 0 meaning as many as there are voices
+  pub const MONO_ON:          u8 = 126;
+  pub const POLY_ON:          u8 = 127;
+}
+
+pub mod mpe {
+  // CC number carrying the third dimension of control, "timbre" or "slide"
+  pub const TIMBRE:           u8 = 74;
+  // Default pitch bend range of member channels, in semitones
+  pub const MEMBER_BEND_RANGE: u8 = 48;
+  // Default pitch bend range of master channels, in semitones
+  pub const MASTER_BEND_RANGE: u8 = 2;
+}
+
+pub mod sysex {
+  // Universal Non-Real Time SysEx ID
+  pub const NON_REALTIME:     u8 = 0x7E;
+  // Universal Real Time SysEx ID
+  pub const REALTIME:         u8 = 0x7F;
+  // Device ID addressing every device
+  pub const ALL_CALL:         u8 = 0x7F;
+
+  // Sub-ID #1 values
+  pub const SAMPLE_DUMP_HEADER: u8 = 0x01;
+  pub const DEVICE_CONTROL:   u8 = 0x04;
+  pub const GENERAL_INFO:     u8 = 0x06;
+  pub const TUNING_STANDARD:  u8 = 0x08;
+  pub const GENERAL_MIDI:     u8 = 0x09;
+  // Sub-ID #1 values of the handshaking messages
+  pub const HANDSHAKE_WAIT:   u8 = 0x7C;
+  pub const HANDSHAKE_CANCEL: u8 = 0x7D;
+  pub const HANDSHAKE_NAK:    u8 = 0x7E;
+  pub const HANDSHAKE_ACK:    u8 = 0x7F;
+
+  // Sub-ID #2 values of General Information
+  pub const IDENTITY_REQUEST: u8 = 0x01;
+  pub const IDENTITY_REPLY:   u8 = 0x02;
+  // Sub-ID #2 values of General MIDI
+  pub const GM1_ON:           u8 = 0x01;
+  pub const GM_OFF:           u8 = 0x02;
+  pub const GM2_ON:           u8 = 0x03;
+  // Sub-ID #2 values of Device Control
+  pub const MASTER_VOLUME:    u8 = 0x01;
+  pub const MASTER_BALANCE:   u8 = 0x02;
+  pub const FINE_TUNING:      u8 = 0x03;
+  pub const COARSE_TUNING:    u8 = 0x04;
+  // Sub-ID #2 values of MIDI Tuning Standard
+  pub const TUNING_DUMP_REQUEST: u8 = 0x00;
+  pub const TUNING_DUMP:      u8 = 0x01;
+  pub const TUNING_NOTE_CHANGE: u8 = 0x02;
+}
+
+pub mod ci {
+  // Universal SysEx Sub-ID #1 of every MIDI-CI message
+  pub const SUB_ID:           u8 = 0x0D;
+  // Message format version sent by this crate (MIDI-CI 1.2)
+  pub const VERSION:          u8 = 0x02;
+  // Destination MUID of messages meant for everyone
+  pub const BROADCAST:        u32 = 0x0FFF_FFFF;
+  // Device ID addressing the whole function block
+  pub const FUNCTION_BLOCK:   u8 = 0x7F;
+  // Device ID addressing the whole group
+  pub const GROUP:            u8 = 0x7E;
+
+  // Sub-ID #2 values
+  pub const PROFILE_INQUIRY:  u8 = 0x20;
+  pub const PROFILE_REPLY:    u8 = 0x21;
+  pub const PROFILE_ON:       u8 = 0x22;
+  pub const PROFILE_OFF:      u8 = 0x23;
+  pub const PROFILE_ENABLED:  u8 = 0x24;
+  pub const PROFILE_DISABLED: u8 = 0x25;
+  pub const PE_CAPABILITY:    u8 = 0x30;
+  pub const PE_CAPABILITY_REPLY: u8 = 0x31;
+  pub const PE_GET:           u8 = 0x34;
+  pub const PE_GET_REPLY:     u8 = 0x35;
+  pub const PE_SET:           u8 = 0x36;
+  pub const PE_SET_REPLY:     u8 = 0x37;
+  pub const DISCOVERY:        u8 = 0x70;
+  pub const DISCOVERY_REPLY:  u8 = 0x71;
+  pub const INVALIDATE_MUID:  u8 = 0x7E;
+  pub const ACK:              u8 = 0x7D;
+  pub const NAK:              u8 = 0x7F;
+
+  // Capability Inquiry Category bits, sent in discovery
+  pub const CATEGORY_PROFILES: u8 = 0x04;
+  pub const CATEGORY_PROPERTY_EXCHANGE: u8 = 0x08;
+}
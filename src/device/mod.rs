@@ -0,0 +1,270 @@
+use std::{collections::BTreeMap, fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+  connection::Output,
+  librarian::DumpDefinition,
+  message::{
+    cc::Cc,
+    cc14::Cc14,
+    nrpn::Nrpn,
+    rpn::{Rpn, RpnKind},
+    sysex::{checksum::ChecksumKind, template::{Part, SysExTemplate, Token}},
+    Message,
+    MessageKind,
+  },
+  util::Channel,
+  Arc,
+  Mutex,
+};
+
+/// An NRPN address, written either as one 14 bit number or as `[msb, lsb]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum NrpnAddress {
+  Number(u16),
+  Pair(u8, u8),
+}
+
+impl NrpnAddress {
+  pub fn split(&self) -> (u8, u8) {
+    match *self {
+      Self::Number(n) => ((n >> 7) as u8 & 0x7f, n as u8 & 0x7f),
+      Self::Pair(msb, lsb) => (msb, lsb),
+    }
+  }
+}
+
+/// Where a parameter lives on the device.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Target {
+  Cc(u8),
+  /// 14 bit CC: MSB on the given controller, LSB on the one 32 above it.
+  Cc14(u8),
+  Nrpn(NrpnAddress),
+  Rpn(u8),
+  /// The value goes in the template's `{v}` tokens.
+  SysEx { template: SysExTemplate, checksum: ChecksumKind, checksum_from: usize },
+}
+
+impl Target {
+  /// Largest value the message can carry.
+  pub fn max(&self) -> u16 {
+    match self {
+      Self::Cc(_) => 0x7f,
+      Self::Cc14(_) | Self::Nrpn(_) | Self::Rpn(_) => 0x3fff,
+      Self::SysEx{ template, .. } => {
+        let fourteen_bit = template
+          .tokens()
+          .iter()
+          .any(|t| matches!(t, Token::Value{ part: Part::Msb, .. }));
+        if fourteen_bit { 0x3fff } else { 0x7f }
+      }
+    }
+  }
+}
+
+/// The way a parameter is written in a definition file. Exactly one
+/// of `cc`, `cc14`, `nrpn`, `rpn` and `sysex` has to be given.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+struct ParameterFile {
+  cc: Option<u8>,
+  cc14: Option<u8>,
+  nrpn: Option<NrpnAddress>,
+  rpn: Option<u8>,
+  sysex: Option<SysExTemplate>,
+  #[serde(default)]
+  checksum: ChecksumKind,
+  #[serde(default)]
+  checksum_from: usize,
+  min: Option<u16>,
+  max: Option<u16>,
+  #[serde(default)]
+  unit: String,
+}
+
+/// A named, ranged device parameter.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(try_from = "ParameterFile", into = "ParameterFile")]
+pub struct Parameter {
+  pub target: Target,
+  /// Raw value sent for 0.0.
+  pub min: u16,
+  /// Raw value sent for 1.0.
+  pub max: u16,
+  /// Unit of the raw value, for display only.
+  pub unit: String,
+}
+
+impl Parameter {
+  /// Maps `normalized`, 0.0 - 1.0, onto the parameter's range.
+  pub fn scale(&self, normalized: f32) -> u16 {
+    let span = f32::from(self.max) - f32::from(self.min);
+    (f32::from(self.min) + normalized.clamp(0.0, 1.0) * span).round() as u16
+  }
+
+  /// Returns the bytes that set the parameter to the raw `value` on `ch`.
+  pub fn to_bytes(&self, value: u16, ch: Channel) -> Result<Vec<u8>, String> {
+    let lo = self.min.min(self.max);
+    let hi = self.min.max(self.max);
+    if !(lo..=hi).contains(&value) {
+      return Err(format!("Value {value} is outside of {lo} - {hi}"))
+    }
+    let msb = (value >> 7) as u8 & 0x7f;
+    let lsb = value as u8 & 0x7f;
+    let bytes = match &self.target {
+      Target::Cc(addr) => checked(Cc{ addr: *addr, val: value as u8 }, ch)?,
+      Target::Cc14(addr) => checked(Cc14::new(*addr, value), ch)?,
+      Target::Nrpn(addr) => checked(Nrpn{ addr: addr.split(), val: (msb, lsb) }, ch)?,
+      Target::Rpn(addr) => {
+        let addr = RpnKind::try_from(*addr).map_err(|e| e.to_string())?;
+        checked(Rpn{ addr, val: (msb, lsb) }, ch)?
+      },
+      Target::SysEx{ template, checksum, checksum_from } => {
+        template.render(&[("v", value)], *checksum, *checksum_from)?
+      },
+    };
+    Ok(bytes)
+  }
+}
+
+fn checked<T: MessageKind>(kind: T, ch: Channel) -> Result<Vec<u8>, String> {
+  Ok(Message::new(kind).map_err(|e| e.to_string())?.to_bytes(ch))
+}
+
+impl TryFrom<ParameterFile> for Parameter {
+  type Error = String;
+  fn try_from(p: ParameterFile) -> Result<Self, Self::Error> {
+    let targets = [
+      p.cc.map(Target::Cc),
+      p.cc14.map(Target::Cc14),
+      p.nrpn.map(Target::Nrpn),
+      p.rpn.map(Target::Rpn),
+      p.sysex.map(|template| Target::SysEx{ template, checksum: p.checksum, checksum_from: p.checksum_from }),
+    ];
+    let mut targets = targets.into_iter().flatten();
+    let target = match (targets.next(), targets.next()) {
+      (Some(target), None) => target,
+      (None, _) => return Err("a parameter needs one of cc, cc14, nrpn, rpn or sysex".to_owned()),
+      (Some(_), Some(_)) => return Err("a parameter can only have one of cc, cc14, nrpn, rpn or sysex".to_owned()),
+    };
+    if matches!(target, Target::Cc14(addr) if addr >= 32) {
+      return Err("cc14 takes the MSB controller, 0 - 31".to_owned())
+    }
+    let max = p.max.unwrap_or(target.max());
+    Ok(Self{ min: p.min.unwrap_or(0), max, unit: p.unit, target })
+  }
+}
+
+impl From<Parameter> for ParameterFile {
+  fn from(p: Parameter) -> Self {
+    let mut file = ParameterFile{ min: Some(p.min), max: Some(p.max), unit: p.unit, ..Default::default() };
+    match p.target {
+      Target::Cc(addr) => file.cc = Some(addr),
+      Target::Cc14(addr) => file.cc14 = Some(addr),
+      Target::Nrpn(addr) => file.nrpn = Some(addr),
+      Target::Rpn(addr) => file.rpn = Some(addr),
+      Target::SysEx{ template, checksum, checksum_from } => {
+        file.sysex = Some(template);
+        file.checksum = checksum;
+        file.checksum_from = checksum_from;
+      },
+    }
+    file
+  }
+}
+
+/// Everything there is to know about controlling one device model.
+/// ```
+/// use midi::device::DeviceDefinition;
+/// use midi::util::Channel;
+/// let synth = DeviceDefinition::from_toml(r#"
+///   name = "Example synth"
+///
+///   [params."filter.cutoff"]
+///   cc14 = 19
+///
+///   [params."osc.shape"]
+///   nrpn = [0, 8]
+///   max = 4
+///
+///   [params."reverb.level"]
+///   sysex = "F0 41 10 42 12 40 01 30 {v} {checksum} F7"
+///   checksum = "roland"
+///   checksum_from = 5
+///   unit = "dB"
+/// "#).unwrap();
+/// let cutoff = synth.params["filter.cutoff"].to_bytes(0x2001, Channel(0)).unwrap();
+/// assert_eq!(cutoff, vec![0xB0, 19, 0x40, 0xB0, 51, 0x01]);
+/// assert_eq!(synth.params["osc.shape"].scale(0.5), 2);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct DeviceDefinition {
+  pub name: String,
+  /// Channel messages are sent on, 0 - 15.
+  #[serde(default)]
+  pub channel: u8,
+  #[serde(default)]
+  pub params: BTreeMap<String, Parameter>,
+  /// Patch dumps the device supports, for the [`librarian`](crate::librarian).
+  #[serde(default)]
+  pub dumps: BTreeMap<String, DumpDefinition>,
+}
+
+impl DeviceDefinition {
+  pub fn from_toml(definition: &str) -> Result<Self, String> {
+    let definition: Self = toml::from_str(definition).map_err(|e| format!("invalid device definition: {e}"))?;
+    Channel::new(definition.channel)?;
+    Ok(definition)
+  }
+
+  pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+    let path = path.as_ref();
+    let definition = fs::read_to_string(path)
+      .map_err(|e| format!("could not read {}: {e}", path.display()))?;
+    Self::from_toml(&definition)
+  }
+
+  pub fn param(&self, name: &str) -> Result<&Parameter, String> {
+    self.params.get(name).ok_or_else(|| format!("{} has no parameter {name}", self.name))
+  }
+}
+
+/// A [`DeviceDefinition`] bound to an output.
+/// ```no_run
+/// use midi::{connection::Output, device::{Device, DeviceDefinition}};
+/// let output = Output::new("Take 5", |_| {}).unwrap();
+/// let take5 = Device::new(DeviceDefinition::load("take5.toml").unwrap(), output);
+/// take5.set("filter.cutoff", 0.73).unwrap();
+/// ```
+pub struct Device {
+  definition: DeviceDefinition,
+  port: Arc<Mutex<Output>>,
+  ch: Channel,
+}
+
+impl Device {
+  pub fn new(definition: DeviceDefinition, port: Arc<Mutex<Output>>) -> Self {
+    let ch = Channel(definition.channel & 0x0f);
+    Self{ definition, port, ch }
+  }
+
+  pub fn definition(&self) -> &DeviceDefinition { &self.definition }
+
+  /// Sends on `ch` instead of the definition's channel.
+  pub fn set_channel(&mut self, ch: Channel) { self.ch = ch }
+
+  /// Sets `param` to `normalized`, 0.0 - 1.0 across its range.
+  pub fn set(&self, param: &str, normalized: f32) -> Result<(), String> {
+    let p = self.definition.param(param)?;
+    // waits for the port, as a dropped parameter change would leave the device out of step
+    Output::send_blocking(&self.port, &p.to_bytes(p.scale(normalized), self.ch)?)
+  }
+
+  /// Sets `param` to the raw device `value`.
+  pub fn set_raw(&self, param: &str, value: u16) -> Result<(), String> {
+    let bytes = self.definition.param(param)?.to_bytes(value, self.ch)?;
+    Output::send_blocking(&self.port, &bytes)
+  }
+}
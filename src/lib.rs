@@ -24,6 +24,43 @@ pub mod note;
 pub mod transport;
 pub mod message;
 pub mod util;
+/// MIDI Polyphonic Expression.
+///
+/// An MPE zone is a master channel plus a range of member channels. Every
+/// sounding note gets a member channel of its own, so that pitch bend,
+/// timbre (CC 74) and channel pressure apply to that note alone.
+pub mod mpe;
+/// Sends messages later without blocking the caller, from a timer thread.
+///
+/// Mostly used for note offs, see [`scheduler::Scheduler::play_note`].
+pub mod scheduler;
+/// LFOs and envelopes, streamed as CC, 14 bit CC, NRPN or pitch bend
+/// from a [`scheduler::Scheduler`].
+pub mod modulation;
+/// Filters, transforms and routes events between inputs and outputs,
+/// through a graph of [`router::node::Node`]s.
+pub mod router;
+/// Microtonal tuning from Scala `.scl` and `.kbm` files, sent as MIDI
+/// Tuning Standard SysEx or as pitch bend on a channel per note.
+pub mod tuning;
+/// Universal MIDI Packets, as defined by MIDI 2.0.
+///
+/// Encodes and decodes every UMP message type, and translates between
+/// UMP and MIDI 1.0 byte streams in [`ump::translate`].
+pub mod ump;
+/// MIDI Capability Inquiry over Universal SysEx.
+///
+/// An [`ci::Endpoint`] takes part in discovery, answers profile and
+/// property exchange requests, and builds requests of its own.
+pub mod ci;
+/// Fetches, stores and restores SysEx patch dumps, driven by
+/// per-device [`librarian::DumpDefinition`] files.
+#[cfg(feature = "definitions")]
+pub mod librarian;
+/// Named device parameters, mapped to CC, 14 bit CC, NRPN, RPN or SysEx
+/// by [`device::DeviceDefinition`] files.
+#[cfg(feature = "definitions")]
+pub mod device;
 // pub mod sequencer;
 /// Contains bitmasks and utility numbers for identifying and sending MIDI messages
 /// ```
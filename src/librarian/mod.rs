@@ -0,0 +1,229 @@
+use std::{
+  collections::BTreeMap,
+  fs,
+  path::Path,
+  time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use crossbeam_channel::Receiver;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+  connection::Output,
+  message::{
+    event::{MidiEvent, TimedEvent},
+    sysex::{
+      checksum::{Checksum, ChecksumKind},
+      sender::{split_messages, ChunkedSender},
+      template::SysExTemplate,
+    },
+  },
+  Arc,
+  Mutex,
+};
+
+fn default_timeout() -> u64 { 2000 }
+fn default_idle() -> u64 { 300 }
+
+/// How to request, recognise and check one kind of dump from one kind
+/// of device. Usually loaded from a TOML file:
+/// ```
+/// use midi::librarian::DumpDefinition;
+/// let definition = DumpDefinition::from_toml(r#"
+///   name = "Roland JX-08 tone"
+///   request = "F0 41 10 00 00 00 6B 11 {program} 00 00 00 00 00 00 {checksum} F7"
+///   reply = "F0 41 10 00 00 00 6B 12"
+///   frames = 1
+///   checksum = "roland"
+///   checksum_from = 8
+/// "#).unwrap();
+/// assert_eq!(definition.timeout_ms, 2000);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct DumpDefinition {
+  pub name: String,
+  /// The dump request. Its `{tokens}` are filled in by [`Librarian::fetch`].
+  pub request: SysExTemplate,
+  /// Frames of the dump start like this. Any SysEx is taken if not set.
+  #[serde(default)]
+  pub reply: Option<SysExTemplate>,
+  /// Number of frames in a complete dump. If not set, the dump is
+  /// complete once no frame has arrived for `idle_ms`.
+  #[serde(default)]
+  pub frames: Option<usize>,
+  /// How long to wait for the whole dump.
+  #[serde(default = "default_timeout")]
+  pub timeout_ms: u64,
+  #[serde(default = "default_idle")]
+  pub idle_ms: u64,
+  /// Checksum of the request, and of every frame of the dump. A frame's
+  /// checksum is the byte before its `F7`.
+  #[serde(default)]
+  pub checksum: ChecksumKind,
+  /// First byte covered by the checksum.
+  #[serde(default)]
+  pub checksum_from: usize,
+  /// Pause after every frame when sending a dump back.
+  #[serde(default)]
+  pub send_delay_ms: u64,
+}
+
+impl DumpDefinition {
+  pub fn from_toml(definition: &str) -> Result<Self, String> {
+    toml::from_str(definition).map_err(|e| format!("invalid dump definition: {e}"))
+  }
+
+  pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+    let path = path.as_ref();
+    let definition = fs::read_to_string(path)
+      .map_err(|e| format!("could not read {}: {e}", path.display()))?;
+    Self::from_toml(&definition)
+  }
+
+  /// Returns `true` if `frame` belongs to this kind of dump.
+  pub fn accepts(&self, frame: &[u8]) -> bool {
+    self.reply.as_ref().is_none_or(|r| r.matches(frame))
+  }
+
+  /// Checks the checksum of one frame.
+  pub fn verify(&self, frame: &[u8]) -> bool {
+    if self.checksum == ChecksumKind::None { return true }
+    match frame.len().checked_sub(2) {
+      Some(sum) if sum >= self.checksum_from => {
+        self.checksum.verify(&frame[self.checksum_from..sum], frame[sum])
+      },
+      _ => false
+    }
+  }
+}
+
+/// What is known about a stored dump, kept next to it in a `.toml` file.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Deserialize, Serialize)]
+pub struct Metadata {
+  /// Name of the [`DumpDefinition`] the dump was fetched with.
+  pub definition: String,
+  /// Free text, for the patch name and such.
+  #[serde(default)]
+  pub name: String,
+  /// When the dump was received, in seconds since the Unix epoch.
+  pub received: u64,
+  pub frames: usize,
+  pub bytes: usize,
+  /// Values the request was filled in with.
+  #[serde(default)]
+  pub values: BTreeMap<String, u16>,
+}
+
+/// A complete dump, one or more SysEx frames.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dump {
+  pub frames: Vec<Vec<u8>>,
+  pub metadata: Metadata,
+}
+
+impl Dump {
+  /// All frames back to back, as in a `.syx` file.
+  pub fn to_bytes(&self) -> Vec<u8> { self.frames.concat() }
+
+  /// Writes the dump to `path` with the `.syx` extension, and its
+  /// metadata next to it with the `.toml` extension.
+  pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
+    let syx = path.as_ref().with_extension("syx");
+    let toml = syx.with_extension("toml");
+    let metadata = toml::to_string(&self.metadata).map_err(|e| format!("could not write metadata: {e}"))?;
+    fs::write(&syx, self.to_bytes()).map_err(|e| format!("could not write {}: {e}", syx.display()))?;
+    fs::write(&toml, metadata).map_err(|e| format!("could not write {}: {e}", toml.display()))
+  }
+
+  /// Reads a `.syx` file, and its metadata if there is any.
+  pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+    let syx = path.as_ref().with_extension("syx");
+    let bytes = fs::read(&syx).map_err(|e| format!("could not read {}: {e}", syx.display()))?;
+    let frames: Vec<Vec<u8>> = split_messages(&bytes).into_iter().map(<[u8]>::to_vec).collect();
+    let metadata = match fs::read_to_string(syx.with_extension("toml")) {
+      Ok(m) => toml::from_str(&m).map_err(|e| format!("invalid metadata for {}: {e}", syx.display()))?,
+      Err(_) => Metadata{ frames: frames.len(), bytes: bytes.len(), ..Default::default() },
+    };
+    Ok(Self{ frames, metadata })
+  }
+}
+
+/// Fetches dumps from a device and sends them back.
+///
+/// Incoming SysEx is read from a [`Receiver`], as returned by
+/// [`ConnectionBuilder::receiver`](crate::connection::ConnectionBuilder::receiver).
+/// ```no_run
+/// use midi::{connection::{ConnectionBuilder, Output, receiver::Overflow}, librarian::{DumpDefinition, Librarian}};
+/// let output = Output::new("JX-08", |_| {}).unwrap();
+/// let (_input, events) = ConnectionBuilder::new("JX-08").receiver(256, Overflow::DropNewest).unwrap();
+/// let tone = DumpDefinition::load("jx08-tone.toml").unwrap();
+/// let librarian = Librarian::new(output, events);
+/// let dump = librarian.fetch(&tone, &[("program", 12)]).unwrap();
+/// dump.save("backup/jx08-tone-12").unwrap();
+/// librarian.restore(&tone, &dump).unwrap();
+/// ```
+pub struct Librarian {
+  output: Arc<Mutex<Output>>,
+  events: Receiver<TimedEvent>,
+}
+
+impl Librarian {
+  pub fn new(output: Arc<Mutex<Output>>, events: Receiver<TimedEvent>) -> Self {
+    Self{ output, events }
+  }
+
+  /// Sends the dump request with `values` filled in, and waits for the dump.
+  pub fn fetch(&self, definition: &DumpDefinition, values: &[(&str, u16)]) -> Result<Dump, String> {
+    let request = definition.request.render(values, definition.checksum, definition.checksum_from)?;
+    // anything already queued is not a reply to this request
+    while self.events.try_recv().is_ok() {}
+    Output::send_blocking(&self.output, &request)?;
+    let mut dump = self.receive(definition)?;
+    dump.metadata.values = values.iter().map(|(k, v)| (k.to_string(), *v)).collect();
+    Ok(dump)
+  }
+
+  /// Waits for a dump sent from the device's front panel, without requesting one.
+  pub fn receive(&self, definition: &DumpDefinition) -> Result<Dump, String> {
+    let deadline = Instant::now() + Duration::from_millis(definition.timeout_ms);
+    let idle = Duration::from_millis(definition.idle_ms);
+    let mut frames: Vec<Vec<u8>> = vec![];
+
+    while definition.frames.is_none_or(|n| frames.len() < n) {
+      let wait = match (frames.is_empty(), definition.frames) {
+        (false, None) => deadline.min(Instant::now() + idle),
+        _ => deadline,
+      };
+      let Ok(timed) = self.events.recv_deadline(wait) else { break };
+      let MidiEvent::SysEx(frame) = timed.event else { continue };
+      if !definition.accepts(&frame) { continue }
+      if !definition.verify(&frame) {
+        return Err(format!("frame {} of {} failed its checksum", frames.len() + 1, definition.name))
+      }
+      frames.push(frame);
+    }
+
+    match (frames.len(), definition.frames) {
+      (0, _) => return Err(format!("no {} dump received", definition.name)),
+      (got, Some(n)) if got < n => return Err(format!("{} dump incomplete, got {got} of {n} frames", definition.name)),
+      _ => ()
+    }
+    let metadata = Metadata{
+      definition: definition.name.clone(),
+      received: SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs()),
+      frames: frames.len(),
+      bytes: frames.iter().map(Vec::len).sum(),
+      ..Default::default()
+    };
+    Ok(Dump{ frames, metadata })
+  }
+
+  /// Sends `dump` back to the device, frame by frame.
+  pub fn restore(&self, definition: &DumpDefinition, dump: &Dump) -> Result<(), String> {
+    if let Some(i) = dump.frames.iter().position(|f| !definition.verify(f)) {
+      return Err(format!("frame {} of the dump failed its checksum", i + 1))
+    }
+    ChunkedSender::new(usize::MAX, Duration::from_millis(definition.send_delay_ms))
+      .send(&self.output, &dump.to_bytes())
+  }
+}
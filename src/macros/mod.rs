@@ -34,6 +34,13 @@ macro_rules! midi {
     $crate::midi! {$($rest)*}
   };
   
+  (chord on: $n:ident, $v:literal, $p:ident, $c:ident; $($rest:tt)*) => {
+    for note in $n.iter() {
+      $crate::midi! {note on: *note, $v, $p, $c;}
+    }
+    $crate::midi! {$($rest)*}
+  };
+  
   (chord on: [$($n:expr),*], [$($v:expr),*], $p:ident, $c:ident; $($rest:tt)*) => {
     $(
       $crate::midi! {note on: $n, $v, $p, $c;}
@@ -122,18 +129,11 @@ $crate::midi! {nrpn: ($addr0, $addr1), ($val.0, $val.1), $p, $c; $($rest)*}
 
   // SYSEX=---------------------------------------------------------
 
-  // (sysex: $data:expr, $p:ident, $c:ident; $($rest:tt)*) => {
-  //   let cow = std::borrow::Cow::Borrowed($data);
-  //   $crate::message::Message::new(
-  //     $crate::message::sysex::SysEx{data: cow}
-  //   ).expect("could not create sysex message").send(&$p, $c);
-  //   $crate::midi! {$($rest)*}
-  // };
-
-  // (sysex: [$($data:expr),*], $p:ident, $c:ident; $($rest:tt)*) => {
-  //   let cow = std::borrow::Cow::borrowed(&[$($data),*]);
-  //   $crate::midi! {sysex: cow, p, c; $($rest)*}
-  // };
+  (sysex: $data:expr, $p:ident, $c:ident; $($rest:tt)*) => {
+    $crate::message::Message::sysex(std::convert::AsRef::<[u8]>::as_ref(&$data))
+      .expect("could not create sysex message").send(&$p, $c);
+    $crate::midi! {$($rest)*}
+  };
 
   // WAIT=---------------------------------------------------------
   
@@ -1,4 +1,5 @@
 use super::*;
+use crate::consts::channel_mode::ALL_SOUND_OFF;
 
 pub struct Cc { pub addr: u8, pub val: u8 }
 
@@ -7,7 +8,8 @@ impl MessageKind for Cc {
       vec![CC|ch, self.addr, self.val]
   }
   
-  fn validate_address(&self) -> bool { self.addr < 128 }
+  /// Controllers 120 - 127 are Channel Mode messages, see [`ChannelMode`](super::channel_mode::ChannelMode).
+  fn validate_address(&self) -> bool { self.addr < ALL_SOUND_OFF }
   fn validate_value(&self) -> bool { self.val < 128 }
   fn repr(&self) -> String { format!("{}", self.val) }
   fn repr_addr(&self) -> String { format!("{}", self.addr) }
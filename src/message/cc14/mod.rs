@@ -0,0 +1,183 @@
+use super::*;
+
+use crate::message::event::MidiEvent;
+
+/// A 14 bit controller, sent as a pair of CCs: the MSB on controller
+/// `addr`, 0 - 31, and the LSB on controller `addr + 32`.
+#[derive(Clone, Copy, Debug)]
+pub struct Cc14 {
+  pub addr: u8,
+  pub val: u16,
+  /// Leave out the LSB when it is the same as the one sent last.
+  pub lsb_on_change: bool,
+  sent_lsb: Option<u8>,
+}
+
+impl Cc14 {
+  pub const MAX: u16 = 0x3fff;
+  /// Offset from a controller's MSB to its LSB.
+  pub const LSB_OFFSET: u8 = 32;
+
+  pub fn new(addr: u8, val: u16) -> Self {
+    Self{ addr, val, lsb_on_change: false, sent_lsb: None }
+  }
+}
+
+impl MessageKind for Cc14 {
+  fn to_bytes(&self, ch: Channel) -> Vec<u8> {
+    let (msb, lsb) = ((self.val >> 7) as u8 & 0x7f, self.val as u8 & 0x7f);
+    let mut msg = vec![CC|ch, self.addr, msb];
+    if !(self.lsb_on_change && self.sent_lsb == Some(lsb)) {
+      msg.extend([CC|ch, self.addr + Self::LSB_OFFSET, lsb]);
+    }
+    msg
+  }
+
+  #[inline]
+  fn validate_address(&self) -> bool { self.addr < Self::LSB_OFFSET }
+
+  #[inline]
+  fn validate_value(&self) -> bool { self.val <= Self::MAX }
+
+  #[inline]
+  fn repr(&self) -> String { format!("{}", self.val) }
+
+  #[inline]
+  fn repr_addr(&self) -> String { format!("{}", self.addr) }
+}
+
+impl FourteenBit for Cc14 {
+  fn split(num: u16) -> Result<(u8, u8), FourteenBitError> {
+    if num & 0b1100_0000_0000_0000 != 0 {
+      return Err(FourteenBitError::Overflow(format!("Num {num} bigger than {}", Self::MAX)))
+    }
+    Ok(((num >> 7) as u8, (num & 0b0111_1111) as u8))
+  }
+}
+
+impl Message<Cc14> {
+  /// ```
+  /// use midi::{message::Message, util::Channel};
+  /// let modwheel = Message::cc14(1, 0x2001).unwrap();
+  /// assert_eq!(modwheel.to_bytes(Channel(0)), vec![0xB0, 1, 0x40, 0xB0, 33, 0x01]);
+  /// ```
+  pub fn cc14(addr: u8, val: u16) -> Result<Message<Cc14>, MidiMessageError> { Message::new(Cc14::new(addr, val)) }
+
+  /// Only send the LSB when it differs from the one last sent with
+  /// [`Message::send_value`]. Receivers that follow the spec reset the
+  /// LSB to 0 on every MSB, so this is meant for devices that do not.
+  /// ```
+  /// use midi::{message::Message, util::Channel};
+  /// let mut coarse = Message::cc14(7, 0x0100).unwrap().lsb_on_change(true);
+  /// coarse.mark_sent();
+  /// coarse.update_value(0x0200).unwrap();
+  /// assert_eq!(coarse.to_bytes(Channel(0)), vec![0xB0, 7, 0x04]);
+  /// ```
+  pub fn lsb_on_change(mut self, on: bool) -> Self {
+    self.kind.lsb_on_change = on;
+    self
+  }
+
+  pub fn update_value(&mut self, val: u16) -> Result<(), String> {
+    if val > Cc14::MAX {
+      return Err(format!("Too big a value: {val}"))
+    }
+    self.kind.val = val;
+    Ok(())
+  }
+
+  pub fn update(&mut self, addr: u8, val: u16) -> Result<(), String> {
+    if addr >= Cc14::LSB_OFFSET {
+      return Err(format!("Not a 14 bit controller: {addr}"))
+    }
+    self.update_value(val)?;
+    self.kind.addr = addr;
+    self.kind.sent_lsb = None;
+    Ok(())
+  }
+
+  /// Remembers the current LSB as sent, for [`Message::lsb_on_change`].
+  pub fn mark_sent(&mut self) {
+    self.kind.sent_lsb = Some(self.kind.val as u8 & 0x7f);
+  }
+
+  /// Updates the value and sends it, leaving out an unchanged LSB
+  /// if [`Message::lsb_on_change`] is set. MSB and LSB go out with a
+  /// send each, waiting for the port, and the LSB only counts as sent
+  /// once both went out.
+  pub fn send_value(&mut self, port: &Arc<Mutex<Output>>, ch: Channel, val: u16) -> Result<(), String> {
+    self.update_value(val)?;
+    Output::send_blocking(port, &self.to_bytes(ch))?;
+    self.mark_sent();
+    Ok(())
+  }
+}
+
+/// A value reassembled by [`Cc14Decoder`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Cc14Value {
+  pub ch: Channel,
+  /// The MSB controller, 0 - 31.
+  pub addr: u8,
+  pub val: u16,
+}
+
+/// Reassembles 14 bit values from incoming CC pairs, on all 16 channels
+/// and all 32 paired controllers.
+///
+/// As the spec asks, an MSB resets the LSB to 0, and is reported on its
+/// own, so that devices sending only MSBs work too. An LSB is reported
+/// combined with the last MSB, and ignored if no MSB came before it.
+/// ```
+/// use midi::{message::{cc14::{Cc14Decoder, Cc14Value}, event::MidiEvent}, util::Channel};
+/// let mut decoder = Cc14Decoder::new();
+/// let ch = Channel(3);
+/// assert_eq!(decoder.handle(&MidiEvent::Cc{ ch, addr: 33, val: 0x10 }), None);
+/// let msb = decoder.handle(&MidiEvent::Cc{ ch, addr: 1, val: 0x40 });
+/// assert_eq!(msb, Some(Cc14Value{ ch, addr: 1, val: 0x2000 }));
+/// let lsb = decoder.handle(&MidiEvent::Cc{ ch, addr: 33, val: 0x10 });
+/// assert_eq!(lsb, Some(Cc14Value{ ch, addr: 1, val: 0x2010 }));
+/// assert_eq!(decoder.value(ch, 1), Some(0x2010));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Cc14Decoder {
+  values: [[Option<u16>; 32]; 16],
+}
+
+impl Default for Cc14Decoder {
+  fn default() -> Self { Self::new() }
+}
+
+impl Cc14Decoder {
+  pub fn new() -> Self {
+    Self{ values: [[None; 32]; 16] }
+  }
+
+  /// Feeds one event, returning the new value if the event was half of a
+  /// controller pair.
+  pub fn handle(&mut self, event: &MidiEvent) -> Option<Cc14Value> {
+    let MidiEvent::Cc{ ch, addr, val } = *event else { return None };
+    let values = &mut self.values[(ch.0 & 0x0f) as usize];
+    let (addr, val) = match addr {
+      0..=31 => (addr, u16::from(val & 0x7f) << 7),
+      32..=63 => {
+        let addr = addr - Cc14::LSB_OFFSET;
+        let msb = values[addr as usize]?;
+        (addr, msb & !0x7f | u16::from(val & 0x7f))
+      },
+      _ => return None
+    };
+    values[addr as usize] = Some(val);
+    Some(Cc14Value{ ch, addr, val })
+  }
+
+  /// Last value of controller `addr`, 0 - 31, on `ch`.
+  pub fn value(&self, ch: Channel, addr: u8) -> Option<u16> {
+    *self.values.get((ch.0 & 0x0f) as usize)?.get(addr as usize)?
+  }
+
+  /// Forgets every value, e.g. after a Reset All Controllers.
+  pub fn reset(&mut self) {
+    self.values = [[None; 32]; 16];
+  }
+}
@@ -0,0 +1,113 @@
+use super::*;
+
+use crate::consts::{channel_mode::*, note::NOTE_OFF};
+
+/// Channel Mode messages, sent on controllers 120 - 127.
+/// ```
+/// use midi::{message::{Message, channel_mode::ChannelMode}, util::Channel};
+/// let local_off = Message::channel_mode(ChannelMode::LocalControl(false)).unwrap();
+/// assert_eq!(local_off.to_bytes(Channel(2)), vec![0xB2, 122, 0]);
+/// assert_eq!(ChannelMode::parse(126, 4), Some(ChannelMode::MonoOn(4)));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChannelMode {
+  /// Mutes all sound right away, without waiting for release phases.
+  AllSoundOff,
+  ResetAllControllers,
+  /// Connects or disconnects the device's own keyboard from its sound engine.
+  LocalControl(bool),
+  /// Releases all notes, as if a note off was sent for each of them.
+  AllNotesOff,
+  OmniOff,
+  OmniOn,
+  /// Number of channels, 1 - 16, or 0 for as many as there are voices.
+  MonoOn(u8),
+  PolyOn,
+}
+
+impl ChannelMode {
+  /// The controller number of the message.
+  pub fn addr(&self) -> u8 {
+    match self {
+      Self::AllSoundOff => ALL_SOUND_OFF,
+      Self::ResetAllControllers => RESET_ALL_CONTROLLERS,
+      Self::LocalControl(_) => LOCAL_CONTROL,
+      Self::AllNotesOff => ALL_NOTES_OFF,
+      Self::OmniOff => OMNI_OFF,
+      Self::OmniOn => OMNI_ON,
+      Self::MonoOn(_) => MONO_ON,
+      Self::PolyOn => POLY_ON,
+    }
+  }
+
+  pub fn value(&self) -> u8 {
+    match *self {
+      Self::LocalControl(true) => 127,
+      Self::MonoOn(channels) => channels,
+      _ => 0
+    }
+  }
+
+  /// Reads a Channel Mode message from a CC. Returns `None` for
+  /// ordinary controllers.
+  pub fn parse(addr: u8, val: u8) -> Option<Self> {
+    match addr {
+      ALL_SOUND_OFF => Some(Self::AllSoundOff),
+      RESET_ALL_CONTROLLERS => Some(Self::ResetAllControllers),
+      // anything from 64 up counts as on, like a switch controller
+      LOCAL_CONTROL => Some(Self::LocalControl(val >= 64)),
+      ALL_NOTES_OFF => Some(Self::AllNotesOff),
+      OMNI_OFF => Some(Self::OmniOff),
+      OMNI_ON => Some(Self::OmniOn),
+      MONO_ON => Some(Self::MonoOn(val)),
+      POLY_ON => Some(Self::PolyOn),
+      _ => None
+    }
+  }
+}
+
+impl MessageKind for ChannelMode {
+  fn to_bytes(&self, ch: Channel) -> Vec<u8> {
+    vec![CC|ch, self.addr(), self.value()]
+  }
+
+  #[inline]
+  fn validate_address(&self) -> bool { true }
+
+  #[inline]
+  fn validate_value(&self) -> bool { self.value() <= 16 || matches!(self, Self::LocalControl(_)) }
+
+  #[inline]
+  fn repr(&self) -> String { format!("{}", self.value()) }
+
+  #[inline]
+  fn repr_addr(&self) -> String { format!("{self:?}") }
+}
+
+impl Message<ChannelMode> {
+  pub fn channel_mode(mode: ChannelMode) -> Result<Message<ChannelMode>, MidiMessageError> { Message::new(mode) }
+}
+
+/// Silences everything connected to `port`: sends All Notes Off,
+/// All Sound Off and Reset All Controllers on all 16 channels.
+///
+/// Devices that ignore Channel Mode messages can be reached with
+/// `sweep`, which also sends a note off for every note on every channel.
+/// Waits for the port rather than dropping anything when it is busy.
+/// ```no_run
+/// use midi::{connection::Output, message::channel_mode::panic};
+/// let output = Output::new("IAC Driver Bus 1", |_| {}).unwrap();
+/// panic(&output, true).unwrap();
+/// ```
+pub fn panic(port: &Arc<Mutex<Output>>, sweep: bool) -> Result<(), String> {
+  // one message per send, as midir's backends take no more
+  for ch in (0..16).map(Channel) {
+    for mode in [ChannelMode::AllNotesOff, ChannelMode::AllSoundOff, ChannelMode::ResetAllControllers] {
+      Output::send_blocking(port, &mode.to_bytes(ch))?;
+    }
+    if sweep {
+      (0..128).try_for_each(|note| Output::send_blocking(port, &[NOTE_OFF|ch, note, 0]))?;
+    }
+  }
+  Ok(())
+}
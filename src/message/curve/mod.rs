@@ -0,0 +1,161 @@
+use super::*;
+
+/// How a [`Curve`] bends its input, both ends fixed at 0 and 1.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Shape {
+  Linear,
+  /// Slow start, fast end. Larger amounts bend harder.
+  Exponential(f32),
+  /// Fast start, slow end, the mirror image of [`Shape::Exponential`].
+  Logarithmic(f32),
+  /// Slow at both ends, fast in the middle.
+  SCurve(f32),
+  /// Straight lines between `(in, out)` points, both 0 - 127, sorted by `in`.
+  Breakpoints(Vec<(u8, u8)>),
+}
+
+/// A response curve for 7 bit values, such as velocities and CCs.
+/// ```
+/// use midi::message::{Message, curve::Curve};
+/// // a light touch: soft playing comes out louder
+/// let light = Curve::logarithmic(3.0);
+/// assert!(light.apply(32) > 32);
+/// assert_eq!(light.apply(127), 127);
+///
+/// let mut note = Message::note(60, 32).unwrap();
+/// note.apply_curve(&light);
+///
+/// // a pedal that works the wrong way around, and should never fully close
+/// let pedal = Curve::linear().invert().clamp(20, 127);
+/// assert_eq!(pedal.apply(127), 20);
+/// assert_eq!(pedal.apply(0), 127);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Curve {
+  shape: Shape,
+  min: u8,
+  max: u8,
+  invert: bool,
+}
+
+impl Default for Curve {
+  fn default() -> Self { Self::linear() }
+}
+
+impl Curve {
+  /// A curve of any shape. Breakpoints are checked and sorted as in
+  /// [`Curve::breakpoints`].
+  /// ```
+  /// use midi::message::curve::{Curve, Shape};
+  /// assert!(Curve::new(Shape::Breakpoints(vec![])).is_err());
+  /// ```
+  pub fn new(shape: Shape) -> Result<Self, String> {
+    match shape {
+      Shape::Breakpoints(points) => Self::breakpoints(&points),
+      shape => Ok(Self::with_shape(shape)),
+    }
+  }
+
+  fn with_shape(shape: Shape) -> Self {
+    Self{ shape, min: 0, max: 127, invert: false }
+  }
+
+  pub fn linear() -> Self { Self::with_shape(Shape::Linear) }
+
+  pub fn exponential(amount: f32) -> Self { Self::with_shape(Shape::Exponential(amount)) }
+
+  pub fn logarithmic(amount: f32) -> Self { Self::with_shape(Shape::Logarithmic(amount)) }
+
+  pub fn s_curve(amount: f32) -> Self { Self::with_shape(Shape::SCurve(amount)) }
+
+  /// A curve through `points`, `(in, out)` pairs in 0 - 127. Inputs
+  /// before the first point or after the last get that point's output.
+  /// ```
+  /// use midi::message::curve::Curve;
+  /// let knee = Curve::breakpoints(&[(0, 0), (64, 100), (127, 127)]).unwrap();
+  /// assert_eq!(knee.apply(32), 50);
+  /// ```
+  pub fn breakpoints(points: &[(u8, u8)]) -> Result<Self, String> {
+    if points.is_empty() {
+      return Err("A breakpoint curve needs at least one point".to_owned())
+    }
+    if let Some(&(i, o)) = points.iter().find(|(i, o)| *i > 127 || *o > 127) {
+      return Err(format!("Breakpoint ({i}, {o}) is outside of 0 - 127"))
+    }
+    let mut points = points.to_vec();
+    points.sort_by_key(|(i, _)| *i);
+    Ok(Self::with_shape(Shape::Breakpoints(points)))
+  }
+
+  /// Turns the curve upside down, 0 coming out as 127 and the other way around.
+  pub fn invert(mut self) -> Self {
+    self.invert = !self.invert;
+    self
+  }
+
+  /// Keeps the output within `min` - `max`.
+  pub fn clamp(mut self, min: u8, max: u8) -> Self {
+    self.min = min.min(max).min(127);
+    self.max = max.max(min).min(127);
+    self
+  }
+
+  pub fn shape(&self) -> &Shape { &self.shape }
+
+  /// Maps `x`, 0.0 - 1.0, through the shape, without clamping.
+  pub fn apply_normalized(&self, x: f32) -> f32 {
+    let x = x.clamp(0.0, 1.0);
+    let y = match &self.shape {
+      Shape::Linear => x,
+      Shape::Exponential(k) if *k > f32::EPSILON => (k * x).exp_m1() / k.exp_m1(),
+      Shape::Logarithmic(k) if *k > f32::EPSILON => (x * k.exp_m1()).ln_1p() / k,
+      Shape::SCurve(k) if *k > f32::EPSILON => {
+        let s = |x: f32| 1.0 / (1.0 + (-k * (x - 0.5)).exp());
+        (s(x) - s(0.0)) / (s(1.0) - s(0.0))
+      },
+      Shape::Breakpoints(points) => breakpoint(points, x * 127.0) / 127.0,
+      _ => x,
+    };
+    if self.invert { 1.0 - y } else { y }
+  }
+
+  /// Maps a 7 bit value through the curve.
+  pub fn apply(&self, value: u8) -> u8 {
+    let y = self.apply_normalized(f32::from(value.min(127)) / 127.0);
+    ((y * 127.0).round() as u8).clamp(self.min, self.max)
+  }
+
+  /// The curve as a lookup table, for when it is applied a lot.
+  pub fn table(&self) -> [u8; 128] {
+    std::array::from_fn(|v| self.apply(v as u8))
+  }
+}
+
+fn breakpoint(points: &[(u8, u8)], x: f32) -> f32 {
+  let point = |i: usize| (f32::from(points[i].0), f32::from(points[i].1));
+  match points.iter().position(|(i, _)| f32::from(*i) >= x) {
+    None => point(points.len() - 1).1,
+    Some(0) => point(0).1,
+    Some(i) => {
+      let ((x0, y0), (x1, y1)) = (point(i - 1), point(i));
+      y0 + (y1 - y0) * (x - x0) / (x1 - x0)
+    }
+  }
+}
+
+impl Message<NoteOn> {
+  /// Maps the velocity through `curve`. A note on stays a note on,
+  /// so velocities are kept at 1 or above.
+  pub fn apply_curve(&mut self, curve: &Curve) {
+    if self.kind.velo > 0 {
+      self.kind.velo = curve.apply(self.kind.velo).max(1);
+    }
+  }
+}
+
+impl Message<Cc> {
+  /// Maps the value through `curve`.
+  pub fn apply_curve(&mut self, curve: &Curve) {
+    self.kind.val = curve.apply(self.kind.val);
+  }
+}
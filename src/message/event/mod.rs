@@ -0,0 +1,208 @@
+use crate::consts::{
+  message::{
+    CC, CHANNEL_PRESSURE, PB, POLY_PRESSURE, PROGRAM_CHANGE, SONG_POSITION,
+    SONG_SELECT, SYSEX_BEGIN, SYSEX_END, TIME_CODE, TUNE_REQUEST,
+  },
+  note::{NOTE_OFF, NOTE_ON},
+  transport::{ACTIVE_SENSING, CLOCK, CONTINUE, RESET, START, STOP},
+};
+use crate::util::Channel;
+
+/// An owned, parsed MIDI 1.0 message, as received from an input.
+/// ```
+/// use midi::message::event::MidiEvent;
+/// use midi::util::Channel;
+/// let event = MidiEvent::parse(&[0x91, 60, 100]);
+/// assert_eq!(event, Some(MidiEvent::NoteOn{ ch: Channel(1), note: 60, velo: 100 }));
+/// assert_eq!(event.unwrap().to_bytes(), vec![0x91, 60, 100]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum MidiEvent {
+  NoteOff { ch: Channel, note: u8, velo: u8 },
+  NoteOn { ch: Channel, note: u8, velo: u8 },
+  PolyPressure { ch: Channel, note: u8, pressure: u8 },
+  Cc { ch: Channel, addr: u8, val: u8 },
+  ProgramChange { ch: Channel, program: u8 },
+  ChannelPressure { ch: Channel, pressure: u8 },
+  /// Raw 14 bit value, `0x2000` being the centre.
+  PitchBend { ch: Channel, value: u16 },
+  /// Complete message, including the `0xF0` and `0xF7` framing.
+  SysEx(Vec<u8>),
+  TimeCode(u8),
+  SongPosition(u16),
+  SongSelect(u8),
+  TuneRequest,
+  Clock,
+  Start,
+  Continue,
+  Stop,
+  ActiveSensing,
+  Reset,
+}
+
+/// A [`MidiEvent`] stamped with the time it was received, in microseconds.
+/// What the timestamp is relative to is up to the platform, see [`midir`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimedEvent {
+  pub timestamp: u64,
+  pub event: MidiEvent,
+}
+
+impl MidiEvent {
+  /// Parses the first message in `bytes`. Returns `None` if `bytes`
+  /// does not start with a complete message.
+  pub fn parse(bytes: &[u8]) -> Option<Self> {
+    let (&status, data) = bytes.split_first()?;
+    Self::parse_with_status(status, data).map(|(event, _)| event)
+  }
+
+  /// Parses every message in `bytes`, following running status and
+  /// skipping anything that can not be made sense of.
+  /// ```
+  /// use midi::message::event::MidiEvent;
+  /// // NRPN select with running status, and a clock tick in the middle
+  /// let events = MidiEvent::parse_all(&[0xB0, 0x63, 1, 0xF8, 0x62, 2]);
+  /// assert_eq!(events.len(), 3);
+  /// assert_eq!(events[1], MidiEvent::Clock);
+  /// ```
+  pub fn parse_all(bytes: &[u8]) -> Vec<Self> {
+    let mut events = vec![];
+    let mut running: Option<u8> = None;
+    let mut i = 0;
+    while i < bytes.len() {
+      let byte = bytes[i];
+      // System Real Time may appear anywhere and leaves running status alone
+      if byte >= CLOCK {
+        events.extend(Self::parse(&[byte]));
+        i += 1;
+        continue
+      }
+      let (status, data) = if byte & 0x80 != 0 {
+        running = (byte < SYSEX_BEGIN).then_some(byte);
+        (byte, &bytes[i + 1..])
+      } else if let Some(status) = running {
+        (status, &bytes[i..])
+      } else {
+        i += 1;
+        continue
+      };
+      match Self::parse_with_status(status, data) {
+        Some((event, used)) => {
+          events.push(event);
+          i += used + usize::from(byte & 0x80 != 0);
+        },
+        None => i += 1
+      }
+    }
+    events
+  }
+
+  /// Returns the MIDI 1.0 byte representation of this message.
+  pub fn to_bytes(&self) -> Vec<u8> {
+    match self {
+      Self::NoteOff { ch, note, velo } => vec![NOTE_OFF|*ch, *note, *velo],
+      Self::NoteOn { ch, note, velo } => vec![NOTE_ON|*ch, *note, *velo],
+      Self::PolyPressure { ch, note, pressure } => vec![POLY_PRESSURE|*ch, *note, *pressure],
+      Self::Cc { ch, addr, val } => vec![CC|*ch, *addr, *val],
+      Self::ProgramChange { ch, program } => vec![PROGRAM_CHANGE|*ch, *program],
+      Self::ChannelPressure { ch, pressure } => vec![CHANNEL_PRESSURE|*ch, *pressure],
+      Self::PitchBend { ch, value } => vec![PB|*ch, (value & 0x7f) as u8, (value >> 7) as u8 & 0x7f],
+      Self::SysEx(data) => data.clone(),
+      Self::TimeCode(v) => vec![TIME_CODE, *v],
+      Self::SongPosition(v) => vec![SONG_POSITION, (v & 0x7f) as u8, (v >> 7) as u8 & 0x7f],
+      Self::SongSelect(v) => vec![SONG_SELECT, *v],
+      Self::TuneRequest => vec![TUNE_REQUEST],
+      Self::Clock => vec![CLOCK],
+      Self::Start => vec![START],
+      Self::Continue => vec![CONTINUE],
+      Self::Stop => vec![STOP],
+      Self::ActiveSensing => vec![ACTIVE_SENSING],
+      Self::Reset => vec![RESET],
+    }
+  }
+
+  /// Returns the channel of channel voice messages.
+  pub fn channel(&self) -> Option<Channel> {
+    match self {
+      Self::NoteOff { ch, .. }
+      | Self::NoteOn { ch, .. }
+      | Self::PolyPressure { ch, .. }
+      | Self::Cc { ch, .. }
+      | Self::ProgramChange { ch, .. }
+      | Self::ChannelPressure { ch, .. }
+      | Self::PitchBend { ch, .. } => Some(*ch),
+      _ => None
+    }
+  }
+
+  /// Returns `true` for a Note Off, or a Note On with velocity 0.
+  pub fn is_note_off(&self) -> bool {
+    matches!(self, Self::NoteOff { .. } | Self::NoteOn { velo: 0, .. })
+  }
+
+  /// Parses one message from a status byte and the bytes following it.
+  /// Returns the message and how many data bytes it used.
+  fn parse_with_status(status: u8, data: &[u8]) -> Option<(Self, usize)> {
+    let ch = Channel(status & 0x0f);
+    let d = |i: usize| data.get(i).copied().filter(|b| b & 0x80 == 0);
+    let event = match status & 0xf0 {
+      NOTE_OFF => (Self::NoteOff { ch, note: d(0)?, velo: d(1)? }, 2),
+      NOTE_ON => (Self::NoteOn { ch, note: d(0)?, velo: d(1)? }, 2),
+      POLY_PRESSURE => (Self::PolyPressure { ch, note: d(0)?, pressure: d(1)? }, 2),
+      CC => (Self::Cc { ch, addr: d(0)?, val: d(1)? }, 2),
+      PROGRAM_CHANGE => (Self::ProgramChange { ch, program: d(0)? }, 1),
+      CHANNEL_PRESSURE => (Self::ChannelPressure { ch, pressure: d(0)? }, 1),
+      PB => (Self::PitchBend { ch, value: u16::from(d(0)?) | u16::from(d(1)?) << 7 }, 2),
+      _ => match status {
+        SYSEX_BEGIN => {
+          let end = data.iter().position(|&b| b == SYSEX_END).map_or(data.len(), |i| i + 1);
+          let mut sysex = Vec::with_capacity(end + 1);
+          sysex.push(SYSEX_BEGIN);
+          sysex.extend_from_slice(&data[..end]);
+          (Self::SysEx(sysex), end)
+        },
+        TIME_CODE => (Self::TimeCode(d(0)?), 1),
+        SONG_POSITION => (Self::SongPosition(u16::from(d(0)?) | u16::from(d(1)?) << 7), 2),
+        SONG_SELECT => (Self::SongSelect(d(0)?), 1),
+        TUNE_REQUEST => (Self::TuneRequest, 0),
+        CLOCK => (Self::Clock, 0),
+        START => (Self::Start, 0),
+        CONTINUE => (Self::Continue, 0),
+        STOP => (Self::Stop, 0),
+        ACTIVE_SENSING => (Self::ActiveSensing, 0),
+        RESET => (Self::Reset, 0),
+        _ => return None
+      }
+    };
+    Some(event)
+  }
+}
+
+/// Splits `bytes` into its messages, without parsing them: a message
+/// starts at every status byte, and SysEx runs up to its `0xF7`. Data
+/// bytes following running status stay with the message before them.
+/// ```
+/// use midi::message::event::split;
+/// let bytes = [0x90, 60, 100, 0xF0, 0x7E, 0xF7, 0xB0, 7, 100, 0xF8];
+/// assert_eq!(split(&bytes), vec![&bytes[..3], &bytes[3..6], &bytes[6..9], &bytes[9..]]);
+/// ```
+pub fn split(bytes: &[u8]) -> Vec<&[u8]> {
+  let mut messages = vec![];
+  let mut start = 0;
+  let mut sysex = false;
+  for (i, &b) in bytes.iter().enumerate() {
+    match b {
+      SYSEX_END if sysex => sysex = false,
+      // realtime bytes may come in the middle of SysEx
+      CLOCK.. if sysex => (),
+      0x80.. => {
+        if i > start { messages.push(&bytes[start..i]) }
+        start = i;
+        sysex = b == SYSEX_BEGIN;
+      },
+      _ => (),
+    }
+  }
+  if start < bytes.len() { messages.push(&bytes[start..]) }
+  messages
+}
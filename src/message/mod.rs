@@ -1,9 +1,14 @@
 pub mod cc;
+pub mod cc14;
+pub mod channel_mode;
+pub mod curve;
 pub mod nrpn;
 pub mod rpn;
 pub mod sysex;
 pub mod note;
 pub mod pitchbend;
+pub mod pressure;
+pub mod event;
 
 use std::{borrow::Cow, fmt::Display};
 use crate::{
@@ -31,7 +36,7 @@ use nrpn::{
   no_term::NrpnNoTerminator
 };
 use rpn::{Rpn, RpnKind};
-use sysex::SysEx;
+use sysex::{OwnedSysEx, SysEx};
 use note::NoteOn;
 use self::pitchbend::PitchBend;
 
@@ -141,6 +146,11 @@ impl<T: MessageKind> Message<T> {
       err_send_log(p.send(&msg))
     } 
   }
+
+  /// Returns the bytes [`Message::send`] would send on `ch`.
+  pub fn to_bytes(&self, ch: Channel) -> Vec<u8> {
+    T::to_bytes(&self.kind, ch)
+  }
 }
 
 impl Message<Cc> {
@@ -168,6 +178,12 @@ impl Message<Cc> {
 }
 
 impl Message<PitchBend> {
+  /// Takes the MSB first, but sends the LSB first, as the spec has it.
+  /// ```
+  /// use midi::{message::Message, util::Channel};
+  /// let pb = Message::pb(0x40, 0x01).unwrap();
+  /// assert_eq!(pb.to_bytes(Channel(2)), vec![0xE2, 0x01, 0x40]);
+  /// ```
   pub fn pb(msb: u8, lsb: u8) -> Result<Message<PitchBend>, MidiMessageError> { Message::new(PitchBend { msb, lsb }) }
 
   pub fn update_value(&mut self, val: (u8,u8)) -> Result<(), String> {
@@ -281,6 +297,25 @@ impl<'a> Message<SysEx<'a>> {
     self.kind.data = Cow::Borrowed(data);
     Ok(())
   }
+  /// Copies the data if it is borrowed, so that the message can be
+  /// stored or moved to another thread.
+  pub fn into_owned(self) -> Message<OwnedSysEx> {
+    Message{ kind: SysEx{ data: Cow::Owned(self.kind.data.into_owned()) } }
+  }
+
+  pub fn data(&self) -> &[u8] { &self.kind.data }
+}
+
+impl Message<OwnedSysEx> {
+  /// Same as [`Message::sysex`], taking ownership of `data`.
+  /// ```
+  /// use midi::message::Message;
+  /// let dump = Message::sysex_owned(vec![0xF0, 0x43, 0x10, 0x7F, 0xF7]).unwrap();
+  /// std::thread::spawn(move || assert_eq!(dump.data().len(), 5));
+  /// ```
+  pub fn sysex_owned(data: Vec<u8>) -> Result<Message<OwnedSysEx>, MidiMessageError> {
+    Message::new(SysEx { data: Cow::Owned(data) })
+  }
 }
 
 impl Message<NoteOn> {
@@ -325,11 +360,11 @@ pub fn cc(port: &Arc<Mutex<Output>>, ch: u8, addr: u8, val: u8) {
   } 
 }
 
-/// Sends an Cc message to the given Output. 
+/// Sends a Pitch Bend message to the given Output. 
 ///
-/// Contiuous Controller message
+/// The LSB goes first on the wire, as the spec has it.
 pub fn pitchbend(port: &Arc<Mutex<Output>>, ch: u8, msb: u8, lsb: u8) {
-  let msg = [PB|ch, msb, lsb];
+  let msg = [PB|ch, lsb, msb];
   if let Ok(mut p) = port.try_lock() {
     err_send_log(p.send(&msg))
   } 
@@ -356,8 +391,8 @@ pub fn nrpn(port: &Arc<Mutex<Output>>, ch: u8, addr: (u8, u8), val: (u8, u8)) {
 /// Registered Parameter Number message
 pub fn rpn(port: &Arc<Mutex<Output>>, ch: u8, addr: &RpnKind, val: (u8, u8)) {
   let msg = [
-    CC|ch, RPN_MSB, 0x00, RPN_LSB, *addr as u8, 
-    CC|ch, RPN_VAL_MSB, val.0, RPN_VAL_LSB, val.1,
+    CC|ch, RPN_MSB, 0x00, CC|ch, RPN_LSB, *addr as u8, 
+    CC|ch, RPN_VAL_MSB, val.0, CC|ch, RPN_VAL_LSB, val.1,
     CC|ch, RPN_MSB, 127, CC|ch, RPN_LSB, 127 // NULL
   ];
   if let Ok(mut p) = port.try_lock() {
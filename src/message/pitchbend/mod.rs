@@ -1,21 +1,227 @@
 use super::*;
 
+use crate::message::event::MidiEvent;
+
+/// A pitch bend, as the 7 bit halves of its 14 bit value. 0x2000 is no bend.
+///
+/// The value can also be given and read signed, -8192 - 8191, normalized,
+/// -1.0 - 1.0, or in semitones for a [`BendRange`].
+/// ```
+/// use midi::message::pitchbend::{PitchBend, BendRange};
+/// let bend = PitchBend::from_value(-4096);
+/// assert_eq!((bend.msb, bend.lsb), (0x20, 0));
+/// assert_eq!(bend.normalized(), -0.5);
+/// assert_eq!(bend.semitones(BendRange::default()), -1.0);
+/// assert_eq!(PitchBend::from_normalized(1.0).value(), PitchBend::MAX);
+/// assert_eq!(PitchBend::from_semitones(0.5, BendRange::new(12, 0).unwrap()).value(), 341);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct PitchBend { pub msb: u8, pub lsb: u8 }
 
+impl PitchBend {
+  /// The raw value of no bend.
+  pub const CENTRE: u16 = 0x2000;
+  pub const MIN: i16 = -8192;
+  pub const MAX: i16 = 8191;
+
+  /// From the raw 14 bit value, clamped to 0 - 0x3fff.
+  pub fn from_raw(raw: u16) -> Self {
+    let raw = raw.min(0x3fff);
+    Self{ msb: (raw >> 7) as u8, lsb: (raw & 0x7f) as u8 }
+  }
+
+  /// From a signed value, clamped to -8192 - 8191.
+  pub fn from_value(value: i16) -> Self {
+    Self::from_raw((value.clamp(Self::MIN, Self::MAX) + Self::CENTRE as i16) as u16)
+  }
+
+  /// From -1.0 - 1.0, reaching all the way down and up.
+  pub fn from_normalized(value: f32) -> Self {
+    let value = value.clamp(-1.0, 1.0);
+    let scale = if value < 0.0 { -f32::from(Self::MIN) } else { f32::from(Self::MAX) };
+    Self::from_value((value * scale).round() as i16)
+  }
+
+  /// A bend of `semitones`, clamped to what `range` can reach.
+  pub fn from_semitones(semitones: f32, range: BendRange) -> Self {
+    if range.total() == 0.0 { return Self::from_value(0) }
+    let value = (semitones / range.total() * 8192.0).round();
+    Self::from_value(value.clamp(f32::from(Self::MIN), f32::from(Self::MAX)) as i16)
+  }
+
+  pub fn from_cents(cents: f32, range: BendRange) -> Self {
+    Self::from_semitones(cents / 100.0, range)
+  }
+
+  pub fn raw(&self) -> u16 { u16::from(self.msb & 0x7f) << 7 | u16::from(self.lsb & 0x7f) }
+
+  pub fn value(&self) -> i16 { self.raw() as i16 - Self::CENTRE as i16 }
+
+  pub fn normalized(&self) -> f32 {
+    let value = self.value();
+    let scale = if value < 0 { -f32::from(Self::MIN) } else { f32::from(Self::MAX) };
+    f32::from(value) / scale
+  }
+
+  /// The bend in semitones, 8192 steps being `range`.
+  pub fn semitones(&self, range: BendRange) -> f32 {
+    f32::from(self.value()) / 8192.0 * range.total()
+  }
+
+  pub fn cents(&self, range: BendRange) -> f32 { self.semitones(range) * 100.0 }
+}
+
+impl Default for PitchBend {
+  fn default() -> Self { Self::from_value(0) }
+}
+
 impl MessageKind for PitchBend {
   fn to_bytes(&self, ch: Channel) -> Vec<u8> {
-      vec![PB|ch, self.msb, self.lsb]
+      // LSB goes first on the wire
+      vec![PB|ch, self.lsb, self.msb]
   }
 
   #[inline]
   fn validate_address(&self) -> bool { self.msb < 128 && self.lsb < 128 }
-  
+
   #[inline]
   fn validate_value(&self) -> bool { self.msb < 128 && self.lsb < 128 }
 
   #[inline]
   fn repr(&self) -> String { format!("{} {}", self.msb, self.lsb) }
-  
+
   #[inline]
   fn repr_addr(&self) -> String { format!("{} {}", self.msb, self.lsb) }
 }
+
+/// How far a full pitch bend reaches either way, as set by RPN 0:
+/// semitones in the value MSB and cents in the LSB.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BendRange {
+  pub semitones: u8,
+  pub cents: u8,
+}
+
+impl Default for BendRange {
+  /// 2 semitones, what receivers start out with.
+  fn default() -> Self { Self{ semitones: 2, cents: 0 } }
+}
+
+impl From<u8> for BendRange {
+  fn from(semitones: u8) -> Self { Self{ semitones, cents: 0 } }
+}
+
+impl BendRange {
+  pub fn new(semitones: u8, cents: u8) -> Result<Self, String> {
+    if semitones > 127 {
+      return Err(format!("Too big a pitch bend range: {semitones} semitones"))
+    }
+    if cents > 99 {
+      return Err(format!("Too many cents in a pitch bend range: {cents}"))
+    }
+    Ok(Self{ semitones, cents })
+  }
+
+  /// The range in semitones, cents included.
+  pub fn total(&self) -> f32 { f32::from(self.semitones) + f32::from(self.cents) / 100.0 }
+
+  /// The RPN 0 message that sets a receiver to this range.
+  /// ```
+  /// use midi::{message::pitchbend::BendRange, util::Channel};
+  /// let rpn = BendRange::new(12, 50).unwrap().rpn().to_bytes(Channel(0));
+  /// assert_eq!(&rpn[6..12], &[0xB0, 6, 12, 0xB0, 38, 50]);
+  /// ```
+  pub fn rpn(&self) -> Message<Rpn> {
+    Message{ kind: Rpn{ addr: RpnKind::PitchBend, val: (self.semitones & 0x7f, self.cents & 0x7f) } }
+  }
+}
+
+impl Message<PitchBend> {
+  /// A bend of -8192 - 8191, clamped.
+  /// ```
+  /// use midi::{message::Message, util::Channel};
+  /// let down = Message::bend(-8192);
+  /// assert_eq!(down.to_bytes(Channel(0)), vec![0xE0, 0, 0]);
+  /// ```
+  pub fn bend(value: i16) -> Self { Self{ kind: PitchBend::from_value(value) } }
+
+  /// A bend of -1.0 - 1.0, clamped.
+  pub fn bend_normalized(value: f32) -> Self { Self{ kind: PitchBend::from_normalized(value) } }
+
+  /// A bend of `semitones`, for a receiver set to `range`.
+  pub fn bend_semitones(semitones: f32, range: BendRange) -> Self {
+    Self{ kind: PitchBend::from_semitones(semitones, range) }
+  }
+
+  pub fn pitch_bend(&self) -> PitchBend { self.kind }
+
+  pub fn set_bend(&mut self, bend: PitchBend) { self.kind = bend }
+}
+
+/// An incoming pitch bend, decoded by [`BendDecoder`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bend {
+  pub ch: Channel,
+  pub bend: PitchBend,
+  /// The bend in semitones, for the channel's range.
+  pub semitones: f32,
+}
+
+/// Decodes incoming pitch bends into semitones, following the pitch bend
+/// range (RPN 0) the sender sets on each channel.
+/// ```
+/// use midi::{message::{event::MidiEvent, pitchbend::BendDecoder}, util::Channel};
+/// let mut decoder = BendDecoder::new();
+/// let ch = Channel(0);
+/// for (addr, val) in [(101, 0), (100, 0), (6, 12), (38, 0)] {
+///   decoder.handle(&MidiEvent::Cc{ ch, addr, val });
+/// }
+/// let bend = decoder.handle(&MidiEvent::PitchBend{ ch, value: 0x3000 }).unwrap();
+/// assert_eq!(bend.semitones, 6.0);
+/// ```
+#[derive(Debug, Clone)]
+pub struct BendDecoder {
+  ranges: [BendRange; 16],
+  rpn: [(Option<u8>, Option<u8>); 16],
+}
+
+impl Default for BendDecoder {
+  fn default() -> Self { Self::new() }
+}
+
+impl BendDecoder {
+  /// Every channel starts out at the default range of 2 semitones.
+  pub fn new() -> Self {
+    Self{ ranges: [BendRange::default(); 16], rpn: [(None, None); 16] }
+  }
+
+  pub fn range(&self, ch: Channel) -> BendRange { self.ranges[usize::from(ch.0 & 0x0f)] }
+
+  pub fn set_range(&mut self, ch: Channel, range: BendRange) { self.ranges[usize::from(ch.0 & 0x0f)] = range }
+
+  /// Feeds one incoming message. Returns the bend for pitch bend messages,
+  /// and follows RPN 0 data entry for everything else.
+  pub fn handle(&mut self, event: &MidiEvent) -> Option<Bend> {
+    match *event {
+      MidiEvent::PitchBend{ ch, value } => {
+        let bend = PitchBend::from_raw(value);
+        Some(Bend{ ch, bend, semitones: bend.semitones(self.range(ch)) })
+      },
+      MidiEvent::Cc{ ch, addr, val } => {
+        let i = usize::from(ch.0 & 0x0f);
+        let selected = self.rpn[i] == (Some(0), Some(RpnKind::PitchBend as u8));
+        match addr {
+          RPN_MSB => self.rpn[i].0 = Some(val),
+          RPN_LSB => self.rpn[i].1 = Some(val),
+          // an NRPN takes data entry over
+          NRPN_MSB | NRPN_LSB => self.rpn[i] = (None, None),
+          RPN_VAL_MSB if selected => self.ranges[i] = BendRange{ semitones: val, cents: 0 },
+          RPN_VAL_LSB if selected => self.ranges[i].cents = val.min(99),
+          _ => ()
+        }
+        None
+      },
+      _ => None
+    }
+  }
+}
@@ -0,0 +1,23 @@
+use super::*;
+use crate::consts::message::CHANNEL_PRESSURE;
+
+/// Channel Pressure, also known as (channel) aftertouch.
+pub struct ChannelPressure { pub val: u8 }
+
+impl MessageKind for ChannelPressure {
+  fn to_bytes(&self, ch: Channel) -> Vec<u8> {
+      vec![CHANNEL_PRESSURE|ch, self.val]
+  }
+
+  #[inline]
+  fn validate_address(&self) -> bool { true }
+  
+  #[inline]
+  fn validate_value(&self) -> bool { self.val < 128 }
+
+  #[inline]
+  fn repr(&self) -> String { format!("{}", self.val) }
+  
+  #[inline]
+  fn repr_addr(&self) -> String { "Channel Pressure".to_owned() }
+}
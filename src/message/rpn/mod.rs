@@ -8,7 +8,25 @@ pub enum RpnKind{
   CoarseTune     = 0x02,
   TuneProgChange = 0x03,
   TuneBankSel    = 0x04,
-  ModDepthRange  = 0x05
+  ModDepthRange  = 0x05,
+  /// MPE Configuration Message, value MSB is the number of member channels
+  MpeConfig      = 0x06,
+}
+
+impl TryFrom<u8> for RpnKind {
+  type Error = MidiMessageError;
+  fn try_from(value: u8) -> Result<Self, Self::Error> {
+    match value {
+      0x00 => Ok(Self::PitchBend),
+      0x01 => Ok(Self::FineTune),
+      0x02 => Ok(Self::CoarseTune),
+      0x03 => Ok(Self::TuneProgChange),
+      0x04 => Ok(Self::TuneBankSel),
+      0x05 => Ok(Self::ModDepthRange),
+      0x06 => Ok(Self::MpeConfig),
+      _ => Err(MidiMessageError::Address(format!("Unknown RPN: {value}")))
+    }
+  }
 }
 
 pub struct Rpn  { pub addr: RpnKind, pub val: (u8, u8) }
@@ -17,8 +35,8 @@ impl MessageKind for Rpn {
   fn to_bytes(&self, ch: Channel) -> Vec<u8> {
     
     vec![
-      CC|ch, RPN_MSB, self.addr as u8, 
-      CC|ch, RPN_LSB, 0x00, 
+      CC|ch, RPN_MSB, 0x00, 
+      CC|ch, RPN_LSB, self.addr as u8, 
       CC|ch, RPN_VAL_MSB, self.val.0,
       CC|ch, RPN_VAL_LSB, self.val.1,
       CC|ch, RPN_MSB, 127, // NULL
@@ -0,0 +1,88 @@
+use super::*;
+
+use crate::consts::message::{SYSEX_BEGIN, SYSEX_END};
+
+/// Frames vendor SysEx: `F0`, manufacturer ID, device ID, header,
+/// payload, checksum and `F7`.
+///
+/// The checksum covers the payload only, so bytes such as a model ID or
+/// command that the checksum should skip go in the header.
+/// ```
+/// use midi::message::sysex::{builder::SysExBuilder, checksum::Roland, manufacturer::ManufacturerId};
+/// // Roland GS reset: model 0x42, command DT1, address 40 00 7F, data 00
+/// let message = SysExBuilder::new(ManufacturerId::Roland)
+///   .device(0x10)
+///   .header(&[0x42, 0x12])
+///   .payload(&[0x40, 0x00, 0x7F, 0x00])
+///   .checksum(Roland)
+///   .build()
+///   .unwrap();
+/// assert_eq!(
+///   message.to_bytes(midi::util::Channel(0)),
+///   vec![0xF0, 0x41, 0x10, 0x42, 0x12, 0x40, 0x00, 0x7F, 0x00, 0x41, 0xF7]
+/// );
+/// ```
+pub struct SysExBuilder {
+  manufacturer: ManufacturerId,
+  device: Option<u8>,
+  header: Vec<u8>,
+  payload: Vec<u8>,
+  checksum: Option<Box<dyn Checksum + Send + Sync>>,
+}
+
+impl SysExBuilder {
+  pub fn new(manufacturer: ManufacturerId) -> Self {
+    Self{ manufacturer, device: None, header: vec![], payload: vec![], checksum: None }
+  }
+
+  /// Device ID sent right after the manufacturer ID. Left out if not set.
+  pub fn device(mut self, device: u8) -> Self {
+    self.device = Some(device);
+    self
+  }
+
+  /// Bytes between the device ID and the payload, not covered by the checksum.
+  pub fn header(mut self, header: &[u8]) -> Self {
+    self.header = header.to_vec();
+    self
+  }
+
+  pub fn payload(mut self, payload: &[u8]) -> Self {
+    self.payload = payload.to_vec();
+    self
+  }
+
+  /// Packs 8 bit `data` with [`pack_msb`](super::pack::pack_msb) and uses it as payload.
+  pub fn packed_payload(self, data: &[u8]) -> Self {
+    let packed = super::pack::pack_msb(data);
+    self.payload(&packed)
+  }
+
+  /// Appends a checksum of the payload before the closing `F7`.
+  pub fn checksum<C: Checksum + Send + Sync + 'static>(mut self, checksum: C) -> Self {
+    self.checksum = Some(Box::new(checksum));
+    self
+  }
+
+  /// Returns the framed bytes, or an error if any byte between
+  /// `F0` and `F7` has its top bit set.
+  pub fn to_bytes(&self) -> Result<Vec<u8>, MidiMessageError> {
+    let mut out = vec![SYSEX_BEGIN];
+    out.extend(self.manufacturer.to_bytes());
+    out.extend(self.device);
+    out.extend(&self.header);
+    out.extend(&self.payload);
+    if let Some(checksum) = &self.checksum {
+      out.push(checksum.checksum(&self.payload));
+    }
+    if let Some(i) = out[1..].iter().position(|b| b & 0x80 != 0) {
+      return Err(MidiMessageError::Value(format!("Byte {} of SysEx is not 7 bit: {:#04x}", i + 1, out[i + 1])))
+    }
+    out.push(SYSEX_END);
+    Ok(out)
+  }
+
+  pub fn build(&self) -> Result<Message<SysEx<'static>>, MidiMessageError> {
+    Message::new(SysEx{ data: Cow::Owned(self.to_bytes()?) })
+  }
+}
@@ -0,0 +1,66 @@
+/// A SysEx checksum, computed over the bytes the vendor specifies.
+pub trait Checksum {
+  /// Returns the checksum byte for `data`.
+  fn checksum(&self, data: &[u8]) -> u8;
+
+  /// Returns `true` if `sum` is the checksum of `data`.
+  fn verify(&self, data: &[u8], sum: u8) -> bool {
+    self.checksum(data) == sum
+  }
+}
+
+/// The Roland checksum: the value that brings the sum of all covered
+/// bytes to a multiple of 128. Yamaha uses the same checksum.
+/// ```
+/// use midi::message::sysex::checksum::{Checksum, Roland};
+/// // DT1 to address 40 00 7F, data 00 (GS reset)
+/// assert_eq!(Roland.checksum(&[0x40, 0x00, 0x7F, 0x00]), 0x41);
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Roland;
+
+impl Checksum for Roland {
+  fn checksum(&self, data: &[u8]) -> u8 {
+    let sum = data.iter().fold(0u8, |sum, b| sum.wrapping_add(*b)) & 0x7f;
+    (0x80 - sum) & 0x7f
+  }
+}
+
+/// All covered bytes XOR'ed together, as in MIDI Tuning Standard dumps.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Xor;
+
+impl Checksum for Xor {
+  fn checksum(&self, data: &[u8]) -> u8 {
+    data.iter().fold(0, |x, b| x ^ b) & 0x7f
+  }
+}
+
+impl<F: Fn(&[u8]) -> u8> Checksum for F {
+  fn checksum(&self, data: &[u8]) -> u8 { self(data) & 0x7f }
+}
+
+/// Picks a [`Checksum`] by name, for SysEx described by data rather than code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "definitions", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(feature = "definitions", serde(rename_all = "lowercase"))]
+pub enum ChecksumKind {
+  #[default]
+  None,
+  Roland,
+  Xor,
+}
+
+impl Checksum for ChecksumKind {
+  fn checksum(&self, data: &[u8]) -> u8 {
+    match self {
+      Self::None => 0,
+      Self::Roland => Roland.checksum(data),
+      Self::Xor => Xor.checksum(data),
+    }
+  }
+
+  fn verify(&self, data: &[u8], sum: u8) -> bool {
+    *self == Self::None || self.checksum(data) == sum
+  }
+}
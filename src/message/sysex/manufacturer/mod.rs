@@ -0,0 +1,113 @@
+/// A SysEx manufacturer ID, one byte or three bytes long.
+///
+/// Three byte IDs start with `0x00` on the wire, which is not stored in
+/// [`ManufacturerId::Extended`].
+/// ```
+/// use midi::message::sysex::manufacturer::ManufacturerId;
+/// assert_eq!(ManufacturerId::Roland.to_bytes(), vec![0x41]);
+/// assert_eq!(ManufacturerId::Arturia.to_bytes(), vec![0x00, 0x20, 0x6B]);
+/// assert_eq!(ManufacturerId::parse(&[0x00, 0x20, 0x3C, 0x06]), Some((ManufacturerId::Elektron, 3)));
+/// assert_eq!(ManufacturerId::parse(&[0x3B]), Some((ManufacturerId::Other(0x3B), 1)));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ManufacturerId {
+  SequentialCircuits,
+  Moog,
+  Ensoniq,
+  Oberheim,
+  Clavia,
+  Waldorf,
+  Kawai,
+  Roland,
+  Korg,
+  Yamaha,
+  Casio,
+  Akai,
+  /// Non-commercial and educational use.
+  NonCommercial,
+  Alesis,
+  Novation,
+  Behringer,
+  Access,
+  Elektron,
+  Arturia,
+  TeenageEngineering,
+  NativeInstruments,
+  /// Any other one byte ID.
+  Other(u8),
+  /// Any other three byte ID, without the leading `0x00`.
+  Extended(u8, u8),
+}
+
+const KNOWN: [(ManufacturerId, [u8; 3]); 21] = [
+  (ManufacturerId::SequentialCircuits, [0x01, 0, 0]),
+  (ManufacturerId::Moog, [0x04, 0, 0]),
+  (ManufacturerId::Ensoniq, [0x0F, 0, 0]),
+  (ManufacturerId::Oberheim, [0x10, 0, 0]),
+  (ManufacturerId::Clavia, [0x33, 0, 0]),
+  (ManufacturerId::Waldorf, [0x3E, 0, 0]),
+  (ManufacturerId::Kawai, [0x40, 0, 0]),
+  (ManufacturerId::Roland, [0x41, 0, 0]),
+  (ManufacturerId::Korg, [0x42, 0, 0]),
+  (ManufacturerId::Yamaha, [0x43, 0, 0]),
+  (ManufacturerId::Casio, [0x44, 0, 0]),
+  (ManufacturerId::Akai, [0x47, 0, 0]),
+  (ManufacturerId::NonCommercial, [0x7D, 0, 0]),
+  (ManufacturerId::Alesis, [0x00, 0x00, 0x0E]),
+  (ManufacturerId::Novation, [0x00, 0x20, 0x29]),
+  (ManufacturerId::Behringer, [0x00, 0x20, 0x32]),
+  (ManufacturerId::Access, [0x00, 0x20, 0x33]),
+  (ManufacturerId::Elektron, [0x00, 0x20, 0x3C]),
+  (ManufacturerId::Arturia, [0x00, 0x20, 0x6B]),
+  (ManufacturerId::TeenageEngineering, [0x00, 0x20, 0x76]),
+  (ManufacturerId::NativeInstruments, [0x00, 0x21, 0x09]),
+];
+
+impl ManufacturerId {
+  /// Returns the ID as three bytes: `[id, 0, 0]` for one byte IDs,
+  /// `[0x00, a, b]` for three byte IDs.
+  pub fn to_array(&self) -> [u8; 3] {
+    match self {
+      Self::Other(id) => [*id, 0, 0],
+      Self::Extended(a, b) => [0, *a, *b],
+      known => KNOWN.iter().find(|(k, _)| k == known).map_or([0; 3], |(_, b)| *b)
+    }
+  }
+
+  /// Returns the ID as it is sent, one or three bytes.
+  pub fn to_bytes(&self) -> Vec<u8> {
+    match self.to_array() {
+      [0, a, b] => vec![0, a, b],
+      [id, ..] => vec![id],
+    }
+  }
+
+  /// Reads the ID at the start of `bytes`, which follow the `0xF0`.
+  /// Returns the ID and how many bytes it took up.
+  pub fn parse(bytes: &[u8]) -> Option<(Self, usize)> {
+    match *bytes {
+      [0, a, b, ..] => Some((Self::from([0, a, b]), 3)),
+      [id, ..] if id != 0 && id & 0x80 == 0 => Some((Self::from([id, 0, 0]), 1)),
+      _ => None
+    }
+  }
+}
+
+impl From<[u8; 3]> for ManufacturerId {
+  fn from(bytes: [u8; 3]) -> Self {
+    let bytes = match bytes {
+      [0, ..] => bytes,
+      [id, ..] => [id, 0, 0],
+    };
+    KNOWN
+      .iter()
+      .find(|(_, b)| *b == bytes)
+      .map_or_else(
+        || match bytes {
+          [0, a, b] => Self::Extended(a, b),
+          [id, ..] => Self::Other(id),
+        },
+        |(k, _)| *k
+      )
+  }
+}
@@ -1,8 +1,23 @@
+pub mod universal;
+pub mod manufacturer;
+pub mod checksum;
+pub mod pack;
+pub mod builder;
+pub mod sender;
+pub mod template;
+
 use super::*;
 
+use manufacturer::ManufacturerId;
+use checksum::Checksum;
+
 
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct SysEx<'a> { pub data: Cow<'a, [u8]> }
 
+/// A [`SysEx`] that owns its data.
+pub type OwnedSysEx = SysEx<'static>;
+
 impl<'a> MessageKind for SysEx<'a> {
   #[inline]
   fn to_bytes(&self, _ch: Channel) -> Vec<u8> {
@@ -0,0 +1,47 @@
+/// Packs 8 bit `data` into 7 bit bytes, "MS bit" style: every group of
+/// up to seven bytes is preceded by a byte holding their top bits, the
+/// first byte's in bit 0. Used by Korg, Sequential and others.
+/// ```
+/// use midi::message::sysex::pack::{pack_msb, unpack_msb};
+/// let data = [0xFF, 0x01, 0x80];
+/// let packed = pack_msb(&data);
+/// assert_eq!(packed, vec![0b101, 0x7F, 0x01, 0x00]);
+/// assert_eq!(unpack_msb(&packed), data);
+/// ```
+pub fn pack_msb(data: &[u8]) -> Vec<u8> {
+  let mut out = Vec::with_capacity(data.len() + data.len().div_ceil(7));
+  for group in data.chunks(7) {
+    let msbs = group
+      .iter()
+      .enumerate()
+      .fold(0, |msbs, (i, b)| msbs | (b >> 7) << i);
+    out.push(msbs);
+    out.extend(group.iter().map(|b| b & 0x7f));
+  }
+  out
+}
+
+/// Reverses [`pack_msb`].
+pub fn unpack_msb(packed: &[u8]) -> Vec<u8> {
+  let mut out = Vec::with_capacity(packed.len() - packed.len().div_ceil(8));
+  for group in packed.chunks(8) {
+    let (msbs, bytes) = (group[0], &group[1..]);
+    out.extend(
+      bytes
+        .iter()
+        .enumerate()
+        .map(|(i, b)| b & 0x7f | (msbs >> i & 1) << 7)
+    );
+  }
+  out
+}
+
+/// Splits every byte into two 4 bit bytes, high nibble first.
+pub fn nibblize(data: &[u8]) -> Vec<u8> {
+  data.iter().flat_map(|b| [b >> 4, b & 0x0f]).collect()
+}
+
+/// Reverses [`nibblize`]. A trailing odd nibble is dropped.
+pub fn denibblize(nibbles: &[u8]) -> Vec<u8> {
+  nibbles.chunks_exact(2).map(|n| (n[0] & 0x0f) << 4 | n[1] & 0x0f).collect()
+}
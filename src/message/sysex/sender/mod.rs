@@ -0,0 +1,177 @@
+use super::*;
+
+use std::time::{Duration, Instant};
+
+use crossbeam_channel::Receiver;
+
+use crate::{
+  consts::{
+    message::{SYSEX_BEGIN, SYSEX_END},
+    sysex::{HANDSHAKE_ACK, HANDSHAKE_CANCEL, HANDSHAKE_NAK, HANDSHAKE_WAIT, NON_REALTIME},
+  },
+  message::event::{MidiEvent, TimedEvent},
+  transport::sleep,
+};
+
+/// A device's answer to a SysEx message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Handshake {
+  Ack,
+  Nak,
+  /// The device is busy, wait for another answer.
+  Wait,
+  Cancel,
+}
+
+impl Handshake {
+  /// Parses the universal handshaking messages, `F0 7E dd 7C..7F pp F7`,
+  /// as used by the Sample Dump Standard and many vendor protocols.
+  pub fn parse(sysex: &[u8]) -> Option<Self> {
+    match *sysex {
+      [SYSEX_BEGIN, NON_REALTIME, _, sub, ..] => match sub {
+        HANDSHAKE_ACK => Some(Self::Ack),
+        HANDSHAKE_NAK => Some(Self::Nak),
+        HANDSHAKE_WAIT => Some(Self::Wait),
+        HANDSHAKE_CANCEL => Some(Self::Cancel),
+        _ => None
+      },
+      _ => None
+    }
+  }
+}
+
+struct AckWait {
+  events: Receiver<TimedEvent>,
+  timeout: Duration,
+  classify: fn(&[u8]) -> Option<Handshake>,
+}
+
+/// Sends large SysEx dumps without overrunning slow receivers.
+///
+/// A dump is split into its SysEx messages, each sent whole, with a
+/// pause after it of `delay` for every started chunk of `chunk_size`
+/// bytes. A message is never split itself: midir sends every send as
+/// a message of its own, so the rest of a split message would not
+/// arrive as SysEx. With [`ChunkedSender::wait_for_ack`], the sender
+/// waits for the device to answer each message, and resends it on a NAK.
+/// ```no_run
+/// use std::time::Duration;
+/// use midi::{connection::{ConnectionBuilder, Output, receiver::Overflow}, message::sysex::sender::ChunkedSender};
+/// let port = Output::new("IAC Driver Bus 1", |_| {}).unwrap();
+/// let (_input, events) = ConnectionBuilder::new("IAC Driver Bus 1").receiver(64, Overflow::DropOldest).unwrap();
+/// let dump = std::fs::read("patches.syx").unwrap();
+/// ChunkedSender::new(256, Duration::from_millis(20))
+///   .wait_for_ack(events, Duration::from_millis(200))
+///   .send(&port, &dump)
+///   .unwrap();
+/// ```
+pub struct ChunkedSender {
+  chunk_size: usize,
+  delay: Duration,
+  retries: usize,
+  ack: Option<AckWait>,
+}
+
+impl ChunkedSender {
+  /// Sends at most `chunk_size` bytes per `delay`, on average, see [`ChunkedSender`].
+  pub fn new(chunk_size: usize, delay: Duration) -> Self {
+    Self{ chunk_size: chunk_size.max(1), delay, retries: 3, ack: None }
+  }
+
+  /// Waits up to `timeout` for a [`Handshake`] on `events` after each message.
+  ///
+  /// A device that does not answer in time is taken to be one that never
+  /// answers, as the Sample Dump Standard prescribes, and sending goes on.
+  pub fn wait_for_ack(self, events: Receiver<TimedEvent>, timeout: Duration) -> Self {
+    self.wait_for(events, timeout, Handshake::parse)
+  }
+
+  /// Same as [`ChunkedSender::wait_for_ack`], for devices with their own
+  /// handshake messages. `classify` is given every incoming SysEx message.
+  pub fn wait_for(
+    mut self,
+    events: Receiver<TimedEvent>,
+    timeout: Duration,
+    classify: fn(&[u8]) -> Option<Handshake>
+  ) -> Self {
+    self.ack = Some(AckWait{ events, timeout, classify });
+    self
+  }
+
+  /// How many times a message is resent after a NAK. Defaults to 3.
+  pub fn retries(mut self, retries: usize) -> Self {
+    self.retries = retries;
+    self
+  }
+
+  /// Sends `dump`, one or more complete SysEx messages back to back.
+  pub fn send(&self, port: &Arc<Mutex<Output>>, dump: &[u8]) -> Result<(), String> {
+    for message in split_messages(dump) {
+      self.send_one(port, message)?;
+    }
+    Ok(())
+  }
+
+  pub fn send_message(&self, port: &Arc<Mutex<Output>>, message: &Message<SysEx>) -> Result<(), String> {
+    self.send(port, message.data())
+  }
+
+  fn send_one(&self, port: &Arc<Mutex<Output>>, message: &[u8]) -> Result<(), String> {
+    for _ in 0..=self.retries {
+      if let Some(ack) = &self.ack {
+        // answers to earlier messages are of no interest anymore
+        while ack.events.try_recv().is_ok() {}
+      }
+      // A dropped message would corrupt the dump, so wait for the port
+      // instead of giving up when it is busy.
+      Output::send_blocking(port, message)?;
+      let chunks = message.len().div_ceil(self.chunk_size).max(1);
+      sleep(self.delay.saturating_mul(u32::try_from(chunks).unwrap_or(u32::MAX)));
+      match self.handshake() {
+        None | Some(Handshake::Ack) => return Ok(()),
+        Some(Handshake::Cancel) => return Err("device cancelled the transfer".to_owned()),
+        Some(Handshake::Nak) | Some(Handshake::Wait) => continue,
+      }
+    }
+    Err(format!("device rejected a message {} times", self.retries + 1))
+  }
+
+  /// Waits for the device's answer. `None` if nobody is listening or
+  /// the device did not answer in time.
+  fn handshake(&self) -> Option<Handshake> {
+    let ack = self.ack.as_ref()?;
+    let mut deadline = Instant::now() + ack.timeout;
+    loop {
+      let MidiEvent::SysEx(data) = ack.events.recv_deadline(deadline).ok()?.event else { continue };
+      match (ack.classify)(&data) {
+        // a busy device gets another full timeout
+        Some(Handshake::Wait) => deadline = Instant::now() + ack.timeout,
+        Some(answer) => return Some(answer),
+        None => (),
+      }
+    }
+  }
+}
+
+/// Splits a dump into its `F0` .. `F7` messages. Bytes outside of
+/// a message are dropped, an unterminated last message is kept.
+/// ```
+/// use midi::message::sysex::sender::split_messages;
+/// let dump = [0xF0, 0x41, 0xF7, 0xF0, 0x42, 0x01, 0xF7];
+/// assert_eq!(split_messages(&dump), vec![&dump[..3], &dump[3..]]);
+/// ```
+pub fn split_messages(dump: &[u8]) -> Vec<&[u8]> {
+  let mut messages = vec![];
+  let mut start = None;
+  for (i, &b) in dump.iter().enumerate() {
+    match b {
+      SYSEX_BEGIN => start = Some(i),
+      SYSEX_END => if let Some(s) = start.take() { messages.push(&dump[s..=i]) },
+      _ => ()
+    }
+  }
+  if let Some(s) = start {
+    messages.push(&dump[s..]);
+  }
+  messages
+}
@@ -0,0 +1,138 @@
+use super::*;
+
+use std::fmt;
+
+/// Which bits of a named value a template token stands for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Part {
+  /// The low 7 bits.
+  Whole,
+  /// Bits 7 - 13.
+  Msb,
+  /// Bits 0 - 6.
+  Lsb,
+  /// Bits 4 - 7.
+  High,
+  /// Bits 0 - 3.
+  Low,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Token {
+  Byte(u8),
+  Value { name: String, part: Part },
+  Checksum,
+}
+
+/// A SysEx message with named holes, written as hex bytes and `{tokens}`:
+///
+/// * `{name}` the low 7 bits of the value `name`
+/// * `{name:msb}` / `{name:lsb}` the two 7 bit halves of a 14 bit value
+/// * `{name:hi}` / `{name:lo}` the two nibbles of an 8 bit value
+/// * `{checksum}` the checksum of the bytes before it
+/// ```
+/// use midi::message::sysex::{checksum::ChecksumKind, template::SysExTemplate};
+/// let template = SysExTemplate::parse("F0 41 10 42 12 40 01 30 {v} {checksum} F7").unwrap();
+/// let bytes = template.render(&[("v", 0x7F)], ChecksumKind::Roland, 5).unwrap();
+/// assert_eq!(bytes, vec![0xF0, 0x41, 0x10, 0x42, 0x12, 0x40, 0x01, 0x30, 0x7F, 0x10, 0xF7]);
+/// assert!(template.matches(&bytes));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "definitions", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(feature = "definitions", serde(try_from = "String", into = "String"))]
+pub struct SysExTemplate {
+  tokens: Vec<Token>,
+}
+
+impl SysExTemplate {
+  pub fn parse(template: &str) -> Result<Self, String> {
+    let tokens = template
+      .split_whitespace()
+      .map(|t| match t.strip_prefix('{').and_then(|t| t.strip_suffix('}')) {
+        Some("checksum") => Ok(Token::Checksum),
+        Some(value) => {
+          let (name, part) = match value.split_once(':') {
+            None => (value, Part::Whole),
+            Some((name, "msb")) => (name, Part::Msb),
+            Some((name, "lsb")) => (name, Part::Lsb),
+            Some((name, "hi")) => (name, Part::High),
+            Some((name, "lo")) => (name, Part::Low),
+            Some((_, part)) => return Err(format!("Unknown value part in SysEx template: {part}")),
+          };
+          Ok(Token::Value{ name: name.to_owned(), part })
+        },
+        None => u8::from_str_radix(t, 16)
+          .map(Token::Byte)
+          .map_err(|_| format!("Invalid byte in SysEx template: {t}"))
+      })
+      .collect::<Result<Vec<_>, _>>()?;
+    Ok(Self{ tokens })
+  }
+
+  pub fn tokens(&self) -> &[Token] { &self.tokens }
+
+  /// Fills in the template. `checksum` covers the bytes from index
+  /// `checksum_from` up to the `{checksum}` token.
+  pub fn render<C: Checksum>(&self, values: &[(&str, u16)], checksum: C, checksum_from: usize) -> Result<Vec<u8>, String> {
+    let mut out = Vec::with_capacity(self.tokens.len());
+    for token in &self.tokens {
+      let byte = match token {
+        Token::Byte(b) => *b,
+        Token::Checksum => checksum.checksum(out.get(checksum_from..).unwrap_or_default()),
+        Token::Value{ name, part } => {
+          let (_, value) = values
+            .iter()
+            .find(|(n, _)| n == name)
+            .ok_or_else(|| format!("No value given for {{{name}}}"))?;
+          match part {
+            Part::Whole | Part::Lsb => (value & 0x7f) as u8,
+            Part::Msb => (value >> 7 & 0x7f) as u8,
+            Part::High => (value >> 4 & 0x0f) as u8,
+            Part::Low => (value & 0x0f) as u8,
+          }
+        }
+      };
+      out.push(byte);
+    }
+    Ok(out)
+  }
+
+  /// Returns `true` if `sysex` starts like the template, tokens
+  /// matching any byte. Useful to recognise replies.
+  pub fn matches(&self, sysex: &[u8]) -> bool {
+    sysex.len() >= self.tokens.len()
+      && self.tokens
+        .iter()
+        .zip(sysex)
+        .all(|(t, b)| !matches!(t, Token::Byte(t) if t != b))
+  }
+}
+
+impl fmt::Display for SysExTemplate {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let tokens: Vec<String> = self.tokens
+      .iter()
+      .map(|t| match t {
+        Token::Byte(b) => format!("{b:02X}"),
+        Token::Checksum => "{checksum}".to_owned(),
+        Token::Value{ name, part } => match part {
+          Part::Whole => format!("{{{name}}}"),
+          Part::Msb => format!("{{{name}:msb}}"),
+          Part::Lsb => format!("{{{name}:lsb}}"),
+          Part::High => format!("{{{name}:hi}}"),
+          Part::Low => format!("{{{name}:lo}}"),
+        }
+      })
+      .collect();
+    write!(f, "{}", tokens.join(" "))
+  }
+}
+
+impl TryFrom<String> for SysExTemplate {
+  type Error = String;
+  fn try_from(template: String) -> Result<Self, Self::Error> { Self::parse(&template) }
+}
+
+impl From<SysExTemplate> for String {
+  fn from(template: SysExTemplate) -> Self { template.to_string() }
+}
@@ -0,0 +1,288 @@
+use super::*;
+
+use crate::consts::{
+  message::{SYSEX_BEGIN, SYSEX_END},
+  sysex::*,
+};
+
+/// Decoded Identity Reply.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Identity {
+  pub manufacturer: ManufacturerId,
+  pub family: u16,
+  pub model: u16,
+  pub version: [u8; 4],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GmMode {
+  Gm1On,
+  Gm2On,
+  Off,
+}
+
+/// The tuning of one note in the MIDI Tuning Standard: the equal tempered
+/// semitone at or below the pitch, plus a fraction of a semitone in 1/16384ths.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TuningEntry {
+  pub semitone: u8,
+  /// 14 bit fraction of a semitone above `semitone`.
+  pub fraction: u16,
+}
+
+impl TuningEntry {
+  /// Leaves a note's tuning as it is.
+  pub const NO_CHANGE: TuningEntry = TuningEntry{ semitone: 0x7f, fraction: 0x3fff };
+
+  /// `cents` above the equal tempered MIDI note `semitone`.
+  /// ```
+  /// use midi::message::sysex::universal::TuningEntry;
+  /// let entry = TuningEntry::from_cents(60, 50.0);
+  /// assert_eq!((entry.semitone, entry.fraction), (60, 0x2000));
+  /// ```
+  pub fn from_cents(semitone: u8, cents: f64) -> Self {
+    Self::from_semitones(f64::from(semitone) + cents / 100.0)
+  }
+
+  pub fn from_frequency(hz: f64) -> Self {
+    Self::from_semitones(69.0 + 12.0 * (hz / 440.0).log2())
+  }
+
+  pub fn to_frequency(&self) -> f64 {
+    440.0 * 2f64.powf((self.semitones() - 69.0) / 12.0)
+  }
+
+  /// Pitch as a fractional MIDI note number.
+  pub fn semitones(&self) -> f64 {
+    f64::from(self.semitone) + f64::from(self.fraction) / 16384.0
+  }
+
+  fn from_semitones(semitones: f64) -> Self {
+    let semitones = semitones.clamp(0.0, 127.0 + 16383.0 / 16384.0);
+    let whole = semitones.floor();
+    let fraction = ((semitones - whole) * 16384.0).round() as u16;
+    match fraction {
+      16384 => Self{ semitone: whole as u8 + 1, fraction: 0 },
+      fraction => Self{ semitone: whole as u8, fraction }
+    }
+  }
+
+  fn to_bytes(self) -> [u8; 3] {
+    [self.semitone & 0x7f, (self.fraction >> 7) as u8 & 0x7f, self.fraction as u8 & 0x7f]
+  }
+
+  fn from_bytes(b: &[u8]) -> Self {
+    Self{ semitone: b[0], fraction: u16::from(b[1]) << 7 | u16::from(b[2]) }
+  }
+}
+
+/// Sample Dump Standard header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SampleDumpHeader {
+  pub sample: u16,
+  /// Significant bits per sample, 8 - 28.
+  pub format: u8,
+  /// Sample period in nanoseconds.
+  pub period: u32,
+  /// Length in words.
+  pub length: u32,
+  pub loop_start: u32,
+  pub loop_end: u32,
+  /// 0: forward only, 1: backward/forward, 0x7F: loop off.
+  pub loop_type: u8,
+}
+
+/// Universal Non-Real Time and Real Time System Exclusive messages.
+///
+/// Every variant carries the device ID it is addressed to, `0x7F`
+/// ([`ALL_CALL`]) addressing every device.
+/// ```
+/// use midi::message::sysex::universal::Universal;
+/// use midi::consts::sysex::ALL_CALL;
+/// let volume = Universal::MasterVolume{ device: ALL_CALL, value: 0x3fff };
+/// let bytes = volume.to_sysex();
+/// assert_eq!(bytes, vec![0xF0, 0x7F, 0x7F, 0x04, 0x01, 0x7f, 0x7f, 0xF7]);
+/// assert_eq!(Universal::parse(&bytes), Some(volume));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub enum Universal {
+  IdentityRequest { device: u8 },
+  IdentityReply { device: u8, identity: Identity },
+  GeneralMidi { device: u8, mode: GmMode },
+  /// 14 bit volume.
+  MasterVolume { device: u8, value: u16 },
+  /// 14 bit balance, `0x2000` being the centre.
+  MasterBalance { device: u8, value: u16 },
+  /// 14 bit, `0x2000` is A440, the extremes -100 and +100 cents.
+  FineTuning { device: u8, value: u16 },
+  /// Semitones, `0x40` is A440, the extremes -64 and +63 semitones.
+  CoarseTuning { device: u8, value: u8 },
+  TuningDumpRequest { device: u8, program: u8 },
+  /// The tuning of all 128 notes.
+  TuningDump { device: u8, program: u8, name: String, entries: Vec<TuningEntry> },
+  /// Real time retuning of single notes.
+  TuningNoteChange { device: u8, program: u8, changes: Vec<(u8, TuningEntry)> },
+  SampleDumpHeader { device: u8, header: SampleDumpHeader },
+}
+
+impl Universal {
+  /// Returns the message framed as SysEx, `0xF0` to `0xF7`.
+  pub fn to_sysex(&self) -> Vec<u8> {
+    let (realtime, device, mut body) = match self {
+      Self::IdentityRequest{ device } => (false, device, vec![GENERAL_INFO, IDENTITY_REQUEST]),
+      Self::IdentityReply{ device, identity } => {
+        let mut body = vec![GENERAL_INFO, IDENTITY_REPLY];
+        body.extend(identity.manufacturer.to_bytes());
+        body.extend(u14(identity.family));
+        body.extend(u14(identity.model));
+        body.extend(identity.version);
+        (false, device, body)
+      },
+      Self::GeneralMidi{ device, mode } => {
+        let sub = match mode {
+          GmMode::Gm1On => GM1_ON,
+          GmMode::Gm2On => GM2_ON,
+          GmMode::Off => GM_OFF,
+        };
+        (false, device, vec![GENERAL_MIDI, sub])
+      },
+      Self::MasterVolume{ device, value } => (true, device, control(MASTER_VOLUME, *value)),
+      Self::MasterBalance{ device, value } => (true, device, control(MASTER_BALANCE, *value)),
+      Self::FineTuning{ device, value } => (true, device, control(FINE_TUNING, *value)),
+      Self::CoarseTuning{ device, value } => (true, device, control(COARSE_TUNING, u16::from(*value) << 7)),
+      Self::TuningDumpRequest{ device, program } => (false, device, vec![TUNING_STANDARD, TUNING_DUMP_REQUEST, *program]),
+      Self::TuningDump{ device, program, name, entries } => {
+        let mut body = vec![TUNING_STANDARD, TUNING_DUMP, *program];
+        let mut name: Vec<u8> = name.bytes().filter(u8::is_ascii).take(16).collect();
+        name.resize(16, b' ');
+        body.extend(name);
+        (0..128).for_each(|i| body.extend(entries.get(i).unwrap_or(&TuningEntry::NO_CHANGE).to_bytes()));
+        // covers everything from the sysex ID on
+        let covered: Vec<u8> = [NON_REALTIME, *device].iter().chain(&body).copied().collect();
+        body.push(checksum::Xor.checksum(&covered));
+        (false, device, body)
+      },
+      Self::TuningNoteChange{ device, program, changes } => {
+        let mut body = vec![TUNING_STANDARD, TUNING_NOTE_CHANGE, *program, changes.len().min(127) as u8];
+        changes.iter().take(127).for_each(|(note, entry)| {
+          body.push(note & 0x7f);
+          body.extend(entry.to_bytes());
+        });
+        (true, device, body)
+      },
+      Self::SampleDumpHeader{ device, header } => {
+        let mut body = vec![SAMPLE_DUMP_HEADER];
+        body.extend(u14(header.sample));
+        body.push(header.format);
+        for v in [header.period, header.length, header.loop_start, header.loop_end] {
+          body.extend(u21(v));
+        }
+        body.push(header.loop_type);
+        (false, device, body)
+      },
+    };
+    let id = if realtime { REALTIME } else { NON_REALTIME };
+    let mut out = vec![SYSEX_BEGIN, id, *device];
+    out.append(&mut body);
+    out.push(SYSEX_END);
+    out
+  }
+
+  /// Wraps the message in a [`Message`], ready to be sent.
+  pub fn message(&self) -> Message<SysEx<'static>> {
+    Message{ kind: SysEx{ data: Cow::Owned(self.to_sysex()) } }
+  }
+
+  /// Parses a universal SysEx message. Returns `None` for anything
+  /// else, and for a Tuning Dump with a bad checksum.
+  pub fn parse(sysex: &[u8]) -> Option<Self> {
+    let body = sysex.strip_prefix(&[SYSEX_BEGIN])?;
+    let body = body.strip_suffix(&[SYSEX_END]).unwrap_or(body);
+    let message = match *body {
+      [NON_REALTIME, device, GENERAL_INFO, IDENTITY_REQUEST, ..] => Self::IdentityRequest{ device },
+      [NON_REALTIME, device, GENERAL_INFO, IDENTITY_REPLY, ref rest @ ..] => {
+        let (manufacturer, len) = ManufacturerId::parse(rest)?;
+        let rest = &rest[len..];
+        let [f0, f1, m0, m1, v0, v1, v2, v3, ..] = *rest else { return None };
+        Self::IdentityReply{ device, identity: Identity{
+          manufacturer,
+          family: from_u14(f0, f1),
+          model: from_u14(m0, m1),
+          version: [v0, v1, v2, v3],
+        }}
+      },
+      [NON_REALTIME, device, GENERAL_MIDI, sub, ..] => {
+        let mode = match sub {
+          GM1_ON => GmMode::Gm1On,
+          GM2_ON => GmMode::Gm2On,
+          GM_OFF => GmMode::Off,
+          _ => return None
+        };
+        Self::GeneralMidi{ device, mode }
+      },
+      [REALTIME, device, DEVICE_CONTROL, sub, lsb, msb, ..] => {
+        let value = from_u14(lsb, msb);
+        match sub {
+          MASTER_VOLUME => Self::MasterVolume{ device, value },
+          MASTER_BALANCE => Self::MasterBalance{ device, value },
+          FINE_TUNING => Self::FineTuning{ device, value },
+          COARSE_TUNING => Self::CoarseTuning{ device, value: msb },
+          _ => return None
+        }
+      },
+      [NON_REALTIME, device, TUNING_STANDARD, TUNING_DUMP_REQUEST, program, ..] => {
+        Self::TuningDumpRequest{ device, program }
+      },
+      [NON_REALTIME, device, TUNING_STANDARD, TUNING_DUMP, program, ref rest @ ..] => {
+        if rest.len() < 16 + 384 + 1 { return None }
+        let (sum, covered) = body.split_last()?;
+        if !checksum::Xor.verify(covered, *sum) { return None }
+        let name = String::from_utf8_lossy(&rest[..16]).trim_end().to_owned();
+        let entries = rest[16..16 + 384].chunks(3).map(TuningEntry::from_bytes).collect();
+        Self::TuningDump{ device, program, name, entries }
+      },
+      [REALTIME, device, TUNING_STANDARD, TUNING_NOTE_CHANGE, program, count, ref rest @ ..] => {
+        let changes = rest
+          .chunks_exact(4)
+          .take(count.into())
+          .map(|c| (c[0], TuningEntry::from_bytes(&c[1..])))
+          .collect();
+        Self::TuningNoteChange{ device, program, changes }
+      },
+      [NON_REALTIME, device, SAMPLE_DUMP_HEADER, ref rest @ ..] if rest.len() >= 16 => {
+        Self::SampleDumpHeader{ device, header: SampleDumpHeader{
+          sample: from_u14(rest[0], rest[1]),
+          format: rest[2],
+          period: from_u21(&rest[3..6]),
+          length: from_u21(&rest[6..9]),
+          loop_start: from_u21(&rest[9..12]),
+          loop_end: from_u21(&rest[12..15]),
+          loop_type: rest[15],
+        }}
+      },
+      _ => return None
+    };
+    Some(message)
+  }
+}
+
+fn control(sub: u8, value: u16) -> Vec<u8> {
+  let [lsb, msb] = u14(value);
+  vec![DEVICE_CONTROL, sub, lsb, msb]
+}
+
+fn u14(v: u16) -> [u8; 2] {
+  [(v & 0x7f) as u8, (v >> 7) as u8 & 0x7f]
+}
+
+fn from_u14(lsb: u8, msb: u8) -> u16 {
+  u16::from(lsb & 0x7f) | u16::from(msb & 0x7f) << 7
+}
+
+fn u21(v: u32) -> [u8; 3] {
+  [0, 7, 14].map(|shift| (v >> shift) as u8 & 0x7f)
+}
+
+fn from_u21(b: &[u8]) -> u32 {
+  b.iter().enumerate().fold(0, |v, (i, &b)| v | u32::from(b & 0x7f) << (7 * i))
+}
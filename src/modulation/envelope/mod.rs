@@ -0,0 +1,145 @@
+use std::time::Duration;
+
+use super::Source;
+
+/// A ramp from wherever the envelope is to `level`, over `time`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Segment {
+  pub level: f32,
+  pub time: Duration,
+}
+
+impl Segment {
+  pub fn new(level: f32, time: Duration) -> Self {
+    Self{ level: level.clamp(0.0, 1.0), time }
+  }
+}
+
+/// A multi-segment envelope, run once from start to end.
+///
+/// With a sustain segment, the envelope holds at the end of it until
+/// released, and then goes on with the segments after it. A release
+/// before the sustain segment is reached skips straight to them, from
+/// the level the envelope is at.
+/// ```
+/// use std::time::Duration;
+/// use midi::modulation::{Source, envelope::Envelope};
+/// let ms = Duration::from_millis;
+/// let mut env = Envelope::adsr(ms(100), ms(100), 0.5, ms(200));
+/// assert_eq!(env.next(ms(0)), Some(0.0));
+/// assert_eq!(env.next(ms(50)), Some(0.5));
+/// assert_eq!(env.next(ms(100)), Some(0.75));
+/// // held at the sustain level
+/// assert_eq!(env.next(ms(1000)), Some(0.5));
+/// env.release();
+/// assert_eq!(env.next(ms(100)), Some(0.25));
+/// assert_eq!(env.next(ms(100)), Some(0.0));
+/// assert_eq!(env.next(ms(100)), None);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Envelope {
+  start: f32,
+  segments: Vec<Segment>,
+  sustain: Option<usize>,
+  /// Segment being played, and how far into it.
+  segment: usize,
+  elapsed: Duration,
+  /// Level the current segment ramps from.
+  from: f32,
+  level: f32,
+  released: bool,
+  finished: bool,
+}
+
+impl Envelope {
+  /// Starts at `start`, and ramps through `segments` in order.
+  pub fn new(start: f32, segments: Vec<Segment>) -> Self {
+    let start = start.clamp(0.0, 1.0);
+    Self{
+      start,
+      segments,
+      sustain: None,
+      segment: 0,
+      elapsed: Duration::ZERO,
+      from: start,
+      level: start,
+      released: false,
+      finished: false,
+    }
+  }
+
+  /// Attack to full, decay to `sustain`, and release to 0 once let go.
+  pub fn adsr(attack: Duration, decay: Duration, sustain: f32, release: Duration) -> Self {
+    Self::new(0.0, vec![
+      Segment::new(1.0, attack),
+      Segment::new(sustain, decay),
+      Segment::new(0.0, release),
+    ]).sustain(1)
+  }
+
+  /// Holds at the end of segment `index` until released.
+  pub fn sustain(mut self, index: usize) -> Self {
+    self.sustain = (index < self.segments.len()).then_some(index);
+    self
+  }
+
+  /// Starts over from the first segment, ramping from the current level.
+  pub fn retrigger(&mut self) {
+    self.segment = 0;
+    self.elapsed = Duration::ZERO;
+    self.from = self.level;
+    self.released = false;
+    self.finished = false;
+  }
+
+  /// Starts over from the start level.
+  pub fn reset(&mut self) {
+    self.retrigger();
+    self.from = self.start;
+    self.level = self.start;
+  }
+
+  pub fn level(&self) -> f32 { self.level }
+
+  fn holding(&self) -> bool {
+    !self.released && self.sustain.is_some_and(|s| self.segment > s)
+  }
+}
+
+impl Source for Envelope {
+  fn next(&mut self, dt: Duration) -> Option<f32> {
+    if self.finished { return None }
+    if self.holding() { return Some(self.level) }
+    self.elapsed += dt;
+    while let Some(segment) = self.segments.get(self.segment) {
+      if self.elapsed < segment.time {
+        let t = self.elapsed.as_secs_f32() / segment.time.as_secs_f32();
+        self.level = self.from + (segment.level - self.from) * t;
+        return Some(self.level)
+      }
+      self.elapsed -= segment.time;
+      self.level = segment.level;
+      self.from = segment.level;
+      self.segment += 1;
+      if self.holding() {
+        self.elapsed = Duration::ZERO;
+        return Some(self.level)
+      }
+    }
+    // the last level is sent before the envelope ends
+    self.finished = true;
+    Some(self.level)
+  }
+
+  fn release(&mut self) {
+    if self.released { return }
+    self.released = true;
+    if let Some(s) = self.sustain {
+      if self.segment <= s {
+        self.segment = s + 1;
+        self.elapsed = Duration::ZERO;
+        self.from = self.level;
+      }
+    }
+  }
+}
@@ -0,0 +1,118 @@
+use std::{
+  f32::consts::PI,
+  time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use super::{Rate, Source};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Wave {
+  Sine,
+  Triangle,
+  SawUp,
+  SawDown,
+  Square,
+  /// A new random value every cycle, held until the next.
+  SampleAndHold,
+  /// Random values, glided between over a cycle.
+  SmoothRandom,
+}
+
+/// xorshift, plenty for modulation and without a dependency.
+struct Rng(u64);
+
+impl Rng {
+  fn new() -> Self {
+    let seed = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_nanos() as u64);
+    Self(seed | 1)
+  }
+
+  /// -1.0 - 1.0
+  fn next(&mut self) -> f32 {
+    self.0 ^= self.0 << 13;
+    self.0 ^= self.0 >> 7;
+    self.0 ^= self.0 << 17;
+    (self.0 >> 40) as f32 / (1u64 << 23) as f32 - 1.0
+  }
+}
+
+/// A low frequency oscillator.
+///
+/// The wave swings `depth` wide around `centre`, by default over the whole
+/// 0.0 - 1.0 range, and is clamped to it.
+/// ```
+/// use std::time::Duration;
+/// use midi::modulation::{Source, Rate, lfo::{Lfo, Wave}};
+/// let mut tremolo = Lfo::new(Wave::Square, Rate::Hz(4.0)).depth(0.5).centre(0.75);
+/// assert_eq!(tremolo.next(Duration::ZERO), Some(1.0));
+/// assert_eq!(tremolo.next(Duration::from_millis(125)), Some(0.5));
+/// ```
+pub struct Lfo {
+  wave: Wave,
+  rate: Rate,
+  depth: f32,
+  centre: f32,
+  phase: f32,
+  random: (f32, f32),
+  rng: Rng,
+}
+
+impl Lfo {
+  pub fn new(wave: Wave, rate: Rate) -> Self {
+    let mut rng = Rng::new();
+    let random = (rng.next(), rng.next());
+    Self{ wave, rate, depth: 1.0, centre: 0.5, phase: 0.0, random, rng }
+  }
+
+  /// Peak to peak swing, 0.0 - 1.0.
+  pub fn depth(mut self, depth: f32) -> Self {
+    self.depth = depth.clamp(0.0, 1.0);
+    self
+  }
+
+  pub fn centre(mut self, centre: f32) -> Self {
+    self.centre = centre.clamp(0.0, 1.0);
+    self
+  }
+
+  /// Where in the cycle to start, 0.0 - 1.0.
+  pub fn phase(mut self, phase: f32) -> Self {
+    self.phase = phase.rem_euclid(1.0);
+    self
+  }
+
+  pub fn set_rate(&mut self, rate: Rate) { self.rate = rate }
+
+  /// The wave at the current phase, -1.0 - 1.0.
+  fn wave(&self) -> f32 {
+    let p = self.phase;
+    match self.wave {
+      Wave::Sine => (2.0 * PI * p).sin(),
+      Wave::Triangle => if p < 0.5 { 4.0 * p - 1.0 } else { 3.0 - 4.0 * p },
+      Wave::SawUp => 2.0 * p - 1.0,
+      Wave::SawDown => 1.0 - 2.0 * p,
+      Wave::Square => if p < 0.5 { 1.0 } else { -1.0 },
+      Wave::SampleAndHold => self.random.0,
+      Wave::SmoothRandom => {
+        let (from, to) = self.random;
+        let t = (1.0 - (PI * p).cos()) / 2.0;
+        from + (to - from) * t
+      },
+    }
+  }
+}
+
+impl Source for Lfo {
+  fn next(&mut self, dt: Duration) -> Option<f32> {
+    let phase = self.phase + dt.as_secs_f32() * self.rate.hz();
+    // a new cycle draws the next random value
+    for _ in 0..(phase.floor() as u32).min(2) {
+      self.random = match self.wave {
+        Wave::SmoothRandom => (self.random.1, self.rng.next()),
+        _ => (self.rng.next(), self.random.1),
+      };
+    }
+    self.phase = phase.rem_euclid(1.0);
+    Some((self.centre + self.wave() * self.depth / 2.0).clamp(0.0, 1.0))
+  }
+}
@@ -0,0 +1,215 @@
+pub mod lfo;
+pub mod envelope;
+
+use std::time::{Duration, Instant};
+
+use crate::{
+  message::{cc::Cc, cc14::Cc14, nrpn::Nrpn, pitchbend::PitchBend, FourteenBit, Message},
+  scheduler::{Handle, Scheduler},
+  transport::Tempo,
+  util::Channel,
+  Arc,
+  Mutex,
+};
+
+/// Something that changes over time, such as an [`lfo::Lfo`] or an
+/// [`envelope::Envelope`].
+pub trait Source: Send {
+  /// Moves the source on by `dt`, returning its value, 0.0 - 1.0, or
+  /// `None` once it has finished.
+  fn next(&mut self, dt: Duration) -> Option<f32>;
+
+  /// Lets go of the source, starting the release of an envelope.
+  /// Does nothing for sources that run until stopped.
+  fn release(&mut self) {}
+}
+
+/// How fast a source cycles: in Hz, or in beats of a [`Tempo`] it follows.
+#[derive(Clone)]
+pub enum Rate {
+  Hz(f32),
+  /// One cycle every `beats` quarter notes.
+  Sync { tempo: Tempo, beats: f64 },
+}
+
+impl Rate {
+  pub fn hz(&self) -> f32 {
+    match self {
+      Self::Hz(hz) => *hz,
+      Self::Sync{ tempo, beats } => (tempo.bpm() / 60.0 / beats.max(f64::EPSILON)) as f32,
+    }
+  }
+}
+
+/// A message a [`Modulator`] can send its values as.
+pub trait Target: Send + 'static {
+  /// Largest value the message carries.
+  const MAX: u16;
+  /// The bytes of the message carrying `value`, which is at most `MAX`.
+  fn bytes(&mut self, value: u16, ch: Channel) -> Vec<u8>;
+  /// Called once the bytes last returned by [`Target::bytes`] were sent.
+  fn sent(&mut self) {}
+}
+
+impl Target for Message<Cc> {
+  const MAX: u16 = Cc::MAX as u16;
+  fn bytes(&mut self, value: u16, ch: Channel) -> Vec<u8> {
+    let _ = self.update_value(value as u8);
+    self.to_bytes(ch)
+  }
+}
+
+impl Target for Message<Cc14> {
+  const MAX: u16 = Cc14::MAX;
+  /// An unchanged LSB is left out if [`Message::lsb_on_change`] is set.
+  fn bytes(&mut self, value: u16, ch: Channel) -> Vec<u8> {
+    let _ = self.update_value(value);
+    self.to_bytes(ch)
+  }
+
+  fn sent(&mut self) { self.mark_sent() }
+}
+
+impl Target for Message<Nrpn> {
+  const MAX: u16 = Nrpn::MAX;
+  fn bytes(&mut self, value: u16, ch: Channel) -> Vec<u8> {
+    if let Ok(val) = Nrpn::split(value) { let _ = self.update_value(&val); }
+    self.to_bytes(ch)
+  }
+}
+
+impl Target for Message<PitchBend> {
+  const MAX: u16 = 0x3fff;
+  fn bytes(&mut self, value: u16, ch: Channel) -> Vec<u8> {
+    self.set_bend(PitchBend::from_raw(value));
+    self.to_bytes(ch)
+  }
+}
+
+struct State<T: Target> {
+  source: Box<dyn Source>,
+  target: T,
+  ch: Channel,
+  range: (u16, u16),
+  dedup: bool,
+  last_tick: Option<Instant>,
+  last_value: Option<u16>,
+}
+
+impl<T: Target> State<T> {
+  fn tick(&mut self, now: Instant) -> Option<Vec<u8>> {
+    let dt = self.last_tick.map_or(Duration::ZERO, |t| now - t);
+    self.last_tick = Some(now);
+    let v = self.source.next(dt)?;
+    let (min, max) = self.range;
+    let value = (f32::from(min) + (f32::from(max) - f32::from(min)) * v.clamp(0.0, 1.0)).round() as u16;
+    if self.dedup && self.last_value == Some(value) { return Some(vec![]) }
+    self.last_value = Some(value);
+    Some(self.target.bytes(value, self.ch))
+  }
+}
+
+/// Streams the values of a [`Source`] to a CC, 14 bit CC, NRPN or pitch
+/// bend, from a [`Scheduler`].
+///
+/// The source is read every [`Modulator::rate_limit`], 10 ms unless
+/// changed, which also caps how many messages are sent. Values that come
+/// out the same as the last one sent are skipped, unless
+/// [`Modulator::dedup`] is turned off.
+/// ```no_run
+/// use std::time::Duration;
+/// use midi::{
+///   connection::Output,
+///   message::Message,
+///   modulation::{Modulator, Rate, lfo::{Lfo, Wave}, envelope::Envelope},
+///   scheduler::Scheduler,
+///   transport::Tempo,
+///   util::Channel,
+/// };
+/// let scheduler = Scheduler::new(Output::new("IAC Driver Bus 1", |_| {}).unwrap());
+/// // a filter sweep over two beats, between cutoff 20 and 100
+/// let tempo = Tempo::new(120.0);
+/// let sweep = Lfo::new(Wave::Triangle, Rate::Sync{ tempo, beats: 2.0 });
+/// let cutoff = Modulator::new(sweep, Message::cc(74, 0).unwrap(), Channel(0)).range(20, 100);
+/// let running = cutoff.start(&scheduler);
+///
+/// // a swell on the mod wheel that fades out once let go
+/// let swell = Envelope::adsr(Duration::from_secs(2), Duration::ZERO, 1.0, Duration::from_secs(1));
+/// let wheel = Modulator::new(swell, Message::cc14(1, 0).unwrap(), Channel(0)).start(&scheduler);
+/// midi::transport::sleep(Duration::from_secs(4));
+/// wheel.release();
+/// running.stop();
+/// ```
+pub struct Modulator<T: Target> {
+  state: State<T>,
+  interval: Duration,
+}
+
+impl<T: Target> Modulator<T> {
+  pub fn new(source: impl Source + 'static, target: T, ch: Channel) -> Self {
+    Self{
+      state: State{
+        source: Box::new(source),
+        target,
+        ch,
+        range: (0, T::MAX),
+        dedup: true,
+        last_tick: None,
+        last_value: None,
+      },
+      interval: Duration::from_millis(10),
+    }
+  }
+
+  /// The values the source's 0.0 - 1.0 is scaled to, clamped to what
+  /// the target carries. `min` may be above `max` to turn the source upside down.
+  pub fn range(mut self, min: u16, max: u16) -> Self {
+    self.state.range = (min.min(T::MAX), max.min(T::MAX));
+    self
+  }
+
+  /// The shortest time between two messages.
+  pub fn rate_limit(mut self, interval: Duration) -> Self {
+    self.interval = interval.max(Duration::from_millis(1));
+    self
+  }
+
+  /// Skip values that are the same as the last one sent.
+  pub fn dedup(mut self, on: bool) -> Self {
+    self.state.dedup = on;
+    self
+  }
+
+  /// Starts sending, until the source finishes or [`Running::stop`] is called.
+  pub fn start(self, scheduler: &Scheduler) -> Running {
+    let state = Arc::new(Mutex::new(self.state));
+    let task = state.clone();
+    let sent = state.clone();
+    let handle = scheduler.repeat_confirmed(
+      self.interval,
+      move |now| task.lock().ok()?.tick(now),
+      Some(Box::new(move || if let Ok(mut s) = sent.lock() { s.target.sent() })),
+    );
+    let release: Box<dyn Fn() + Send> = Box::new(move || {
+      if let Ok(mut s) = state.lock() { s.source.release() }
+    });
+    Running{ handle, release }
+  }
+}
+
+/// A [`Modulator`] sending from a [`Scheduler`]. Dropping it leaves the
+/// modulator running.
+pub struct Running {
+  handle: Handle,
+  release: Box<dyn Fn() + Send>,
+}
+
+impl Running {
+  /// See [`Source::release`].
+  pub fn release(&self) { (self.release)() }
+
+  /// Stops sending right away. Returns `false` if it had already stopped.
+  pub fn stop(&self) -> bool { self.handle.cancel() }
+
+  pub fn is_running(&self) -> bool { self.handle.is_pending() }
+}
@@ -0,0 +1,395 @@
+use crate::{
+  connection::Output,
+  consts::{
+    message::{NRPN_LSB, NRPN_MSB, RPN_LSB, RPN_MSB, RPN_VAL_MSB},
+    mpe::{MASTER_BEND_RANGE, MEMBER_BEND_RANGE, TIMBRE},
+  },
+  message::{
+    cc::Cc,
+    event::MidiEvent,
+    note::{NoteOff, NoteOn},
+    pitchbend::{BendDecoder, BendRange, PitchBend},
+    pressure::ChannelPressure,
+    rpn::RpnKind,
+    Message,
+    MessageKind,
+  },
+  util::Channel,
+  Arc,
+  Mutex,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Zone {
+  /// Master channel 1, members counting up from channel 2.
+  Lower,
+  /// Master channel 16, members counting down from channel 15.
+  Upper,
+}
+
+impl Zone {
+  pub fn master(&self) -> Channel {
+    match self {
+      Self::Lower => Channel(0),
+      Self::Upper => Channel(15),
+    }
+  }
+}
+
+/// Layout of one MPE zone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ZoneConfig {
+  pub zone: Zone,
+  /// Number of member channels, 0 - 15. 0 turns the zone off.
+  pub members: u8,
+  /// Pitch bend range of the member channels, in semitones.
+  pub member_bend_range: u8,
+  /// Pitch bend range of the master channel, in semitones.
+  pub master_bend_range: u8,
+}
+
+impl ZoneConfig {
+  pub fn new(zone: Zone, members: u8) -> Result<Self, String> {
+    if members > 15 {
+      return Err(format!("An MPE zone can have at most 15 member channels, got {members}"))
+    }
+    Ok(Self{
+      zone,
+      members,
+      member_bend_range: MEMBER_BEND_RANGE,
+      master_bend_range: MASTER_BEND_RANGE
+    })
+  }
+
+  pub fn master(&self) -> Channel { self.zone.master() }
+
+  /// Returns the member channels, closest to the master channel first.
+  pub fn member_channels(&self) -> Vec<Channel> {
+    (1..=self.members)
+      .map(|i| match self.zone {
+        Zone::Lower => Channel(i),
+        Zone::Upper => Channel(15 - i),
+      })
+      .collect()
+  }
+
+  pub fn is_member(&self, ch: Channel) -> bool {
+    match self.zone {
+      Zone::Lower => (1..=self.members).contains(&ch.0),
+      Zone::Upper => ch.0 < 15 && 15 - ch.0 <= self.members,
+    }
+  }
+
+  /// Sends the MPE Configuration Message (RPN 6) on the master channel,
+  /// followed by the pitch bend ranges (RPN 0) of the master and member channels.
+  pub fn configure(&self, port: &Arc<Mutex<Output>>) -> Result<(), String> {
+    let mcm = Message::rpn(RpnKind::MpeConfig, (self.members, 0)).map_err(|e| e.to_string())?;
+    Output::send_blocking(port, &mcm.to_bytes(self.master()))?;
+    if self.members == 0 { return Ok(()) }
+    let master_range = Message::rpn(RpnKind::PitchBend, (self.master_bend_range, 0)).map_err(|e| e.to_string())?;
+    Output::send_blocking(port, &master_range.to_bytes(self.master()))?;
+    let member_range = Message::rpn(RpnKind::PitchBend, (self.member_bend_range, 0)).map_err(|e| e.to_string())?;
+    self.member_channels()
+      .into_iter()
+      .try_for_each(|ch| Output::send_blocking(port, &member_range.to_bytes(ch)))
+  }
+}
+
+/// A note sounding on its own member channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MpeNote {
+  pub ch: Channel,
+  pub note: u8,
+}
+
+struct Slot {
+  ch: Channel,
+  note: Option<u8>,
+  last_used: u64,
+}
+
+/// Sends MPE notes, giving each note a member channel of a zone.
+///
+/// Free channels are handed out least recently used first, so that the
+/// release tail of one note is not disturbed by the next. When every
+/// member channel is busy the oldest note is released to make room.
+/// ```
+/// use midi::{connection::Output, mpe::{MpeOutput, Zone, ZoneConfig}};
+/// if let Ok(port) = Output::new("IAC Driver Bus 1", |_| {}) {
+///   let mut mpe = MpeOutput::new(ZoneConfig::new(Zone::Lower, 15).unwrap());
+///   mpe.configure(&port).unwrap();
+///   let note = mpe.note_on(&port, 60, 100).unwrap();
+///   mpe.pitch_bend(&port, note, 0.5).unwrap(); // quarter tone up
+///   mpe.timbre(&port, note, 90).unwrap();
+///   mpe.pressure(&port, note, 40).unwrap();
+///   mpe.note_off(&port, note).unwrap();
+/// }
+/// ```
+pub struct MpeOutput {
+  config: ZoneConfig,
+  slots: Vec<Slot>,
+  clock: u64,
+}
+
+impl MpeOutput {
+  pub fn new(config: ZoneConfig) -> Self {
+    let slots = config
+      .member_channels()
+      .into_iter()
+      .map(|ch| Slot{ ch, note: None, last_used: 0 })
+      .collect();
+    Self{ config, slots, clock: 0 }
+  }
+
+  pub fn config(&self) -> &ZoneConfig { &self.config }
+
+  /// See [`ZoneConfig::configure`].
+  pub fn configure(&self, port: &Arc<Mutex<Output>>) -> Result<(), String> {
+    self.config.configure(port)
+  }
+
+  /// Returns every note currently holding a member channel.
+  pub fn active(&self) -> Vec<MpeNote> {
+    self.slots
+      .iter()
+      .filter_map(|s| s.note.map(|note| MpeNote{ ch: s.ch, note }))
+      .collect()
+  }
+
+  /// Allocates a member channel and starts `note` on it. The channel's
+  /// pitch bend is centred first, so the note does not start out of tune.
+  ///
+  /// If the zone has no member channels the note is sent on the master channel.
+  /// Returns an error if a message could not be built or sent.
+  pub fn note_on(&mut self, port: &Arc<Mutex<Output>>, note: u8, velo: u8) -> Result<MpeNote, String> {
+    self.clock += 1;
+    let Some(i) = self.allocate() else {
+      let n = MpeNote{ ch: self.config.master(), note };
+      send(port, n.ch, NoteOn{ note, velo })?;
+      return Ok(n)
+    };
+    if let Some(stolen) = self.slots[i].note.take() {
+      send(port, self.slots[i].ch, NoteOff{ note: stolen })?;
+    }
+    let slot = &mut self.slots[i];
+    slot.note = Some(note);
+    slot.last_used = self.clock;
+    let n = MpeNote{ ch: slot.ch, note };
+    send(port, n.ch, PitchBend::default())?;
+    send(port, n.ch, NoteOn{ note, velo })?;
+    Ok(n)
+  }
+
+  /// Releases `note` and frees its member channel.
+  pub fn note_off(&mut self, port: &Arc<Mutex<Output>>, note: MpeNote) -> Result<(), String> {
+    self.clock += 1;
+    if let Some(slot) = self.slots.iter_mut().find(|s| s.ch == note.ch && s.note == Some(note.note)) {
+      slot.note = None;
+      slot.last_used = self.clock;
+    }
+    send(port, note.ch, NoteOff{ note: note.note })
+  }
+
+  /// Bends `note` by `semitones`, within the member pitch bend range.
+  pub fn pitch_bend(&self, port: &Arc<Mutex<Output>>, note: MpeNote, semitones: f32) -> Result<(), String> {
+    let range = BendRange::from(self.config.member_bend_range);
+    send(port, note.ch, PitchBend::from_semitones(semitones, range))
+  }
+
+  /// Sends the third dimension of control, CC 74, for `note`.
+  pub fn timbre(&self, port: &Arc<Mutex<Output>>, note: MpeNote, val: u8) -> Result<(), String> {
+    send(port, note.ch, Cc{ addr: TIMBRE, val: val.min(Cc::MAX) })
+  }
+
+  /// Sends channel pressure for `note`.
+  pub fn pressure(&self, port: &Arc<Mutex<Output>>, note: MpeNote, val: u8) -> Result<(), String> {
+    send(port, note.ch, ChannelPressure{ val: val.min(127) })
+  }
+
+  /// Picks a free member channel, or the one holding the oldest note.
+  fn allocate(&self) -> Option<usize> {
+    let free = self.slots
+      .iter()
+      .enumerate()
+      .filter(|(_, s)| s.note.is_none())
+      .min_by_key(|(_, s)| s.last_used);
+    let oldest = || self.slots
+      .iter()
+      .enumerate()
+      .min_by_key(|(_, s)| s.last_used);
+    free.or_else(oldest).map(|(i, _)| i)
+  }
+}
+
+fn send<T: MessageKind>(port: &Arc<Mutex<Output>>, ch: Channel, kind: T) -> Result<(), String> {
+  let msg = Message::new(kind).map_err(|e| e.to_string())?;
+  Output::send_blocking(port, &msg.to_bytes(ch))
+}
+
+/// Cuts a zone down to what is left next to a zone of `other` members.
+fn shrink(mut zone: ZoneConfig, other: u8) -> Option<ZoneConfig> {
+  zone.members = zone.members.min(14u8.saturating_sub(other));
+  (zone.members > 0).then_some(zone)
+}
+
+/// Per-note expression, decoded from incoming MPE traffic.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MpeEvent {
+  NoteOn { note: MpeNote, velo: u8 },
+  NoteOff { note: MpeNote, velo: u8 },
+  /// Pitch offset of the note, in semitones.
+  PitchBend { note: MpeNote, semitones: f32 },
+  Timbre { note: MpeNote, val: u8 },
+  Pressure { note: MpeNote, val: u8 },
+  /// A message on a master channel, affecting the whole zone.
+  Master { zone: Zone, event: MidiEvent },
+  /// A zone was set up, or turned off, by an MPE Configuration Message.
+  Configured(ZoneConfig),
+}
+
+#[derive(Clone, Copy, Default)]
+struct RpnState {
+  msb: Option<u8>,
+  lsb: Option<u8>,
+}
+
+/// Turns incoming MIDI into per-note [`MpeEvent`]s.
+///
+/// Zones are taken from the MPE Configuration Messages the sender
+/// transmits; until one arrives, [`MpeReceiver::default`] assumes a lower
+/// zone using all 15 member channels. Pitch bend ranges set through RPN 0
+/// are followed as well, by a [`BendDecoder`].
+/// ```
+/// use midi::{message::event::MidiEvent, mpe::{MpeEvent, MpeReceiver}, util::Channel};
+/// let mut mpe = MpeReceiver::default();
+/// mpe.handle(&MidiEvent::NoteOn{ ch: Channel(3), note: 60, velo: 90 });
+/// let bend = mpe.handle(&MidiEvent::PitchBend{ ch: Channel(3), value: 0x3000 });
+/// assert!(matches!(bend, Some(MpeEvent::PitchBend{ semitones, .. }) if semitones == 24.0));
+/// ```
+pub struct MpeReceiver {
+  lower: Option<ZoneConfig>,
+  upper: Option<ZoneConfig>,
+  notes: [Option<u8>; 16],
+  bends: BendDecoder,
+  rpn: [RpnState; 16],
+}
+
+impl Default for MpeReceiver {
+  fn default() -> Self {
+    Self::new(ZoneConfig::new(Zone::Lower, 15).ok(), None)
+  }
+}
+
+impl MpeReceiver {
+  pub fn new(lower: Option<ZoneConfig>, upper: Option<ZoneConfig>) -> Self {
+    let mut receiver = Self{
+      lower: None,
+      upper: None,
+      notes: [None; 16],
+      bends: BendDecoder::new(),
+      rpn: [RpnState::default(); 16],
+    };
+    lower.into_iter().chain(upper).for_each(|z| receiver.set_zone(z));
+    receiver
+  }
+
+  pub fn zone(&self, zone: Zone) -> Option<&ZoneConfig> {
+    match zone {
+      Zone::Lower => self.lower.as_ref(),
+      Zone::Upper => self.upper.as_ref(),
+    }
+  }
+
+  /// Feeds one incoming message, returning what it means for the zone, if anything.
+  pub fn handle(&mut self, event: &MidiEvent) -> Option<MpeEvent> {
+    let ch = event.channel()?;
+    if let MidiEvent::Cc{ addr, val, .. } = event {
+      self.bends.handle(event);
+      let range = self.bends.range(ch).semitones;
+      for zone in [self.lower.as_mut(), self.upper.as_mut()].into_iter().flatten() {
+        if zone.master() == ch { zone.master_bend_range = range }
+      }
+      if let Some(configured) = self.track_config(ch, *addr, *val) {
+        return Some(configured)
+      }
+    }
+    if let Some(zone) = self.master_of(ch) {
+      return Some(MpeEvent::Master{ zone, event: event.clone() })
+    }
+    if !self.is_member(ch) { return None }
+
+    let i = usize::from(ch.0);
+    match *event {
+      MidiEvent::NoteOn{ note, velo, .. } if velo > 0 => {
+        self.notes[i] = Some(note);
+        Some(MpeEvent::NoteOn{ note: MpeNote{ ch, note }, velo })
+      },
+      MidiEvent::NoteOn{ note, velo, .. } | MidiEvent::NoteOff{ note, velo, .. } => {
+        if self.notes[i] == Some(note) { self.notes[i] = None }
+        Some(MpeEvent::NoteOff{ note: MpeNote{ ch, note }, velo })
+      },
+      MidiEvent::PitchBend{ .. } => {
+        let note = MpeNote{ ch, note: self.notes[i]? };
+        Some(MpeEvent::PitchBend{ note, semitones: self.bends.handle(event)?.semitones })
+      },
+      MidiEvent::Cc{ addr: TIMBRE, val, .. } => {
+        Some(MpeEvent::Timbre{ note: MpeNote{ ch, note: self.notes[i]? }, val })
+      },
+      MidiEvent::ChannelPressure{ pressure, .. } => {
+        Some(MpeEvent::Pressure{ note: MpeNote{ ch, note: self.notes[i]? }, val: pressure })
+      },
+      _ => None
+    }
+  }
+
+  fn set_zone(&mut self, config: ZoneConfig) {
+    // A new zone takes its member channels from the other one.
+    match config.zone {
+      Zone::Lower => {
+        self.upper = self.upper.and_then(|z| shrink(z, config.members));
+        self.lower = (config.members > 0).then_some(config);
+      },
+      Zone::Upper => {
+        self.lower = self.lower.and_then(|z| shrink(z, config.members));
+        self.upper = (config.members > 0).then_some(config);
+      },
+    }
+    config.member_channels().into_iter().for_each(|ch| self.bends.set_range(ch, config.member_bend_range.into()));
+  }
+
+  fn master_of(&self, ch: Channel) -> Option<Zone> {
+    [self.lower, self.upper]
+      .into_iter()
+      .flatten()
+      .find(|z| z.master() == ch)
+      .map(|z| z.zone)
+  }
+
+  fn is_member(&self, ch: Channel) -> bool {
+    [self.lower, self.upper].into_iter().flatten().any(|z| z.is_member(ch))
+  }
+
+  /// Follows RPN selection on `ch` and applies MPE Configuration Messages.
+  /// Pitch bend ranges are left to the [`BendDecoder`].
+  fn track_config(&mut self, ch: Channel, addr: u8, val: u8) -> Option<MpeEvent> {
+    let state = &mut self.rpn[usize::from(ch.0)];
+    match addr {
+      RPN_MSB => state.msb = Some(val),
+      RPN_LSB => state.lsb = Some(val),
+      // an NRPN takes data entry over
+      NRPN_MSB | NRPN_LSB => *state = RpnState::default(),
+      RPN_VAL_MSB if (state.msb, state.lsb) == (Some(0), Some(RpnKind::MpeConfig as u8)) => {
+        let zone = match ch.0 { 0 => Zone::Lower, 15 => Zone::Upper, _ => return None };
+        let mut config = ZoneConfig::new(zone, val.min(15)).ok()?;
+        if let Some(current) = self.zone(zone) {
+          config.member_bend_range = current.member_bend_range;
+          config.master_bend_range = current.master_bend_range;
+        }
+        self.set_zone(config);
+        return Some(MpeEvent::Configured(config))
+      },
+      _ => ()
+    }
+    None
+  }
+}
@@ -0,0 +1,178 @@
+/// Chord qualities, by the intervals above the root.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Quality {
+  Major,
+  Minor,
+  Diminished,
+  Augmented,
+  Sus2,
+  Sus4,
+  Major6,
+  Minor6,
+  Dominant7,
+  Major7,
+  Minor7,
+  MinorMajor7,
+  HalfDiminished7,
+  Diminished7,
+  Add9,
+  Dominant9,
+  Major9,
+  Minor9,
+}
+
+impl Quality {
+  /// Semitones above the root, the root included.
+  pub fn intervals(&self) -> &'static [u8] {
+    match self {
+      Self::Major => &[0, 4, 7],
+      Self::Minor => &[0, 3, 7],
+      Self::Diminished => &[0, 3, 6],
+      Self::Augmented => &[0, 4, 8],
+      Self::Sus2 => &[0, 2, 7],
+      Self::Sus4 => &[0, 5, 7],
+      Self::Major6 => &[0, 4, 7, 9],
+      Self::Minor6 => &[0, 3, 7, 9],
+      Self::Dominant7 => &[0, 4, 7, 10],
+      Self::Major7 => &[0, 4, 7, 11],
+      Self::Minor7 => &[0, 3, 7, 10],
+      Self::MinorMajor7 => &[0, 3, 7, 11],
+      Self::HalfDiminished7 => &[0, 3, 6, 10],
+      Self::Diminished7 => &[0, 3, 6, 9],
+      Self::Add9 => &[0, 4, 7, 14],
+      Self::Dominant9 => &[0, 4, 7, 10, 14],
+      Self::Major9 => &[0, 4, 7, 11, 14],
+      Self::Minor9 => &[0, 3, 7, 10, 14],
+    }
+  }
+}
+
+/// A chord on a root note, in root position or inverted.
+///
+/// [`Chord::notes`] can be handed straight to the `chord on:` arm of [`midi!`](crate::midi).
+/// ```
+/// use midi::note::chord::{Chord, Quality};
+/// let c7 = Chord::new(60, Quality::Dominant7);
+/// assert_eq!(c7.notes(), vec![60, 64, 67, 70]);
+/// // second inversion: the fifth in the bass
+/// assert_eq!(c7.inversion(2).notes(), vec![67, 70, 72, 76]);
+/// ```
+/// ```no_run
+/// use midi::{midi, connection::Output, note::chord::{Chord, Quality}, util::Channel};
+/// let port = Output::new("IAC Driver Bus 1", |_| {}).unwrap();
+/// let ch = Channel(0);
+/// let am7 = Chord::new(57, Quality::Minor7).notes();
+/// midi!{
+///   chord on: am7, 100, port, ch;
+///   wait: 500;
+///   chord off: am7, port, ch;
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Chord {
+  pub root: u8,
+  pub quality: Quality,
+  /// How many of the lowest notes are moved up an octave.
+  pub inversion: usize,
+}
+
+impl Chord {
+  pub fn new(root: u8, quality: Quality) -> Self {
+    Self{ root, quality, inversion: 0 }
+  }
+
+  pub fn inversion(mut self, inversion: usize) -> Self {
+    self.inversion = inversion;
+    self
+  }
+
+  /// The notes of the chord from low to high. Notes above 127 are left out.
+  pub fn notes(&self) -> Vec<u8> {
+    let intervals = self.quality.intervals();
+    let mut notes: Vec<u16> = intervals.iter().map(|i| u16::from(self.root) + u16::from(*i)).collect();
+    for i in 0..self.inversion {
+      let octaves = 1 + i / intervals.len();
+      notes[i % intervals.len()] += 12 * octaves as u16;
+    }
+    notes.sort_unstable();
+    notes.into_iter().filter_map(|n| u8::try_from(n).ok().filter(|n| *n < 128)).collect()
+  }
+}
+
+/// Places the notes of `to` so that the voices of `from` move as little
+/// as possible. Only the pitch classes of `to` count, not their octaves.
+///
+/// Chords of up to 8 notes are voiced by trying every assignment of
+/// notes to voices, larger ones by moving each note close to `from`.
+/// ```
+/// use midi::note::chord::{voice_lead, Chord, Quality};
+/// let c = Chord::new(60, Quality::Major).notes();
+/// let f = Chord::new(65, Quality::Major).notes();
+/// // C E G to F A C, the C staying where it is
+/// assert_eq!(voice_lead(&c, &f), vec![60, 65, 69]);
+/// ```
+pub fn voice_lead(from: &[u8], to: &[u8]) -> Vec<u8> {
+  let mut classes: Vec<u8> = vec![];
+  for class in to.iter().map(|n| n % 12) {
+    if !classes.contains(&class) { classes.push(class) }
+  }
+  if from.is_empty() || classes.is_empty() { return to.to_vec() }
+
+  let centre = (from.iter().map(|n| u32::from(*n)).sum::<u32>() / from.len() as u32) as u8;
+  let mut notes: Vec<u8> = if classes.len() <= 8 && from.len() <= 8 {
+    let mut best = (u32::MAX, vec![]);
+    assign(&classes, from, centre, &mut vec![false; from.len()], &mut vec![], 0, &mut best);
+    best.1
+  } else {
+    classes.iter().map(|c| nearest(*c, centre)).collect()
+  };
+  notes.sort_unstable();
+  notes.dedup();
+  notes
+}
+
+/// The note of pitch class `class` closest to `to`.
+fn nearest(class: u8, to: u8) -> u8 {
+  let below = i16::from(to) - (i16::from(to) - i16::from(class)).rem_euclid(12);
+  [below, below + 12]
+    .into_iter()
+    .filter(|n| (0..128).contains(n))
+    .min_by_key(|n| (n - i16::from(to)).abs())
+    .unwrap_or(i16::from(class)) as u8
+}
+
+/// Tries every way of giving the pitch classes to the voices. Classes
+/// left over once every voice has one are placed near the centre.
+fn assign(
+  classes: &[u8],
+  voices: &[u8],
+  centre: u8,
+  used: &mut [bool],
+  notes: &mut Vec<u8>,
+  cost: u32,
+  best: &mut (u32, Vec<u8>),
+) {
+  if cost >= best.0 { return }
+  let Some((&class, rest)) = classes.split_first() else {
+    *best = (cost, notes.clone());
+    return
+  };
+  let free = used.iter().filter(|u| !**u).count();
+  // a voice left without a note is fine, a note left without a voice only
+  // if there are more notes than voices
+  if rest.len() >= free {
+    let note = nearest(class, centre);
+    notes.push(note);
+    assign(rest, voices, centre, used, notes, cost + u32::from(note.abs_diff(centre)), best);
+    notes.pop();
+  }
+  for (i, &voice) in voices.iter().enumerate() {
+    if used[i] { continue }
+    let note = nearest(class, voice);
+    used[i] = true;
+    notes.push(note);
+    assign(rest, voices, centre, used, notes, cost + u32::from(note.abs_diff(voice)), best);
+    notes.pop();
+    used[i] = false;
+  }
+}
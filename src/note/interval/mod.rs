@@ -0,0 +1,64 @@
+use std::fmt;
+
+/// A distance between two notes, in semitones. Negative intervals go down.
+/// ```
+/// use midi::note::interval::Interval;
+/// let fifth = Interval::between(60, 67);
+/// assert_eq!(fifth, Interval::PERFECT_FIFTH);
+/// assert_eq!(fifth.invert(), Interval::PERFECT_FOURTH);
+/// assert_eq!(fifth.apply(60), Some(67));
+/// assert_eq!(fifth.to_string(), "P5");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Interval(pub i8);
+
+impl Interval {
+  pub const UNISON:         Self = Self(0);
+  pub const MINOR_SECOND:   Self = Self(1);
+  pub const MAJOR_SECOND:   Self = Self(2);
+  pub const MINOR_THIRD:    Self = Self(3);
+  pub const MAJOR_THIRD:    Self = Self(4);
+  pub const PERFECT_FOURTH: Self = Self(5);
+  pub const TRITONE:        Self = Self(6);
+  pub const PERFECT_FIFTH:  Self = Self(7);
+  pub const MINOR_SIXTH:    Self = Self(8);
+  pub const MAJOR_SIXTH:    Self = Self(9);
+  pub const MINOR_SEVENTH:  Self = Self(10);
+  pub const MAJOR_SEVENTH:  Self = Self(11);
+  pub const OCTAVE:         Self = Self(12);
+
+  /// The interval from `from` up, or down, to `to`.
+  pub fn between(from: u8, to: u8) -> Self {
+    Self((i16::from(to) - i16::from(from)) as i8)
+  }
+
+  pub fn semitones(&self) -> i8 { self.0 }
+
+  /// The interval within one octave, 0 - 11 semitones up.
+  pub fn simple(&self) -> Self { Self(self.0.rem_euclid(12)) }
+
+  /// The interval that completes this one to an octave, such as a
+  /// fourth for a fifth.
+  pub fn invert(&self) -> Self { Self((12 - self.simple().0) % 12) }
+
+  /// Moves `note` by the interval. `None` if it leaves the MIDI range.
+  pub fn apply(&self, note: u8) -> Option<u8> {
+    u8::try_from(i16::from(note) + i16::from(self.0)).ok().filter(|n| *n < 128)
+  }
+}
+
+impl fmt::Display for Interval {
+  /// Short names, such as `m3` or `P5`. Compound intervals get the
+  /// name of their simple interval with the octaves added, e.g. `M3+1`.
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    const NAMES: [&str; 12] = ["P1", "m2", "M2", "m3", "M3", "P4", "TT", "P5", "m6", "M6", "m7", "M7"];
+    let sign = if self.0 < 0 { "-" } else { "" };
+    let semitones = self.0.unsigned_abs();
+    let (octaves, simple) = (semitones / 12, semitones % 12);
+    match (octaves, simple) {
+      (1, 0) => write!(f, "{sign}P8"),
+      (0, s) => write!(f, "{sign}{}", NAMES[usize::from(s)]),
+      (o, s) => write!(f, "{sign}{}+{o}", NAMES[usize::from(s)]),
+    }
+  }
+}
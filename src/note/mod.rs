@@ -1,3 +1,9 @@
+pub mod tracker;
+pub mod name;
+pub mod interval;
+pub mod scale;
+pub mod chord;
+
 use crate::{
   connection::Output,
   consts::note::{NOTE_OFF, NOTE_ON, DEFAULT_NOTE_OFF_VEL},
@@ -0,0 +1,86 @@
+use crate::consts::MIDDLE_C;
+
+const SHARPS: [&str; 12] = ["C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B"];
+const FLATS: [&str; 12] = ["C", "Db", "D", "Eb", "E", "F", "Gb", "G", "Ab", "A", "Bb", "B"];
+
+/// How note names are written. Manufacturers disagree on the octave
+/// of middle C: most say C4, Yamaha and others say C3.
+/// ```
+/// use midi::note::name::Naming;
+/// assert_eq!(Naming::default().parse("C#4"), Ok(61));
+/// assert_eq!(Naming::yamaha().parse("C#4"), Ok(73));
+/// assert_eq!(Naming::default().flats().name(70), "Bb4");
+/// assert!(Naming::default().parse("C3000").is_err());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Naming {
+  /// Octave number of [`MIDDLE_C`].
+  pub middle_c: i8,
+  /// Write black keys as flats rather than sharps.
+  pub flats: bool,
+}
+
+impl Default for Naming {
+  fn default() -> Self { Self{ middle_c: 4, flats: false } }
+}
+
+impl Naming {
+  /// Middle C is C3.
+  pub fn yamaha() -> Self { Self{ middle_c: 3, ..Self::default() } }
+
+  pub fn flats(mut self) -> Self {
+    self.flats = true;
+    self
+  }
+
+  /// Parses a note name such as `C4`, `f#2`, `Bb-1` or `Ebb3`.
+  pub fn parse(&self, name: &str) -> Result<u8, String> {
+    let invalid = || format!("Not a note name: {name}");
+    let mut chars = name.trim().chars().peekable();
+    let class: i32 = match chars.next().map(|c| c.to_ascii_uppercase()) {
+      Some('C') => 0,
+      Some('D') => 2,
+      Some('E') => 4,
+      Some('F') => 5,
+      Some('G') => 7,
+      Some('A') => 9,
+      Some('B') => 11,
+      _ => return Err(invalid())
+    };
+    let mut accidental: i32 = 0;
+    while let Some(&c) = chars.peek() {
+      match c {
+        '#' | '♯' => accidental += 1,
+        'b' | '♭' => accidental -= 1,
+        _ => break
+      }
+      chars.next();
+    }
+    let octave: i32 = chars.collect::<String>().parse().map_err(|_| invalid())?;
+    octave
+      .checked_sub(i32::from(self.middle_c))
+      .and_then(|o| o.checked_mul(12))
+      .and_then(|n| n.checked_add(i32::from(MIDDLE_C) + class + accidental))
+      .and_then(|n| u8::try_from(n).ok())
+      .filter(|n| *n < 128)
+      .ok_or_else(|| format!("Note {name} is outside of the MIDI range"))
+  }
+
+  pub fn name(&self, note: u8) -> String {
+    let names = if self.flats { FLATS } else { SHARPS };
+    let octave = i16::from(note) / 12 - i16::from(MIDDLE_C / 12) + i16::from(self.middle_c);
+    format!("{}{octave}", names[usize::from(note % 12)])
+  }
+}
+
+/// Parses a note name, with middle C being C4.
+pub fn parse(name: &str) -> Result<u8, String> { Naming::default().parse(name) }
+
+/// Names a note, with middle C being C4 and black keys as sharps.
+pub fn name(note: u8) -> String { Naming::default().name(note) }
+
+/// Name of a pitch class, 0 being C.
+pub fn pitch_class_name(class: u8, flats: bool) -> &'static str {
+  let names = if flats { FLATS } else { SHARPS };
+  names[usize::from(class % 12)]
+}
@@ -0,0 +1,129 @@
+/// Steps of common scales, in semitones above the root.
+pub mod modes {
+  pub const MAJOR:            &[u8] = &[0, 2, 4, 5, 7, 9, 11];
+  pub const IONIAN:           &[u8] = MAJOR;
+  pub const DORIAN:           &[u8] = &[0, 2, 3, 5, 7, 9, 10];
+  pub const PHRYGIAN:         &[u8] = &[0, 1, 3, 5, 7, 8, 10];
+  pub const LYDIAN:           &[u8] = &[0, 2, 4, 6, 7, 9, 11];
+  pub const MIXOLYDIAN:       &[u8] = &[0, 2, 4, 5, 7, 9, 10];
+  pub const MINOR:            &[u8] = &[0, 2, 3, 5, 7, 8, 10];
+  pub const AEOLIAN:          &[u8] = MINOR;
+  pub const LOCRIAN:          &[u8] = &[0, 1, 3, 5, 6, 8, 10];
+  pub const HARMONIC_MINOR:   &[u8] = &[0, 2, 3, 5, 7, 8, 11];
+  pub const MELODIC_MINOR:    &[u8] = &[0, 2, 3, 5, 7, 9, 11];
+  pub const MAJOR_PENTATONIC: &[u8] = &[0, 2, 4, 7, 9];
+  pub const MINOR_PENTATONIC: &[u8] = &[0, 3, 5, 7, 10];
+  pub const BLUES:            &[u8] = &[0, 3, 5, 6, 7, 10];
+  pub const WHOLE_TONE:       &[u8] = &[0, 2, 4, 6, 8, 10];
+  pub const CHROMATIC:        &[u8] = &[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11];
+}
+
+/// Which way [`Scale::quantize`] goes with a note between two scale notes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Round {
+  /// The closer one, the lower one if both are as close.
+  #[default]
+  Nearest,
+  Down,
+  Up,
+}
+
+/// A root and the steps above it, repeating every octave.
+/// ```
+/// use midi::note::scale::{Scale, Round, modes::MINOR_PENTATONIC};
+/// let a_minor = Scale::new(9, MINOR_PENTATONIC).unwrap();
+/// assert!(a_minor.contains(57));
+/// assert_eq!(a_minor.quantize(61, Round::Nearest), 60);
+/// assert_eq!(a_minor.quantize(61, Round::Up), 62);
+/// // two scale steps up from A3
+/// assert_eq!(a_minor.step(57, 2), Some(62));
+/// // out of range notes do not overflow
+/// assert_eq!(a_minor.degree(252), Some(1));
+/// assert_eq!(a_minor.quantize(250, Round::Up), 249);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Scale {
+  root: u8,
+  steps: Vec<u8>,
+}
+
+impl Scale {
+  /// `root` is a pitch class, 0 being C, and `steps` the semitones
+  /// above it, 0 - 11. Steps are sorted, and the root is always in.
+  pub fn new(root: u8, steps: &[u8]) -> Result<Self, String> {
+    if let Some(s) = steps.iter().find(|s| **s > 11) {
+      return Err(format!("Scale step {s} is an octave or more above the root"))
+    }
+    let mut steps = steps.to_vec();
+    steps.push(0);
+    steps.sort_unstable();
+    steps.dedup();
+    Ok(Self{ root: root % 12, steps })
+  }
+
+  pub fn root(&self) -> u8 { self.root }
+
+  pub fn steps(&self) -> &[u8] { &self.steps }
+
+  /// The scale starting on its `n`th degree, such as Dorian for the
+  /// second degree of a major scale.
+  pub fn mode(&self, n: usize) -> Self {
+    let n = n % self.steps.len();
+    let offset = self.steps[n];
+    let steps = self.steps.iter().map(|s| (s + 12 - offset) % 12).collect::<Vec<u8>>();
+    let mut mode = Self{ root: (self.root + offset) % 12, steps };
+    mode.steps.sort_unstable();
+    mode
+  }
+
+  pub fn contains(&self, note: u8) -> bool {
+    self.degree(note).is_some()
+  }
+
+  /// Position of `note` in the scale, 0 being the root.
+  pub fn degree(&self, note: u8) -> Option<usize> {
+    let step = (note % 12 + 12 - self.root) % 12;
+    self.steps.iter().position(|s| *s == step)
+  }
+
+  /// Moves `note` onto the scale. Notes already on it stay where they are.
+  pub fn quantize(&self, note: u8, round: Round) -> u8 {
+    if self.contains(note) { return note }
+    let down = (1..12).filter(|d| *d <= note).map(|d| note - d).find(|n| self.contains(*n));
+    let up = (1..12).filter_map(|d| note.checked_add(d)).filter(|n| *n < 128).find(|n| self.contains(*n));
+    let quantized = match (round, down, up) {
+      (Round::Nearest, Some(d), Some(u)) => Some(if u - note < note - d { u } else { d }),
+      (Round::Up, d, u) => u.or(d),
+      (_, d, u) => d.or(u),
+    };
+    quantized.unwrap_or(note)
+  }
+
+  /// The note `steps` scale steps above, or below, `note`. `note` is
+  /// quantized down onto the scale first.
+  pub fn step(&self, note: u8, steps: i32) -> Option<u8> {
+    let note = self.quantize(note, Round::Down);
+    let len = self.steps.len() as i32;
+    let degree = self.degree(note)? as i32 + steps;
+    let octave_root = i32::from(note) - i32::from(self.steps[self.degree(note)?]);
+    let note = octave_root + degree.div_euclid(len) * 12 + i32::from(self.steps[degree.rem_euclid(len) as usize]);
+    u8::try_from(note).ok().filter(|n| *n < 128)
+  }
+
+  /// Every note of the scale in `low` - `high`.
+  pub fn notes(&self, low: u8, high: u8) -> Vec<u8> {
+    (low..=high.min(127)).filter(|n| self.contains(*n)).collect()
+  }
+
+  /// Stacks `count` notes in thirds of the scale on `note`, e.g. the
+  /// triads and seventh chords that belong to the scale.
+  /// ```
+  /// use midi::note::scale::{Scale, modes::MAJOR};
+  /// let c_major = Scale::new(0, MAJOR).unwrap();
+  /// // the ii7 chord: D F A C
+  /// assert_eq!(c_major.stack(62, 4), vec![62, 65, 69, 72]);
+  /// ```
+  pub fn stack(&self, note: u8, count: usize) -> Vec<u8> {
+    (0..count as i32).filter_map(|i| self.step(note, i * 2)).collect()
+  }
+}
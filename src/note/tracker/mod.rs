@@ -0,0 +1,111 @@
+use super::*;
+
+use std::collections::BTreeSet;
+
+use crate::{
+  consts::{channel_mode::{ALL_NOTES_OFF, ALL_SOUND_OFF}, transport::STOP},
+  message::{channel_mode, event::{split, MidiEvent}, Message, MessageKind},
+  util::Channel,
+};
+
+/// An [`Output`] wrapper that remembers every note it sent a note on
+/// for, and has not sent a note off for yet.
+///
+/// Held notes are released when the tracker is dropped, when a
+/// transport Stop goes through it, or when asked to with
+/// [`NoteTracker::release_all`]. A Note On with velocity 0 counts as a
+/// note off, and All Notes Off / All Sound Off release a whole channel.
+/// Errors on drop can not be reported, call [`NoteTracker::release_all`]
+/// first to see them.
+///
+/// Unlike the free functions in [`note`](crate::note), the tracker
+/// waits for the port instead of dropping a message when it is busy,
+/// and only forgets a note once its note off was sent.
+/// ```no_run
+/// use midi::{connection::Output, note::tracker::NoteTracker, util::Channel};
+/// let output = Output::new("IAC Driver Bus 1", |_| {}).unwrap();
+/// let mut notes = NoteTracker::new(output);
+/// notes.note_on(Channel(0), 60, 100).unwrap();
+/// notes.note_on(Channel(0), 64, 100).unwrap();
+/// notes.note_off(Channel(0), 60).unwrap();
+/// assert_eq!(notes.held(), vec![(Channel(0), 64)]);
+/// // note 64 is released here
+/// drop(notes);
+/// ```
+pub struct NoteTracker {
+  port: Arc<Mutex<Output>>,
+  held: BTreeSet<(Channel, u8)>,
+}
+
+impl NoteTracker {
+  pub fn new(port: Arc<Mutex<Output>>) -> Self {
+    Self{ port, held: BTreeSet::new() }
+  }
+
+  pub fn port(&self) -> &Arc<Mutex<Output>> { &self.port }
+
+  /// Sends raw bytes, keeping track of the notes in them. Every message
+  /// goes out with its own send, and only counts once it was sent.
+  pub fn send(&mut self, bytes: &[u8]) -> Result<(), String> {
+    for message in split(bytes) {
+      Output::send_blocking(&self.port, message)?;
+      for event in MidiEvent::parse_all(message) {
+        match event {
+          MidiEvent::NoteOn{ ch, note, velo } if velo > 0 => { self.held.insert((ch, note)); },
+          MidiEvent::NoteOn{ ch, note, .. } | MidiEvent::NoteOff{ ch, note, .. } => { self.held.remove(&(ch, note)); },
+          MidiEvent::Cc{ ch, addr: ALL_NOTES_OFF | ALL_SOUND_OFF, .. } => self.held.retain(|(c, _)| *c != ch),
+          MidiEvent::Reset => self.held.clear(),
+          MidiEvent::Stop => self.release_all()?,
+          _ => ()
+        }
+      }
+    }
+    Ok(())
+  }
+
+  pub fn message<T: MessageKind>(&mut self, message: &Message<T>, ch: Channel) -> Result<(), String> {
+    self.send(&message.to_bytes(ch))
+  }
+
+  pub fn note_on(&mut self, ch: Channel, note: u8, velo: u8) -> Result<(), String> {
+    self.send(&[NOTE_ON|ch, note, velo])
+  }
+
+  pub fn note_off(&mut self, ch: Channel, note: u8) -> Result<(), String> {
+    self.send(&[NOTE_OFF|ch, note, DEFAULT_NOTE_OFF_VEL])
+  }
+
+  /// Sends a transport Stop, and releases every held note.
+  pub fn stop(&mut self) -> Result<(), String> {
+    self.send(&[STOP])
+  }
+
+  /// Sends a note off for every held note.
+  pub fn release_all(&mut self) -> Result<(), String> {
+    // one send each, so that a note is only forgotten once its own note off went out
+    let held: Vec<(Channel, u8)> = self.held.iter().copied().collect();
+    held.into_iter().try_for_each(|(ch, note)| self.note_off(ch, note))
+  }
+
+  /// Releases every held note, then sends a [`channel_mode::panic`]
+  /// for notes that were not sent through the tracker.
+  pub fn panic(&mut self, sweep: bool) -> Result<(), String> {
+    self.release_all()?;
+    channel_mode::panic(&self.port, sweep)
+  }
+
+  /// Notes currently sounding, ordered by channel and note.
+  pub fn held(&self) -> Vec<(Channel, u8)> {
+    self.held.iter().copied().collect()
+  }
+
+  pub fn is_held(&self, ch: Channel, note: u8) -> bool {
+    self.held.contains(&(ch, note))
+  }
+}
+
+impl Drop for NoteTracker {
+  fn drop(&mut self) {
+    let _ = self.release_all();
+  }
+}
@@ -0,0 +1,197 @@
+pub mod node;
+
+use std::collections::{BTreeMap, HashMap};
+
+use crate::{
+  connection::{ConnectionBuilder, Input, Output},
+  message::event::MidiEvent,
+  util::Channel,
+  Arc,
+  Mutex,
+};
+
+use node::Node;
+
+/// Events from one or more sources, through a [`Node`], to one or more outputs.
+pub struct Route {
+  sources: Vec<String>,
+  node: Box<dyn Node>,
+  outputs: Vec<String>,
+}
+
+impl Route {
+  pub fn new<N: Node + 'static>(node: N) -> Self {
+    Self{ sources: vec![], node: Box::new(node), outputs: vec![] }
+  }
+
+  /// Takes events from `source`. Several sources are merged.
+  /// A route without sources takes events from all of them.
+  pub fn from(mut self, source: impl Into<String>) -> Self {
+    self.sources.push(source.into());
+    self
+  }
+
+  /// Sends the result to `output`, as well as to any other outputs.
+  pub fn to(mut self, output: impl Into<String>) -> Self {
+    self.outputs.push(output.into());
+    self
+  }
+
+  fn takes(&self, source: &str) -> bool {
+    self.sources.is_empty() || self.sources.iter().any(|s| s == source)
+  }
+}
+
+/// Where the note on for an incoming note went, so that its note off
+/// follows it even if the routes changed in the meantime.
+type Sounding = HashMap<(String, Channel, u8), Vec<(String, Channel, u8)>>;
+
+/// A graph of named sources, [`Route`]s and named outputs.
+///
+/// Events come in from sources, usually [`Input`]s connected with
+/// [`Router::attach`], and every route that takes from the source
+/// processes them. Splitting is done with several routes taking from
+/// the same source, merging with a route taking from several sources.
+///
+/// Routes and outputs can be changed at any time. A note off always
+/// goes where its note on went, so notes do not hang when a route is
+/// changed while they sound.
+/// ```
+/// use midi::{
+///   message::event::MidiEvent,
+///   router::{Route, Router, node::{ChannelMap, Filter, NodeExt, Transpose}},
+///   util::Channel,
+/// };
+/// let mut router = Router::new();
+/// // split the keyboard at middle C, the lower half an octave down on channel 2
+/// router.add_route("bass", Route::new(Filter::notes(0..=59).then(Transpose(-12)).then(ChannelMap::all(Channel(1)))).from("keys").to("synth"));
+/// router.add_route("lead", Route::new(Filter::notes(60..=127)).from("keys").to("synth"));
+///
+/// let on = router.process("keys", MidiEvent::NoteOn{ ch: Channel(0), note: 48, velo: 100 });
+/// assert_eq!(on, vec![("synth".to_owned(), MidiEvent::NoteOn{ ch: Channel(1), note: 36, velo: 100 })]);
+///
+/// // the note off still goes to the bass, with the split gone
+/// router.remove_route("bass");
+/// let off = router.process("keys", MidiEvent::NoteOff{ ch: Channel(0), note: 48, velo: 0 });
+/// assert_eq!(off, vec![("synth".to_owned(), MidiEvent::NoteOff{ ch: Channel(1), note: 36, velo: 0 })]);
+/// ```
+#[derive(Default)]
+pub struct Router {
+  outputs: HashMap<String, Arc<Mutex<Output>>>,
+  routes: BTreeMap<String, Route>,
+  sounding: Sounding,
+}
+
+impl Router {
+  pub fn new() -> Self { Self::default() }
+
+  pub fn add_output(&mut self, name: impl Into<String>, port: Arc<Mutex<Output>>) {
+    self.outputs.insert(name.into(), port);
+  }
+
+  pub fn remove_output(&mut self, name: &str) -> Option<Arc<Mutex<Output>>> {
+    self.outputs.remove(name)
+  }
+
+  /// Adds a route, replacing the one with the same name.
+  pub fn add_route(&mut self, name: impl Into<String>, route: Route) {
+    self.routes.insert(name.into(), route);
+  }
+
+  pub fn remove_route(&mut self, name: &str) -> bool {
+    self.routes.remove(name).is_some()
+  }
+
+  /// Replaces all routes at once.
+  pub fn set_routes<I: IntoIterator<Item = (String, Route)>>(&mut self, routes: I) {
+    self.routes = routes.into_iter().collect();
+  }
+
+  pub fn routes(&self) -> impl Iterator<Item = &str> {
+    self.routes.keys().map(String::as_str)
+  }
+
+  /// Runs `event` from `source` through the routes, returning what
+  /// comes out, with the name of the output it goes to.
+  pub fn process(&mut self, source: &str, event: MidiEvent) -> Vec<(String, MidiEvent)> {
+    if let MidiEvent::NoteOff{ ch, note, velo } | MidiEvent::NoteOn{ ch, note, velo } = event {
+      if event.is_note_off() {
+        if let Some(targets) = self.sounding.remove(&(source.to_owned(), ch, note)) {
+          return targets
+            .into_iter()
+            .map(|(output, ch, note)| (output, MidiEvent::NoteOff{ ch, note, velo }))
+            .collect()
+        }
+      }
+    }
+
+    let mut routed = vec![];
+    let mut out = vec![];
+    for route in self.routes.values_mut().filter(|r| r.takes(source)) {
+      route.node.process(event.clone(), &mut out);
+      for e in out.drain(..) {
+        for output in &route.outputs { routed.push((output.clone(), e.clone())) }
+      }
+    }
+
+    if let MidiEvent::NoteOn{ ch, note, velo: 1.. } = event {
+      let targets = routed.iter().filter_map(|(output, e)| match *e {
+        MidiEvent::NoteOn{ ch, note, velo: 1.. } => Some((output.clone(), ch, note)),
+        _ => None
+      });
+      self.sounding.entry((source.to_owned(), ch, note)).or_default().extend(targets);
+    }
+    routed
+  }
+
+  /// Runs every message in `bytes` through the routes, and sends the
+  /// result to the outputs, every event with its own send. An output
+  /// that fails does not stop the others; the ones that failed are
+  /// returned, with the error, and get nothing more from `bytes`.
+  pub fn handle(&mut self, source: &str, bytes: &[u8]) -> Vec<(String, String)> {
+    let mut failed: Vec<(String, String)> = vec![];
+    for event in MidiEvent::parse_all(bytes) {
+      for (output, e) in self.process(source, event) {
+        let Some(port) = self.outputs.get(&output) else { continue };
+        if failed.iter().any(|(f, _)| *f == output) { continue }
+        // a dropped note off would leave a note hanging, so wait for the port
+        if let Err(e) = Output::send_blocking(port, &e.to_bytes()) { failed.push((output, e)) }
+      }
+    }
+    failed
+  }
+
+  /// Connects an [`Input`] as the source `source` of `router`.
+  /// Events are routed on the input's thread as they arrive.
+  /// ```no_run
+  /// use std::sync::{Arc, Mutex};
+  /// use midi::{connection::{ConnectionBuilder, Output}, router::{Route, Router, node::Transpose}};
+  /// let router = Arc::new(Mutex::new(Router::new()));
+  /// router.lock().unwrap().add_output("synth", Output::new("Synth", |_| {}).unwrap());
+  /// router.lock().unwrap().add_route("up", Route::new(Transpose(12)).from("keys").to("synth"));
+  /// let _keys = Router::attach(&router, "keys", ConnectionBuilder::new("Keyboard")).unwrap();
+  /// ```
+  pub fn attach(router: &SharedRouter, source: &str, input: ConnectionBuilder) -> Result<RouterInput, String> {
+    let link = RouterLink{ router: router.clone(), source: source.to_owned() };
+    input.input(link, route as RouterCallback)
+  }
+}
+
+pub type SharedRouter = Arc<Mutex<Router>>;
+
+pub struct RouterLink {
+  router: SharedRouter,
+  source: String,
+}
+
+pub type RouterCallback = fn(u64, &[u8], &mut RouterLink);
+
+/// An [`Input`] feeding a [`Router`], see [`Router::attach`].
+pub type RouterInput = Input<RouterLink, RouterCallback>;
+
+fn route(_timestamp: u64, message: &[u8], link: &mut RouterLink) {
+  // nobody to tell on the input's thread, the outputs that failed are skipped
+  if let Ok(mut router) = link.router.lock() {
+    let _ = router.handle(&link.source, message);
+  }
+}
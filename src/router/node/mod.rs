@@ -0,0 +1,387 @@
+use super::*;
+
+use std::{collections::HashMap, ops::RangeInclusive};
+
+use crate::message::curve::Curve;
+
+/// One processing step of a [`Route`]. Takes an event, and passes on
+/// none, one or several.
+///
+/// Closures returning an `Option<MidiEvent>` are nodes too.
+/// ```
+/// use midi::{message::event::MidiEvent, router::node::{Node, NodeExt, Transpose}, util::Channel};
+/// // an octave up, and everything on channel 10
+/// let mut node = Transpose(12).then(|e: MidiEvent| match e {
+///   MidiEvent::NoteOn{ note, velo, .. } => Some(MidiEvent::NoteOn{ ch: Channel(9), note, velo }),
+///   e => Some(e),
+/// });
+/// let mut out = vec![];
+/// node.process(MidiEvent::NoteOn{ ch: Channel(0), note: 36, velo: 100 }, &mut out);
+/// assert_eq!(out, vec![MidiEvent::NoteOn{ ch: Channel(9), note: 48, velo: 100 }]);
+/// ```
+pub trait Node: Send {
+  /// Processes `event`, pushing whatever comes out of it to `out`.
+  fn process(&mut self, event: MidiEvent, out: &mut Vec<MidiEvent>);
+}
+
+impl<F: FnMut(MidiEvent) -> Option<MidiEvent> + Send> Node for F {
+  fn process(&mut self, event: MidiEvent, out: &mut Vec<MidiEvent>) {
+    out.extend(self(event))
+  }
+}
+
+impl Node for Box<dyn Node> {
+  fn process(&mut self, event: MidiEvent, out: &mut Vec<MidiEvent>) {
+    (**self).process(event, out)
+  }
+}
+
+pub trait NodeExt: Node + Sized + 'static {
+  /// Feeds the output of this node into `next`.
+  fn then<N: Node + 'static>(self, next: N) -> Chain {
+    Chain::new().then(self).then(next)
+  }
+
+  fn boxed(self) -> Box<dyn Node> { Box::new(self) }
+}
+
+impl<N: Node + Sized + 'static> NodeExt for N {}
+
+/// Nodes run one after the other.
+#[derive(Default)]
+pub struct Chain {
+  nodes: Vec<Box<dyn Node>>,
+}
+
+impl Chain {
+  pub fn new() -> Self { Self::default() }
+
+  pub fn then<N: Node + 'static>(mut self, next: N) -> Self {
+    self.nodes.push(Box::new(next));
+    self
+  }
+}
+
+impl Node for Chain {
+  fn process(&mut self, event: MidiEvent, out: &mut Vec<MidiEvent>) {
+    let mut events = vec![event];
+    for node in &mut self.nodes {
+      let mut next = Vec::with_capacity(events.len());
+      for e in events { node.process(e, &mut next) }
+      events = next;
+    }
+    out.extend(events)
+  }
+}
+
+/// Nodes run side by side on copies of every event, e.g. to layer a
+/// transposed copy on top of the original.
+#[derive(Default)]
+pub struct Fork {
+  branches: Vec<Box<dyn Node>>,
+}
+
+impl Fork {
+  pub fn new() -> Self { Self::default() }
+
+  pub fn branch<N: Node + 'static>(mut self, branch: N) -> Self {
+    self.branches.push(Box::new(branch));
+    self
+  }
+}
+
+impl Node for Fork {
+  fn process(&mut self, event: MidiEvent, out: &mut Vec<MidiEvent>) {
+    for branch in &mut self.branches { branch.process(event.clone(), out) }
+  }
+}
+
+/// What kind of message a [`MidiEvent`] is, for [`Filter::types`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EventType {
+  /// Note Off, and Note On with velocity 0.
+  NoteOff,
+  NoteOn,
+  PolyPressure,
+  Cc,
+  ProgramChange,
+  ChannelPressure,
+  PitchBend,
+  SysEx,
+  /// Time code, song position, song select and tune request.
+  SystemCommon,
+  /// Clock, transport, active sensing and reset.
+  Realtime,
+}
+
+impl EventType {
+  pub fn of(event: &MidiEvent) -> Self {
+    match event {
+      e if e.is_note_off() => Self::NoteOff,
+      MidiEvent::NoteOn{ .. } => Self::NoteOn,
+      MidiEvent::NoteOff{ .. } => Self::NoteOff,
+      MidiEvent::PolyPressure{ .. } => Self::PolyPressure,
+      MidiEvent::Cc{ .. } => Self::Cc,
+      MidiEvent::ProgramChange{ .. } => Self::ProgramChange,
+      MidiEvent::ChannelPressure{ .. } => Self::ChannelPressure,
+      MidiEvent::PitchBend{ .. } => Self::PitchBend,
+      MidiEvent::SysEx(_) => Self::SysEx,
+      MidiEvent::TimeCode(_) | MidiEvent::SongPosition(_) | MidiEvent::SongSelect(_) | MidiEvent::TuneRequest => Self::SystemCommon,
+      _ => Self::Realtime,
+    }
+  }
+}
+
+fn note_of(event: &MidiEvent) -> Option<u8> {
+  match *event {
+    MidiEvent::NoteOn{ note, .. } | MidiEvent::NoteOff{ note, .. } | MidiEvent::PolyPressure{ note, .. } => Some(note),
+    _ => None
+  }
+}
+
+fn with_note(event: MidiEvent, note: u8) -> MidiEvent {
+  match event {
+    MidiEvent::NoteOn{ ch, velo, .. } => MidiEvent::NoteOn{ ch, note, velo },
+    MidiEvent::NoteOff{ ch, velo, .. } => MidiEvent::NoteOff{ ch, note, velo },
+    MidiEvent::PolyPressure{ ch, pressure, .. } => MidiEvent::PolyPressure{ ch, note, pressure },
+    e => e
+  }
+}
+
+/// Lets through the events a predicate accepts.
+/// ```
+/// use midi::{message::event::MidiEvent, router::node::{Filter, Node}, util::Channel};
+/// let mut lower = Filter::notes(0..=59);
+/// let mut out = vec![];
+/// lower.process(MidiEvent::NoteOn{ ch: Channel(0), note: 60, velo: 90 }, &mut out);
+/// lower.process(MidiEvent::Cc{ ch: Channel(0), addr: 64, val: 127 }, &mut out);
+/// assert_eq!(out, vec![MidiEvent::Cc{ ch: Channel(0), addr: 64, val: 127 }]);
+/// ```
+pub struct Filter {
+  pass: Box<dyn Fn(&MidiEvent) -> bool + Send>,
+}
+
+impl Filter {
+  pub fn new<F: Fn(&MidiEvent) -> bool + Send + 'static>(pass: F) -> Self {
+    Self{ pass: Box::new(pass) }
+  }
+
+  /// Lets through the given kinds of message only.
+  pub fn types(types: &[EventType]) -> Self {
+    let types = types.to_vec();
+    Self::new(move |e| types.contains(&EventType::of(e)))
+  }
+
+  /// Lets through channel messages on the given channels, and every
+  /// system message.
+  pub fn channels(channels: &[Channel]) -> Self {
+    let channels = channels.to_vec();
+    Self::new(move |e| e.channel().is_none_or(|ch| channels.contains(&ch)))
+  }
+
+  /// Lets through note messages in `range`, and everything that is
+  /// not about a note, such as the sustain pedal.
+  pub fn notes(range: RangeInclusive<u8>) -> Self {
+    Self::new(move |e| note_of(e).is_none_or(|n| range.contains(&n)))
+  }
+
+  /// Lets through what this filter blocks, and the other way around.
+  pub fn invert(self) -> Self {
+    let pass = self.pass;
+    Self::new(move |e| !pass(e))
+  }
+}
+
+impl Node for Filter {
+  fn process(&mut self, event: MidiEvent, out: &mut Vec<MidiEvent>) {
+    if (self.pass)(&event) { out.push(event) }
+  }
+}
+
+/// Shifts notes by a number of semitones. Notes shifted out of
+/// 0 - 127 are dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Transpose(pub i8);
+
+impl Node for Transpose {
+  fn process(&mut self, event: MidiEvent, out: &mut Vec<MidiEvent>) {
+    let Some(note) = note_of(&event) else { return out.push(event) };
+    match u8::try_from(i16::from(note) + i16::from(self.0)) {
+      Ok(note) if note < 128 => out.push(with_note(event, note)),
+      _ => ()
+    }
+  }
+}
+
+/// Moves channel messages from one channel to another.
+/// ```
+/// use midi::{message::event::MidiEvent, router::node::{ChannelMap, Node}, util::Channel};
+/// let mut map = ChannelMap::new().map(Channel(0), Channel(3));
+/// let mut out = vec![];
+/// map.process(MidiEvent::ProgramChange{ ch: Channel(0), program: 5 }, &mut out);
+/// assert_eq!(out[0].channel(), Some(Channel(3)));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChannelMap {
+  map: [Channel; 16],
+}
+
+impl Default for ChannelMap {
+  fn default() -> Self { Self::new() }
+}
+
+impl ChannelMap {
+  /// Leaves every channel where it is.
+  pub fn new() -> Self {
+    Self{ map: std::array::from_fn(|ch| Channel(ch as u8)) }
+  }
+
+  /// Moves every channel to `to`.
+  pub fn all(to: Channel) -> Self {
+    Self{ map: [to; 16] }
+  }
+
+  pub fn map(mut self, from: Channel, to: Channel) -> Self {
+    self.map[(from.0 & 0x0f) as usize] = to;
+    self
+  }
+}
+
+impl Node for ChannelMap {
+  fn process(&mut self, event: MidiEvent, out: &mut Vec<MidiEvent>) {
+    let to = |ch: Channel| self.map[(ch.0 & 0x0f) as usize];
+    out.push(match event {
+      MidiEvent::NoteOff{ ch, note, velo } => MidiEvent::NoteOff{ ch: to(ch), note, velo },
+      MidiEvent::NoteOn{ ch, note, velo } => MidiEvent::NoteOn{ ch: to(ch), note, velo },
+      MidiEvent::PolyPressure{ ch, note, pressure } => MidiEvent::PolyPressure{ ch: to(ch), note, pressure },
+      MidiEvent::Cc{ ch, addr, val } => MidiEvent::Cc{ ch: to(ch), addr, val },
+      MidiEvent::ProgramChange{ ch, program } => MidiEvent::ProgramChange{ ch: to(ch), program },
+      MidiEvent::ChannelPressure{ ch, pressure } => MidiEvent::ChannelPressure{ ch: to(ch), pressure },
+      MidiEvent::PitchBend{ ch, value } => MidiEvent::PitchBend{ ch: to(ch), value },
+      e => e
+    })
+  }
+}
+
+/// Maps the velocity of every note on. A note on is never turned into
+/// a note off, velocities are kept within 1 - 127.
+pub struct Velocity {
+  map: Box<dyn FnMut(u8) -> u8 + Send>,
+}
+
+impl Velocity {
+  pub fn new<F: FnMut(u8) -> u8 + Send + 'static>(map: F) -> Self {
+    Self{ map: Box::new(map) }
+  }
+
+  /// Every note at the same velocity.
+  pub fn fixed(velo: u8) -> Self {
+    Self::new(move |_| velo)
+  }
+
+  /// Maps velocities through `curve`.
+  /// ```
+  /// use midi::{message::{curve::Curve, event::MidiEvent}, router::node::{Node, Velocity}, util::Channel};
+  /// let mut heavy = Velocity::curve(Curve::exponential(2.0));
+  /// let mut out = vec![];
+  /// heavy.process(MidiEvent::NoteOn{ ch: Channel(0), note: 60, velo: 64 }, &mut out);
+  /// assert_eq!(out, vec![MidiEvent::NoteOn{ ch: Channel(0), note: 60, velo: 35 }]);
+  /// ```
+  pub fn curve(curve: Curve) -> Self {
+    let table = curve.table();
+    Self::new(move |v| table[(v & 0x7f) as usize])
+  }
+
+  /// Squeezes 1 - 127 into `min` - `max`.
+  pub fn scale(min: u8, max: u8) -> Self {
+    Self::new(move |v| {
+      let span = i32::from(max) - i32::from(min);
+      (i32::from(min) + (i32::from(v) - 1) * span / 126) as u8
+    })
+  }
+}
+
+impl Node for Velocity {
+  fn process(&mut self, event: MidiEvent, out: &mut Vec<MidiEvent>) {
+    out.push(match event {
+      MidiEvent::NoteOn{ ch, note, velo } if velo > 0 => {
+        MidiEvent::NoteOn{ ch, note, velo: (self.map)(velo).clamp(1, 127) }
+      },
+      e => e
+    })
+  }
+}
+
+/// Maps controller values through a [`Curve`].
+pub struct CcCurve {
+  table: [u8; 128],
+  controllers: Vec<u8>,
+}
+
+impl CcCurve {
+  /// Applies `curve` to every controller, unless narrowed down with
+  /// [`CcCurve::controller`].
+  pub fn new(curve: Curve) -> Self {
+    Self{ table: curve.table(), controllers: vec![] }
+  }
+
+  /// Applies the curve to controller `addr`, and the others given this way, only.
+  pub fn controller(mut self, addr: u8) -> Self {
+    self.controllers.push(addr);
+    self
+  }
+}
+
+impl Node for CcCurve {
+  fn process(&mut self, event: MidiEvent, out: &mut Vec<MidiEvent>) {
+    out.push(match event {
+      MidiEvent::Cc{ ch, addr, val } if self.controllers.is_empty() || self.controllers.contains(&addr) => {
+        MidiEvent::Cc{ ch, addr, val: self.table[(val & 0x7f) as usize] }
+      },
+      e => e
+    })
+  }
+}
+
+/// Moves controllers to other controller numbers. Controllers that
+/// are not mapped pass unchanged.
+/// ```
+/// use midi::{message::event::MidiEvent, router::node::{CcMap, Node}, util::Channel};
+/// // mod wheel to filter cutoff
+/// let mut map = CcMap::new().map(1, 74);
+/// let mut out = vec![];
+/// map.process(MidiEvent::Cc{ ch: Channel(0), addr: 1, val: 90 }, &mut out);
+/// assert_eq!(out, vec![MidiEvent::Cc{ ch: Channel(0), addr: 74, val: 90 }]);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CcMap {
+  map: HashMap<u8, Option<u8>>,
+}
+
+impl CcMap {
+  pub fn new() -> Self { Self::default() }
+
+  pub fn map(mut self, from: u8, to: u8) -> Self {
+    self.map.insert(from, Some(to));
+    self
+  }
+
+  /// Drops controller `addr` altogether.
+  pub fn block(mut self, addr: u8) -> Self {
+    self.map.insert(addr, None);
+    self
+  }
+}
+
+impl Node for CcMap {
+  fn process(&mut self, event: MidiEvent, out: &mut Vec<MidiEvent>) {
+    match event {
+      MidiEvent::Cc{ ch, addr, val } => match self.map.get(&addr) {
+        Some(Some(to)) => out.push(MidiEvent::Cc{ ch, addr: *to, val }),
+        Some(None) => (),
+        None => out.push(event),
+      },
+      e => out.push(e)
+    }
+  }
+}
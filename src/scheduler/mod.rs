@@ -0,0 +1,300 @@
+use std::{
+  cmp::Reverse,
+  collections::{BinaryHeap, HashMap},
+  sync::Condvar,
+  thread::{self, JoinHandle},
+  time::{Duration, Instant},
+};
+
+use crate::{
+  connection::Output,
+  consts::note::{DEFAULT_NOTE_OFF_VEL, NOTE_OFF, NOTE_ON},
+  util::Channel,
+  Arc,
+  Mutex,
+};
+
+/// What a repeating task gets run with, see [`Scheduler::repeat`].
+type TaskFn = Box<dyn FnMut(Instant) -> Option<Vec<u8>> + Send>;
+/// Told that what the task returned was sent.
+type SentFn = Box<dyn FnMut() + Send>;
+
+struct Task {
+  period: Duration,
+  run: TaskFn,
+  sent: Option<SentFn>,
+}
+
+struct Pending {
+  bytes: Vec<u8>,
+  /// The note this message releases, if it is a scheduled note off.
+  note: Option<(Channel, u8)>,
+  /// Set for repeating tasks, which send whatever they return instead of `bytes`.
+  task: Option<Task>,
+}
+
+#[derive(Default)]
+struct State {
+  queue: BinaryHeap<Reverse<(Instant, u64)>>,
+  pending: HashMap<u64, Pending>,
+  /// Scheduled note off of every sounding note.
+  sounding: HashMap<(Channel, u8), u64>,
+  next_id: u64,
+  shutdown: bool,
+}
+
+impl State {
+  fn push(&mut self, at: Instant, pending: Pending) -> u64 {
+    let id = self.next_id;
+    self.next_id += 1;
+    if let Some(note) = pending.note {
+      self.sounding.insert(note, id);
+    }
+    self.queue.push(Reverse((at, id)));
+    self.pending.insert(id, pending);
+    id
+  }
+
+  /// Puts a repeating task back in line, under the same id.
+  fn requeue(&mut self, at: Instant, id: u64, pending: Pending) {
+    self.queue.push(Reverse((at, id)));
+    self.pending.insert(id, pending);
+  }
+
+  fn take(&mut self, id: u64) -> Option<Pending> {
+    let pending = self.pending.remove(&id)?;
+    if let Some(note) = pending.note {
+      if self.sounding.get(&note) == Some(&id) { self.sounding.remove(&note); }
+    }
+    Some(pending)
+  }
+}
+
+struct Shared {
+  port: Arc<Mutex<Output>>,
+  state: Mutex<State>,
+  wake: Condvar,
+}
+
+impl Shared {
+  // Called with the state locked, so that a message can not be sent
+  // after the one that was meant to replace it. A failed send is
+  // dropped, the rest of the schedule goes on.
+  fn send(&self, bytes: &[u8]) { let _ = self.try_send(bytes); }
+
+  // Every message goes out with its own send.
+  fn try_send(&self, bytes: &[u8]) -> Result<(), String> {
+    Output::send_blocking(&self.port, bytes)
+  }
+
+  fn run(&self) {
+    let Ok(mut state) = self.state.lock() else { return };
+    while !state.shutdown {
+      let now = Instant::now();
+      match state.queue.peek() {
+        Some(&Reverse((at, id))) if at <= now => {
+          state.queue.pop();
+          let Some(mut pending) = state.take(id) else { continue };
+          let Some(task) = pending.task.as_mut() else {
+            self.send(&pending.bytes);
+            continue
+          };
+          if let Some(bytes) = (task.run)(now) {
+            if !bytes.is_empty() && self.try_send(&bytes).is_ok() {
+              if let Some(sent) = task.sent.as_mut() { sent() }
+            }
+            // a late task skips ahead rather than catching up in a burst
+            let next = (at + task.period).max(now);
+            state.requeue(next, id, pending);
+          }
+        },
+        Some(&Reverse((at, _))) => {
+          let Ok((s, _)) = self.wake.wait_timeout(state, at - now) else { return };
+          state = s;
+        },
+        None => {
+          let Ok(s) = self.wake.wait(state) else { return };
+          state = s;
+        },
+      }
+    }
+    // nothing may be left hanging once the scheduler is gone
+    let ids: Vec<u64> = state.pending.keys().copied().collect();
+    for id in ids {
+      if let Some(pending) = state.take(id) {
+        if pending.note.is_some() { self.send(&pending.bytes) }
+      }
+    }
+  }
+}
+
+/// Plays notes of a given length without blocking.
+///
+/// [`Scheduler::play_note`] sends the note on right away and leaves the
+/// note off to a timer thread shared by everything sent through the
+/// scheduler. Replaying a note that is still sounding releases it first,
+/// and moves its note off to the end of the new duration.
+///
+/// Dropping the scheduler sends every note off that is still pending.
+/// ```no_run
+/// use std::time::Duration;
+/// use midi::{connection::Output, scheduler::Scheduler, util::Channel};
+/// let output = Output::new("IAC Driver Bus 1", |_| {}).unwrap();
+/// let scheduler = Scheduler::new(output);
+/// let drone = scheduler.play_note(Channel(0), 36, 100, Duration::from_secs(8));
+/// for note in [60, 64, 67] {
+///   scheduler.play_note(Channel(0), note, 90, Duration::from_millis(250));
+///   midi::transport::sleep(Duration::from_millis(500));
+/// }
+/// // cut the drone short
+/// drone.trigger();
+/// ```
+pub struct Scheduler {
+  shared: Arc<Shared>,
+  timer: Option<JoinHandle<()>>,
+}
+
+impl Scheduler {
+  pub fn new(port: Arc<Mutex<Output>>) -> Self {
+    let shared = Arc::new(Shared{ port, state: Mutex::new(State::default()), wake: Condvar::new() });
+    let timer = {
+      let shared = shared.clone();
+      thread::spawn(move || shared.run())
+    };
+    Self{ shared, timer: Some(timer) }
+  }
+
+  /// Sends a note on now, and its note off after `duration`.
+  pub fn play_note(&self, ch: Channel, note: u8, velo: u8, duration: Duration) -> Handle {
+    let off = vec![NOTE_OFF|ch, note, DEFAULT_NOTE_OFF_VEL];
+    let Ok(mut state) = self.shared.state.lock() else { return self.handle(None) };
+    if let Some(id) = state.sounding.get(&(ch, note)).copied() {
+      // retrigger: the old note off would cut the new note short
+      if let Some(pending) = state.take(id) { self.shared.send(&pending.bytes) }
+    }
+    self.shared.send(&[NOTE_ON|ch, note, velo]);
+    let id = state.push(Instant::now() + duration, Pending{ bytes: off, note: Some((ch, note)), task: None });
+    self.shared.wake.notify_one();
+    self.handle(Some(id))
+  }
+
+  /// Sends `bytes` after `delay`.
+  pub fn send_after(&self, bytes: &[u8], delay: Duration) -> Handle {
+    self.send_at(bytes, Instant::now() + delay)
+  }
+
+  /// Sends `bytes` at `at`, or right away if `at` has passed.
+  pub fn send_at(&self, bytes: &[u8], at: Instant) -> Handle {
+    let Ok(mut state) = self.shared.state.lock() else { return self.handle(None) };
+    let id = state.push(at, Pending{ bytes: bytes.to_vec(), note: None, task: None });
+    self.shared.wake.notify_one();
+    self.handle(Some(id))
+  }
+
+  /// Runs `task` now and then every `period`, sending the bytes it
+  /// returns, if any. The task stops when it returns `None`, or when
+  /// its handle is cancelled.
+  ///
+  /// Tasks run on the timer thread, so they should be quick.
+  /// ```no_run
+  /// use std::time::Duration;
+  /// use midi::{connection::Output, scheduler::Scheduler};
+  /// let scheduler = Scheduler::new(Output::new("IAC Driver Bus 1", |_| {}).unwrap());
+  /// // a fade out on CC 7 of channel 1
+  /// let mut volume = 127u8;
+  /// scheduler.repeat(Duration::from_millis(20), move |_| {
+  ///   volume = volume.checked_sub(1)?;
+  ///   Some(vec![0xB0, 7, volume])
+  /// });
+  /// ```
+  pub fn repeat<F>(&self, period: Duration, task: F) -> Handle
+  where F: FnMut(Instant) -> Option<Vec<u8>> + Send + 'static {
+    self.repeat_confirmed(period, task, None)
+  }
+
+  /// [`Scheduler::repeat`], calling `sent` after every send that went out.
+  pub(crate) fn repeat_confirmed<F>(&self, period: Duration, task: F, sent: Option<SentFn>) -> Handle
+  where F: FnMut(Instant) -> Option<Vec<u8>> + Send + 'static {
+    let Ok(mut state) = self.shared.state.lock() else { return self.handle(None) };
+    let task = Task{ period, run: Box::new(task), sent };
+    let id = state.push(Instant::now(), Pending{ bytes: vec![], note: None, task: Some(task) });
+    self.shared.wake.notify_one();
+    self.handle(Some(id))
+  }
+
+  /// Notes that are sounding, waiting for their note off.
+  pub fn sounding(&self) -> Vec<(Channel, u8)> {
+    let Ok(state) = self.shared.state.lock() else { return vec![] };
+    let mut notes: Vec<(Channel, u8)> = state.sounding.keys().copied().collect();
+    notes.sort();
+    notes
+  }
+
+  /// Sends every pending note off now, and drops everything else
+  /// that was scheduled, repeating tasks included.
+  pub fn release_all(&self) {
+    let Ok(mut state) = self.shared.state.lock() else { return };
+    let ids: Vec<u64> = state.pending.keys().copied().collect();
+    for id in ids {
+      if let Some(pending) = state.take(id) {
+        if pending.note.is_some() { self.shared.send(&pending.bytes) }
+      }
+    }
+    state.queue.clear();
+  }
+
+  fn handle(&self, id: Option<u64>) -> Handle {
+    Handle{ shared: self.shared.clone(), id }
+  }
+}
+
+impl Drop for Scheduler {
+  fn drop(&mut self) {
+    if let Ok(mut state) = self.shared.state.lock() {
+      state.shutdown = true;
+    }
+    self.shared.wake.notify_one();
+    if let Some(timer) = self.timer.take() {
+      let _ = timer.join();
+    }
+  }
+}
+
+/// A message waiting in a [`Scheduler`], e.g. the note off of a note
+/// started with [`Scheduler::play_note`].
+pub struct Handle {
+  shared: Arc<Shared>,
+  id: Option<u64>,
+}
+
+impl Handle {
+  /// Returns `true` if the message has not been sent yet.
+  pub fn is_pending(&self) -> bool {
+    self.id.is_some_and(|id| {
+      self.shared.state.lock().is_ok_and(|state| state.pending.contains_key(&id))
+    })
+  }
+
+  /// Sends the message now instead of later. For a note, this ends it
+  /// early, and a repeating task is run one last time.
+  /// Returns `false` if it was already sent or cancelled.
+  pub fn trigger(&self) -> bool {
+    let Some(id) = self.id else { return false };
+    let Ok(mut state) = self.shared.state.lock() else { return false };
+    let Some(pending) = state.take(id) else { return false };
+    let bytes = match pending.task {
+      Some(mut task) => (task.run)(Instant::now()).unwrap_or_default(),
+      None => pending.bytes,
+    };
+    if !bytes.is_empty() { self.shared.send(&bytes) }
+    true
+  }
+
+  /// Drops the message without sending it. For a note, this leaves it
+  /// sounding, so its note off is up to the caller.
+  /// Returns `false` if it was already sent or cancelled.
+  pub fn cancel(&self) -> bool {
+    let Some(id) = self.id else { return false };
+    self.shared.state.lock().is_ok_and(|mut state| state.take(id).is_some())
+  }
+}
@@ -1,11 +1,13 @@
+use std::collections::VecDeque;
 use std::sync::atomic::AtomicBool;
-use std::time::{Duration, SystemTime};
+use std::time::{Duration, Instant, SystemTime};
 use crate::{Arc, Mutex, connection::Output,
+  message::event::MidiEvent,
   util::{
     logging::err_send_log,
     calc_midi_ppq
   },
-  consts::transport::{START, STOP, CONTINUE, CLOCK}
+  consts::transport::{START, STOP, CONTINUE, CLOCK, PPQ}
 };
 /// re-export from spin_sleep crate
 pub use spin_sleep::{SpinSleeper, SpinStrategy, sleep};
@@ -35,11 +37,16 @@ pub fn clock(port: &Arc<Mutex<Output>>) {
 }
 
 pub fn transport(port: &Arc<Mutex<Output>>, bpm: f64, run: Arc<AtomicBool>) {
-  let dur = Duration::from_secs_f64(calc_midi_ppq(bpm));
+  transport_tempo(port, &Tempo::new(bpm), run)
+}
+
+/// Like [`transport`], but follows changes to `tempo` while running.
+pub fn transport_tempo(port: &Arc<Mutex<Output>>, tempo: &Tempo, run: Arc<AtomicBool>) {
   let spin_sleeper = SpinSleeper::new(10_000)
     .with_spin_strategy(SpinStrategy::YieldThread);
 
   'clock: loop {
+    let dur = Duration::from_secs_f64(calc_midi_ppq(tempo.bpm()));
     let now = SystemTime::now();
     if !run.load(std::sync::atomic::Ordering::Acquire) { break 'clock }
     if let Ok(mut p) = port.try_lock() {
@@ -57,3 +64,65 @@ pub fn transport(port: &Arc<Mutex<Output>>, bpm: f64, run: Arc<AtomicBool>) {
   }
 }
 
+struct TempoState {
+  bpm: f64,
+  /// Arrival of the last beat's worth of incoming clocks.
+  clocks: VecDeque<Instant>,
+}
+
+/// A tempo shared between the transport and what follows it, such as
+/// tempo synced LFOs. It can be set by hand, or follow incoming MIDI clock.
+/// ```
+/// use midi::transport::Tempo;
+/// let tempo = Tempo::new(120.0);
+/// let follower = tempo.clone();
+/// tempo.set_bpm(90.0);
+/// assert_eq!(follower.bpm(), 90.0);
+/// assert_eq!(follower.beat().as_millis(), 666);
+/// ```
+#[derive(Clone)]
+pub struct Tempo(Arc<Mutex<TempoState>>);
+
+impl Tempo {
+  pub fn new(bpm: f64) -> Self {
+    Self(Arc::new(Mutex::new(TempoState{ bpm: bpm.max(1.0), clocks: VecDeque::new() })))
+  }
+
+  pub fn bpm(&self) -> f64 {
+    self.0.lock().map_or(120.0, |t| t.bpm)
+  }
+
+  pub fn set_bpm(&self, bpm: f64) {
+    if let Ok(mut t) = self.0.lock() { t.bpm = bpm.max(1.0) }
+  }
+
+  /// Length of one beat, a quarter note.
+  pub fn beat(&self) -> Duration {
+    Duration::from_secs_f64(60.0 / self.bpm())
+  }
+
+  /// Counts an incoming clock. Once a beat's worth has arrived, the
+  /// tempo follows their average rate.
+  pub fn clock(&self) {
+    let Ok(mut t) = self.0.lock() else { return };
+    let now = Instant::now();
+    t.clocks.push_back(now);
+    if t.clocks.len() > usize::from(PPQ) + 1 { t.clocks.pop_front(); }
+    if t.clocks.len() == usize::from(PPQ) + 1 {
+      let beat = now - t.clocks[0];
+      if !beat.is_zero() { t.bpm = 60.0 / beat.as_secs_f64() }
+    }
+  }
+
+  /// Follows clock from incoming messages. Start and stop forget the
+  /// clocks counted so far, as the sender may have changed tempo in between.
+  pub fn handle(&self, event: &MidiEvent) {
+    match event {
+      MidiEvent::Clock => self.clock(),
+      MidiEvent::Start | MidiEvent::Stop => {
+        if let Ok(mut t) = self.0.lock() { t.clocks.clear() }
+      },
+      _ => ()
+    }
+  }
+}
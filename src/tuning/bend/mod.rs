@@ -0,0 +1,136 @@
+use crate::{
+  connection::Output,
+  message::{note::{NoteOff, NoteOn}, pitchbend::BendRange, Message, MessageKind},
+  util::Channel,
+  Arc,
+  Mutex,
+};
+use super::Tuning;
+
+/// A retuned note: the key that was played, and the equal tempered note
+/// and channel it sounds on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RetunedNote {
+  pub ch: Channel,
+  pub key: u8,
+  pub note: u8,
+}
+
+struct Slot {
+  ch: Channel,
+  note: Option<RetunedNote>,
+  last_used: u64,
+}
+
+/// Plays a [`Tuning`] on synths without MIDI Tuning Standard support.
+///
+/// Every note gets a channel of its own, rotating through `channels`,
+/// and sounds as the closest equal tempered note bent the rest of the
+/// way. The bend is sent before the note on, so the note starts in tune.
+/// When every channel is busy the oldest note is released to make room.
+/// ```no_run
+/// use midi::{connection::Output, tuning::{Tuning, bend::BendRetuner}, util::Channel};
+/// let port = Output::new("IAC Driver Bus 1", |_| {}).unwrap();
+/// let tuning = Tuning::load("just.scl", Some("just.kbm")).unwrap();
+/// let mut retuner = BendRetuner::new(tuning, (0..8).map(Channel).collect()).unwrap();
+/// retuner.configure(&port).unwrap();
+/// if let Some(note) = retuner.note_on(&port, 64, 100).unwrap() {
+///   retuner.note_off(&port, note).unwrap();
+/// }
+/// ```
+pub struct BendRetuner {
+  tuning: Tuning,
+  bend_range: BendRange,
+  slots: Vec<Slot>,
+  clock: u64,
+}
+
+impl BendRetuner {
+  /// The pitch bend range starts out at 2 semitones, the usual default.
+  pub fn new(tuning: Tuning, channels: Vec<Channel>) -> Result<Self, String> {
+    if channels.is_empty() {
+      return Err("pitch bend retuning needs at least one channel".to_owned())
+    }
+    let slots = channels.into_iter().map(|ch| Slot{ ch, note: None, last_used: 0 }).collect();
+    Ok(Self{ tuning, bend_range: BendRange::default(), slots, clock: 0 })
+  }
+
+  /// Has to match the synth, see [`BendRetuner::configure`]. Ranges
+  /// under a semitone are raised to one, as bends go up to half a semitone.
+  pub fn bend_range(mut self, range: BendRange) -> Self {
+    self.bend_range = if range.semitones == 0 { BendRange::from(1) } else { range };
+    self
+  }
+
+  pub fn tuning(&self) -> &Tuning { &self.tuning }
+
+  pub fn set_tuning(&mut self, tuning: Tuning) { self.tuning = tuning }
+
+  /// Sets the pitch bend range (RPN 0) of every channel.
+  pub fn configure(&self, port: &Arc<Mutex<Output>>) -> Result<(), String> {
+    let range = self.bend_range.rpn();
+    // send_blocking sends each CC of the RPN on its own
+    self.slots.iter().try_for_each(|s| Output::send_blocking(port, &range.to_bytes(s.ch)))
+  }
+
+  /// Every note currently holding a channel.
+  pub fn active(&self) -> Vec<RetunedNote> {
+    self.slots.iter().filter_map(|s| s.note).collect()
+  }
+
+  /// Plays `key` in the tuning. `Ok(None)` if the key is unmapped.
+  pub fn note_on(&mut self, port: &Arc<Mutex<Output>>, key: u8, velo: u8) -> Result<Option<RetunedNote>, String> {
+    let Some(pitch) = self.tuning.semitones(key) else { return Ok(None) };
+    let note = pitch.round().clamp(0.0, 127.0);
+
+    self.clock += 1;
+    let i = self.allocate();
+    let slot = &mut self.slots[i];
+    let stolen = slot.note.take();
+    let retuned = RetunedNote{ ch: slot.ch, key, note: note as u8 };
+    slot.note = Some(retuned);
+    slot.last_used = self.clock;
+    // one message per send: note off of the stolen note, bend, note on
+    if let Some(stolen) = stolen {
+      Output::send_blocking(port, &kind_bytes(NoteOff{ note: stolen.note }, retuned.ch))?;
+    }
+    let pb = Message::bend_semitones((pitch - note) as f32, self.bend_range);
+    Output::send_blocking(port, &pb.to_bytes(retuned.ch))?;
+    Output::send_blocking(port, &kind_bytes(NoteOn{ note: retuned.note, velo: velo.min(127) }, retuned.ch))?;
+    Ok(Some(retuned))
+  }
+
+  /// Releases `note` and frees its channel. A note that was already
+  /// stolen by a later one sends nothing, so that it can not cut off
+  /// whatever now plays on its channel.
+  pub fn note_off(&mut self, port: &Arc<Mutex<Output>>, note: RetunedNote) -> Result<(), String> {
+    self.clock += 1;
+    let Some(slot) = self.slots.iter_mut().find(|s| s.note == Some(note)) else { return Ok(()) };
+    slot.note = None;
+    slot.last_used = self.clock;
+    Output::send_blocking(port, &kind_bytes(NoteOff{ note: note.note }, note.ch))
+  }
+
+  /// Releases every active note.
+  pub fn release_all(&mut self, port: &Arc<Mutex<Output>>) -> Result<(), String> {
+    self.active().into_iter().try_for_each(|n| self.note_off(port, n))
+  }
+
+  /// A free channel, least recently used first, or the one holding the oldest note.
+  fn allocate(&self) -> usize {
+    let free = self.slots
+      .iter()
+      .enumerate()
+      .filter(|(_, s)| s.note.is_none())
+      .min_by_key(|(_, s)| s.last_used);
+    let oldest = || self.slots
+      .iter()
+      .enumerate()
+      .min_by_key(|(_, s)| s.last_used);
+    free.or_else(oldest).map_or(0, |(i, _)| i)
+  }
+}
+
+fn kind_bytes<T: MessageKind>(kind: T, ch: Channel) -> Vec<u8> {
+  Message::new(kind).map(|m| m.to_bytes(ch)).unwrap_or_default()
+}
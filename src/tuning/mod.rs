@@ -0,0 +1,123 @@
+pub mod scala;
+pub mod bend;
+
+use std::path::Path;
+use crate::message::sysex::universal::{TuningEntry, Universal};
+use self::scala::{KeyboardMap, Scale};
+
+/// The pitch of every MIDI key, as a fractional MIDI note number
+/// (69.0 being A 440 Hz). Unmapped keys have no pitch and are left alone.
+///
+/// A tuning reaches a synth either as MIDI Tuning Standard SysEx, see
+/// [`Tuning::bulk_dump`] and [`Tuning::note_changes`], or as pitch bend
+/// on a channel per note through a [`bend::BendRetuner`].
+/// ```
+/// use midi::tuning::{Tuning, scala::{Scale, KeyboardMap}};
+/// let just = Scale::parse("5-limit just intonation major
+///  7
+///  9/8
+///  5/4
+///  4/3
+///  3/2
+///  5/3
+///  15/8
+///  2/1
+/// ").unwrap();
+/// // white keys only, C4 on degree 0 and A4 at 440 Hz
+/// let map = KeyboardMap::parse("
+/// 12
+/// 0
+/// 127
+/// 60
+/// 69
+/// 440.0
+/// 7
+/// 0
+/// x
+/// 1
+/// x
+/// 2
+/// 3
+/// x
+/// 4
+/// x
+/// 5
+/// x
+/// 6
+/// ").unwrap();
+/// let tuning = Tuning::from_scala(&just, &map);
+/// assert!((tuning.frequency(69).unwrap() - 440.0).abs() < 1e-9);
+/// // D is 9/8 above C, a syntonic comma off a pure fifth below A
+/// assert!((tuning.frequency(62).unwrap() - 297.0).abs() < 1e-9);
+/// assert_eq!(tuning.semitones(61), None);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tuning {
+  pitches: Vec<Option<f64>>,
+}
+
+impl Default for Tuning {
+  fn default() -> Self { Self::equal() }
+}
+
+impl Tuning {
+  /// 12 tone equal temperament at A 440 Hz.
+  pub fn equal() -> Self {
+    Self{ pitches: (0..128).map(|k| Some(f64::from(k))).collect() }
+  }
+
+  pub fn from_scala(scale: &Scale, map: &KeyboardMap) -> Self {
+    // the reference key sets the pitch, even if it does not sound itself
+    let reference = map
+      .cents(map.reference, scale)
+      .unwrap_or_else(|| scale.cents(i32::from(map.reference) - i32::from(map.middle)));
+    let base = 69.0 + 12.0 * (map.frequency / 440.0).log2();
+    let pitches = (0..128)
+      .map(|k| map.cents(k, scale).map(|c| base + (c - reference) / 100.0))
+      .collect();
+    Self{ pitches }
+  }
+
+  /// Loads a `.scl` file, mapped by a `.kbm` file or by [`KeyboardMap::default`].
+  pub fn load<P: AsRef<Path>>(scl: P, kbm: Option<P>) -> Result<Self, String> {
+    let scale = Scale::load(scl)?;
+    let map = kbm.map(KeyboardMap::load).transpose()?.unwrap_or_default();
+    Ok(Self::from_scala(&scale, &map))
+  }
+
+  /// Pitch of `key` as a fractional MIDI note number.
+  pub fn semitones(&self, key: u8) -> Option<f64> {
+    self.pitches.get(usize::from(key)).copied().flatten()
+  }
+
+  pub fn frequency(&self, key: u8) -> Option<f64> {
+    self.semitones(key).map(|s| 440.0 * 2f64.powf((s - 69.0) / 12.0))
+  }
+
+  /// The MIDI Tuning Standard entry of `key`, [`TuningEntry::NO_CHANGE`] if unmapped.
+  pub fn entry(&self, key: u8) -> TuningEntry {
+    self.frequency(key).map_or(TuningEntry::NO_CHANGE, TuningEntry::from_frequency)
+  }
+
+  /// Every key in one Bulk Tuning Dump, stored as tuning `program`.
+  /// `name` is cut or padded to 16 characters when sent.
+  pub fn bulk_dump(&self, device: u8, program: u8, name: &str) -> Universal {
+    let entries = (0..128).map(|k| self.entry(k)).collect();
+    Universal::TuningDump{ device, program, name: name.to_owned(), entries }
+  }
+
+  /// Single Note Tuning Changes for `keys` in tuning `program`, which
+  /// synths apply to sounding notes right away. Unmapped keys are left
+  /// out, and more than 127 keys are split over several messages.
+  pub fn note_changes(&self, device: u8, program: u8, keys: &[u8]) -> Vec<Universal> {
+    let changes: Vec<(u8, TuningEntry)> = keys
+      .iter()
+      .filter(|k| self.semitones(**k).is_some())
+      .map(|k| (*k, self.entry(*k)))
+      .collect();
+    changes
+      .chunks(127)
+      .map(|c| Universal::TuningNoteChange{ device, program, changes: c.to_vec() })
+      .collect()
+  }
+}
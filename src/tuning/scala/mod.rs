@@ -0,0 +1,177 @@
+use std::{fs, path::Path};
+
+/// Lines that carry data: `!` starts a comment.
+fn lines(text: &str) -> impl Iterator<Item = &str> {
+  text.lines().filter(|l| !l.starts_with('!'))
+}
+
+fn read<P: AsRef<Path>>(path: P) -> Result<String, String> {
+  let path = path.as_ref();
+  fs::read_to_string(path).map_err(|e| format!("could not read {}: {e}", path.display()))
+}
+
+/// A scale in the Scala `.scl` format.
+/// ```
+/// use midi::tuning::scala::Scale;
+/// let just = Scale::parse("! just.scl
+/// 5-limit just intonation major
+///  7
+///  9/8
+///  5/4
+///  4/3
+///  3/2
+///  5/3
+///  15/8
+///  2/1
+/// ").unwrap();
+/// assert_eq!(just.len(), 7);
+/// assert!((just.cents(2) - 386.31).abs() < 0.01);
+/// assert!((just.cents(7) - 1200.0).abs() < 1e-9);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scale {
+  pub description: String,
+  /// Degrees 1 and up in cents above the root, the last being the period.
+  pub degrees: Vec<f64>,
+}
+
+impl Scale {
+  pub fn parse(scl: &str) -> Result<Self, String> {
+    let mut lines = lines(scl);
+    let description = lines.next().ok_or("empty scale file")?.trim().to_owned();
+    let count: usize = lines
+      .next()
+      .and_then(|l| l.split_whitespace().next())
+      .and_then(|n| n.parse().ok())
+      .ok_or("scale file is missing the number of notes")?;
+    let degrees = lines
+      .filter(|l| !l.trim().is_empty())
+      .take(count)
+      .map(pitch)
+      .collect::<Result<Vec<f64>, String>>()?;
+    if degrees.len() != count {
+      return Err(format!("scale file has {} of its {count} notes", degrees.len()))
+    }
+    if count == 0 {
+      return Err("scale has no notes".to_owned())
+    }
+    Ok(Self{ description, degrees })
+  }
+
+  pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+    Self::parse(&read(path)?)
+  }
+
+  /// Equal temperament with `notes` steps to the octave.
+  pub fn equal(notes: usize) -> Self {
+    let notes = notes.max(1);
+    let degrees = (1..=notes).map(|d| 1200.0 * d as f64 / notes as f64).collect();
+    Self{ description: format!("{notes} tone equal temperament"), degrees }
+  }
+
+  /// Notes per period.
+  pub fn len(&self) -> usize { self.degrees.len() }
+
+  pub fn is_empty(&self) -> bool { self.degrees.is_empty() }
+
+  /// The interval the scale repeats at, usually an octave.
+  pub fn period(&self) -> f64 { self.degrees.last().copied().unwrap_or(1200.0) }
+
+  /// Cents of `degree` above the root, counting on into the periods
+  /// above and below.
+  pub fn cents(&self, degree: i32) -> f64 {
+    let n = self.len() as i32;
+    let periods = degree.div_euclid(n);
+    let step = degree.rem_euclid(n) as usize;
+    let within = if step == 0 { 0.0 } else { self.degrees[step - 1] };
+    f64::from(periods) * self.period() + within
+  }
+}
+
+/// Reads a pitch: cents if it has a dot, a ratio or whole number otherwise.
+fn pitch(line: &str) -> Result<f64, String> {
+  let value = line.split_whitespace().next().unwrap_or_default();
+  let invalid = || format!("invalid pitch in scale file: {value}");
+  if value.contains('.') {
+    return value.parse().map_err(|_| invalid())
+  }
+  let (num, den) = value.split_once('/').unwrap_or((value, "1"));
+  let num: f64 = num.parse().map_err(|_| invalid())?;
+  let den: f64 = den.parse().map_err(|_| invalid())?;
+  if num <= 0.0 || den <= 0.0 { return Err(invalid()) }
+  Ok(1200.0 * (num / den).log2())
+}
+
+/// A keyboard mapping in the Scala `.kbm` format: which key plays
+/// which degree of a [`Scale`], and the frequency everything is tuned to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyboardMap {
+  /// Keys outside of `first` - `last` are left alone.
+  pub first: u8,
+  pub last: u8,
+  /// The key that plays degree 0.
+  pub middle: u8,
+  /// The key tuned to `frequency`.
+  pub reference: u8,
+  pub frequency: f64,
+  /// Degree the mapping repeats at. Defaults to the scale's size.
+  pub octave_degree: Option<i32>,
+  /// Degree of each key in one repetition, starting at `middle`. `None`
+  /// entries are unmapped keys. Empty means key `n` plays degree `n - middle`.
+  pub map: Vec<Option<i32>>,
+}
+
+impl Default for KeyboardMap {
+  /// Middle C plays degree 0, and A above it is 440 Hz.
+  fn default() -> Self {
+    Self{ first: 0, last: 127, middle: 60, reference: 69, frequency: 440.0, octave_degree: None, map: vec![] }
+  }
+}
+
+impl KeyboardMap {
+  pub fn parse(kbm: &str) -> Result<Self, String> {
+    let mut values = lines(kbm)
+      .filter(|l| !l.trim().is_empty())
+      .map(|l| l.split_whitespace().next().unwrap_or_default());
+    let mut next = |what: &str| values.next().ok_or_else(|| format!("keyboard mapping is missing the {what}"));
+    let number = |v: &str, what: &str| v.parse::<i64>().map_err(|_| format!("invalid {what} in keyboard mapping: {v}"));
+    let key = |v: &str, what: &str| {
+      number(v, what).and_then(|n| u8::try_from(n).ok().filter(|n| *n < 128).ok_or(format!("{what} {n} is not a MIDI note")))
+    };
+
+    let size = number(next("map size")?, "map size")?;
+    let first = key(next("first note")?, "first note")?;
+    let last = key(next("last note")?, "last note")?;
+    let middle = key(next("middle note")?, "middle note")?;
+    let reference = key(next("reference note")?, "reference note")?;
+    let frequency = next("reference frequency")?;
+    let frequency: f64 = frequency.parse().map_err(|_| format!("invalid reference frequency in keyboard mapping: {frequency}"))?;
+    let octave_degree = number(next("octave degree")?, "octave degree")? as i32;
+    let map = (0..size)
+      .map(|_| match next("mapping")? {
+        "x" | "X" => Ok(None),
+        v => number(v, "mapping").map(|d| Some(d as i32)),
+      })
+      .collect::<Result<Vec<_>, String>>()?;
+    if frequency <= 0.0 {
+      return Err(format!("reference frequency {frequency} is not above 0"))
+    }
+    let octave_degree = (octave_degree > 0).then_some(octave_degree);
+    Ok(Self{ first, last, middle, reference, frequency, octave_degree, map })
+  }
+
+  pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+    Self::parse(&read(path)?)
+  }
+
+  /// Pitch of `key` in cents above `middle`, or `None` if the key is unmapped.
+  pub fn cents(&self, key: u8, scale: &Scale) -> Option<f64> {
+    if !(self.first..=self.last).contains(&key) { return None }
+    let offset = i32::from(key) - i32::from(self.middle);
+    if self.map.is_empty() { return Some(scale.cents(offset)) }
+    let size = self.map.len() as i32;
+    let entry = self.map[offset.rem_euclid(size) as usize]?;
+    let octave = scale.cents(self.octave_degree.unwrap_or(scale.len() as i32));
+    Some(scale.cents(entry) + f64::from(offset.div_euclid(size)) * octave)
+  }
+}
@@ -0,0 +1,124 @@
+use super::*;
+
+/// Status banks of Flex Data messages.
+pub mod bank {
+  pub const SETUP_AND_PERFORMANCE: u8 = 0x00;
+  pub const METADATA_TEXT:         u8 = 0x01;
+  pub const PERFORMANCE_TEXT:      u8 = 0x02;
+}
+
+/// Statuses of the Setup and Performance bank.
+pub mod status {
+  pub const SET_TEMPO:             u8 = 0x00;
+  pub const SET_TIME_SIGNATURE:    u8 = 0x01;
+  pub const SET_METRONOME:         u8 = 0x02;
+  pub const SET_KEY_SIGNATURE:     u8 = 0x05;
+  pub const SET_CHORD_NAME:        u8 = 0x06;
+}
+
+/// What a Flex Data message applies to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FlexAddress {
+  Channel(Channel),
+  Group,
+}
+
+/// A Flex Data packet, Message Type 0xD. Text and other payloads longer
+/// than 12 bytes are split over several packets, see [`Chunk`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FlexData {
+  pub group: u8,
+  pub chunk: Chunk,
+  pub address: FlexAddress,
+  pub bank: u8,
+  pub status: u8,
+  pub data: [u8; 12],
+}
+
+impl FlexData {
+  pub fn encode(&self) -> Vec<u32> {
+    let (addrs, ch) = match self.address {
+      FlexAddress::Channel(ch) => (0, ch.0 & 0xf),
+      FlexAddress::Group => (1, 0),
+    };
+    let mut bytes = [0u8; 16];
+    bytes[0] = header(mt::FLEX_DATA, self.group);
+    bytes[1] = (self.chunk as u8) << 6 | addrs << 4 | ch;
+    bytes[2] = self.bank;
+    bytes[3] = self.status;
+    bytes[4..].copy_from_slice(&self.data);
+    pack(&bytes)
+  }
+
+  pub fn decode(words: &[u32]) -> Result<Self, UmpError> {
+    if words.len() < 4 {
+      return Err(UmpError::Truncated{ needed: 4, got: words.len() })
+    }
+    let bytes = unpack(&words[..4]);
+    let address = match (bytes[1] >> 4) & 0x3 {
+      0 => FlexAddress::Channel(Channel(bytes[1] & 0xf)),
+      1 => FlexAddress::Group,
+      a => return Err(UmpError::Invalid(format!("Flex Data address {a}")))
+    };
+    let mut data = [0u8; 12];
+    data.copy_from_slice(&bytes[4..]);
+    Ok(Self{
+      group: bytes[0] & 0xf,
+      chunk: Chunk::from_bits(bytes[1] >> 6).unwrap_or(Chunk::Complete),
+      address,
+      bank: bytes[2],
+      status: bytes[3],
+      data,
+    })
+  }
+
+  /// Set Tempo, in units of 10 nanoseconds per quarter note.
+  pub fn tempo(group: u8, ten_ns_per_quarter: u32) -> Self {
+    let mut data = [0u8; 12];
+    data[..4].copy_from_slice(&ten_ns_per_quarter.to_be_bytes());
+    Self{
+      group,
+      chunk: Chunk::Complete,
+      address: FlexAddress::Group,
+      bank: bank::SETUP_AND_PERFORMANCE,
+      status: status::SET_TEMPO,
+      data
+    }
+  }
+
+  /// Set Tempo from beats per minute.
+  pub fn tempo_bpm(group: u8, bpm: f64) -> Self {
+    Self::tempo(group, (6_000_000_000.0 / bpm).round() as u32)
+  }
+
+  /// Splits UTF-8 `text` over as many packets as needed, 12 bytes each.
+  /// ```
+  /// use midi::ump::{flex::{bank, FlexAddress, FlexData}, Chunk};
+  /// let packets = FlexData::text(0, FlexAddress::Group, bank::METADATA_TEXT, 0x01, "Twenty Characters!!!");
+  /// assert_eq!(packets.len(), 2);
+  /// assert_eq!(packets[1].chunk, Chunk::End);
+  /// assert_eq!(FlexData::collect_text(&packets), "Twenty Characters!!!");
+  /// ```
+  pub fn text(group: u8, address: FlexAddress, bank: u8, status: u8, text: &str) -> Vec<Self> {
+    let bytes = text.as_bytes();
+    let n = bytes.len().div_ceil(12).max(1);
+    (0..n)
+      .map(|i| {
+        let mut data = [0u8; 12];
+        let part = &bytes[(i * 12).min(bytes.len())..((i + 1) * 12).min(bytes.len())];
+        data[..part.len()].copy_from_slice(part);
+        Self{ group, chunk: Chunk::of(i, n), address, bank, status, data }
+      })
+      .collect()
+  }
+
+  /// Joins the text carried by `packets`, dropping the zero padding.
+  pub fn collect_text(packets: &[Self]) -> String {
+    let bytes: Vec<u8> = packets
+      .iter()
+      .flat_map(|p| p.data)
+      .filter(|&b| b != 0)
+      .collect();
+    String::from_utf8_lossy(&bytes).into_owned()
+  }
+}
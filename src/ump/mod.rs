@@ -0,0 +1,426 @@
+pub mod translate;
+pub mod flex;
+
+use std::fmt::Display;
+
+use crate::{
+  message::event::MidiEvent,
+  util::Channel,
+};
+
+pub use flex::{FlexAddress, FlexData};
+
+/// Message Type, the top nibble of every Universal MIDI Packet.
+pub mod mt {
+  pub const UTILITY:            u8 = 0x0;
+  pub const SYSTEM:             u8 = 0x1;
+  pub const MIDI1_CHANNEL_VOICE: u8 = 0x2;
+  pub const DATA64:             u8 = 0x3;
+  pub const MIDI2_CHANNEL_VOICE: u8 = 0x4;
+  pub const DATA128:            u8 = 0x5;
+  pub const FLEX_DATA:          u8 = 0xD;
+  pub const UMP_STREAM:         u8 = 0xF;
+}
+
+/// Returns the size in 32 bit words of a packet of message type `mt`.
+pub fn packet_words(mt: u8) -> usize {
+  match mt & 0xf {
+    0x0..=0x2 | 0x6 | 0x7 => 1,
+    0x3 | 0x4 | 0x8..=0xA => 2,
+    0xB | 0xC => 3,
+    _ => 4,
+  }
+}
+
+/// Position of a packet within a message split over several packets.
+/// Shared by SysEx (7 and 8 bit) and Flex Data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Chunk {
+  Complete = 0,
+  Start    = 1,
+  Continue = 2,
+  End      = 3,
+}
+
+impl Chunk {
+  fn from_bits(bits: u8) -> Option<Self> {
+    match bits {
+      0 => Some(Self::Complete),
+      1 => Some(Self::Start),
+      2 => Some(Self::Continue),
+      3 => Some(Self::End),
+      _ => None
+    }
+  }
+
+  /// The chunk status of packet `i` out of `n`.
+  pub fn of(i: usize, n: usize) -> Self {
+    match (i, n) {
+      (_, 0 | 1) => Self::Complete,
+      (0, _) => Self::Start,
+      (i, n) if i + 1 == n => Self::End,
+      _ => Self::Continue,
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Utility {
+  Noop,
+  /// Jitter Reduction Clock, in units of 1/31250 seconds.
+  JrClock(u16),
+  /// Jitter Reduction Timestamp, in units of 1/31250 seconds.
+  JrTimestamp(u16),
+  DeltaTicksPerQuarter(u16),
+  /// 20 bit tick count since the last event.
+  DeltaTicks(u32),
+}
+
+/// MIDI 2.0 Channel Voice messages, values at full resolution.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Midi2Message {
+  NoteOff { note: u8, velo: u16, attribute: u8, attribute_data: u16 },
+  NoteOn { note: u8, velo: u16, attribute: u8, attribute_data: u16 },
+  PolyPressure { note: u8, value: u32 },
+  RegisteredPerNoteController { note: u8, index: u8, value: u32 },
+  AssignablePerNoteController { note: u8, index: u8, value: u32 },
+  /// RPN: `bank` and `index` are the MSB and LSB of the parameter number.
+  RegisteredController { bank: u8, index: u8, value: u32 },
+  /// NRPN: `bank` and `index` are the MSB and LSB of the parameter number.
+  AssignableController { bank: u8, index: u8, value: u32 },
+  RelativeRegisteredController { bank: u8, index: u8, value: i32 },
+  RelativeAssignableController { bank: u8, index: u8, value: i32 },
+  /// `0x8000_0000` is the centre.
+  PerNotePitchBend { note: u8, value: u32 },
+  ControlChange { index: u8, value: u32 },
+  /// `bank` is the Bank Select MSB and LSB, if any.
+  ProgramChange { program: u8, bank: Option<(u8, u8)> },
+  ChannelPressure { value: u32 },
+  /// `0x8000_0000` is the centre.
+  PitchBend { value: u32 },
+  PerNoteManagement { note: u8, detach: bool, reset: bool },
+}
+
+impl Midi2Message {
+  fn opcode(&self) -> u8 {
+    match self {
+      Self::RegisteredPerNoteController { .. } => 0x0,
+      Self::AssignablePerNoteController { .. } => 0x1,
+      Self::RegisteredController { .. } => 0x2,
+      Self::AssignableController { .. } => 0x3,
+      Self::RelativeRegisteredController { .. } => 0x4,
+      Self::RelativeAssignableController { .. } => 0x5,
+      Self::PerNotePitchBend { .. } => 0x6,
+      Self::NoteOff { .. } => 0x8,
+      Self::NoteOn { .. } => 0x9,
+      Self::PolyPressure { .. } => 0xA,
+      Self::ControlChange { .. } => 0xB,
+      Self::ProgramChange { .. } => 0xC,
+      Self::ChannelPressure { .. } => 0xD,
+      Self::PitchBend { .. } => 0xE,
+      Self::PerNoteManagement { .. } => 0xF,
+    }
+  }
+}
+
+/// A Universal MIDI Packet.
+/// ```
+/// use midi::{ump::{Ump, Midi2Message}, util::Channel};
+/// let packet = Ump::Midi2{
+///   group: 0,
+///   ch: Channel(2),
+///   message: Midi2Message::NoteOn{ note: 60, velo: 0xffff, attribute: 0, attribute_data: 0 },
+/// };
+/// let words = packet.encode();
+/// assert_eq!(words, vec![0x4092_3c00, 0xffff_0000]);
+/// assert_eq!(Ump::decode(&words), Ok((packet, 2)));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Ump {
+  /// Message Type 0x0, without a group.
+  Utility(Utility),
+  /// Message Type 0x1: System Common and System Real Time.
+  /// SysEx does not fit here, see [`Ump::sysex7`].
+  System { group: u8, event: MidiEvent },
+  /// Message Type 0x2: MIDI 1.0 Channel Voice, as in the byte protocol.
+  Midi1 { group: u8, event: MidiEvent },
+  /// Message Type 0x3: 7 bit SysEx, up to 6 bytes per packet, without `0xF0`/`0xF7`.
+  SysEx7 { group: u8, chunk: Chunk, data: Vec<u8> },
+  /// Message Type 0x4: MIDI 2.0 Channel Voice.
+  Midi2 { group: u8, ch: Channel, message: Midi2Message },
+  /// Message Type 0x5, status 0 - 3: 8 bit SysEx, up to 13 bytes per packet.
+  SysEx8 { group: u8, chunk: Chunk, stream: u8, data: Vec<u8> },
+  /// Message Type 0x5, status 8 (header) and 9 (payload): Mixed Data Set.
+  MixedDataSet { group: u8, status: u8, id: u8, bytes: [u8; 13] },
+  /// Message Type 0xD.
+  FlexData(FlexData),
+  /// Any other message type, including UMP Stream, kept as is.
+  Unknown(Vec<u32>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UmpError {
+  /// The packet needs more words than were given.
+  Truncated { needed: usize, got: usize },
+  /// A status, opcode or length field holds a value the spec does not define.
+  Invalid(String),
+}
+
+impl Display for UmpError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Self::Truncated{ needed, got } => write!(f, "UMP needs {needed} words, got {got}"),
+      Self::Invalid(s) => write!(f, "Invalid UMP: {s}"),
+    }
+  }
+}
+
+impl std::error::Error for UmpError {}
+
+impl Ump {
+  /// Returns the packet as 32 bit words, most significant byte first.
+  pub fn encode(&self) -> Vec<u32> {
+    match self {
+      Self::Utility(u) => {
+        let (status, value) = match *u {
+          Utility::Noop => (0x0, 0),
+          Utility::JrClock(v) => (0x1, u32::from(v)),
+          Utility::JrTimestamp(v) => (0x2, u32::from(v)),
+          Utility::DeltaTicksPerQuarter(v) => (0x3, u32::from(v)),
+          Utility::DeltaTicks(v) => (0x4, v & 0xf_ffff),
+        };
+        vec![(status << 20) | value]
+      },
+      Self::System { group, event } | Self::Midi1 { group, event } => {
+        let mt = if matches!(self, Self::System { .. }) { mt::SYSTEM } else { mt::MIDI1_CHANNEL_VOICE };
+        let bytes = event.to_bytes();
+        let b = |i: usize| bytes.get(i).copied().unwrap_or(0);
+        pack(&[header(mt, *group), b(0), b(1), b(2)])
+      },
+      Self::SysEx7 { group, chunk, data } => {
+        let n = data.len().min(6);
+        let mut bytes = [0u8; 8];
+        bytes[0] = header(mt::DATA64, *group);
+        bytes[1] = (*chunk as u8) << 4 | n as u8;
+        data.iter().take(n).enumerate().for_each(|(i, d)| bytes[2 + i] = d & 0x7f);
+        pack(&bytes)
+      },
+      Self::Midi2 { group, ch, message } => {
+        let (b2, b3, value) = match *message {
+          Midi2Message::NoteOff { note, velo, attribute, attribute_data }
+          | Midi2Message::NoteOn { note, velo, attribute, attribute_data } =>
+            (note, attribute, u32::from(velo) << 16 | u32::from(attribute_data)),
+          Midi2Message::PolyPressure { note, value }
+          | Midi2Message::PerNotePitchBend { note, value } => (note, 0, value),
+          Midi2Message::RegisteredPerNoteController { note, index, value }
+          | Midi2Message::AssignablePerNoteController { note, index, value } => (note, index, value),
+          Midi2Message::RegisteredController { bank, index, value }
+          | Midi2Message::AssignableController { bank, index, value } => (bank, index, value),
+          Midi2Message::RelativeRegisteredController { bank, index, value }
+          | Midi2Message::RelativeAssignableController { bank, index, value } => (bank, index, value as u32),
+          Midi2Message::ControlChange { index, value } => (index, 0, value),
+          Midi2Message::ProgramChange { program, bank } => {
+            let (msb, lsb) = bank.unwrap_or((0, 0));
+            (0, u8::from(bank.is_some()), u32::from(program & 0x7f) << 24 | u32::from(msb & 0x7f) << 8 | u32::from(lsb & 0x7f))
+          },
+          Midi2Message::ChannelPressure { value }
+          | Midi2Message::PitchBend { value } => (0, 0, value),
+          Midi2Message::PerNoteManagement { note, detach, reset } =>
+            (note, u8::from(detach) << 1 | u8::from(reset), 0),
+        };
+        let first = pack(&[header(mt::MIDI2_CHANNEL_VOICE, *group), message.opcode() << 4 | ch.0 & 0xf, b2 & 0x7f, b3])[0];
+        vec![first, value]
+      },
+      Self::SysEx8 { group, chunk, stream, data } => {
+        let n = data.len().min(13);
+        let mut bytes = [0u8; 16];
+        bytes[0] = header(mt::DATA128, *group);
+        // the byte count includes the stream id
+        bytes[1] = (*chunk as u8) << 4 | (n as u8 + 1);
+        bytes[2] = *stream;
+        bytes[3..3 + n].copy_from_slice(&data[..n]);
+        pack(&bytes)
+      },
+      Self::MixedDataSet { group, status, id, bytes: payload } => {
+        let mut bytes = [0u8; 16];
+        bytes[0] = header(mt::DATA128, *group);
+        bytes[1] = (status & 0xf) << 4 | (id & 0xf);
+        bytes[2..15].copy_from_slice(payload);
+        pack(&bytes)
+      },
+      Self::FlexData(flex) => flex.encode(),
+      Self::Unknown(words) => words.clone(),
+    }
+  }
+
+  /// Decodes the packet at the start of `words`, returning it together
+  /// with the number of words it took up.
+  pub fn decode(words: &[u32]) -> Result<(Self, usize), UmpError> {
+    let first = *words.first().ok_or(UmpError::Truncated{ needed: 1, got: 0 })?;
+    let mt = (first >> 28) as u8;
+    let len = packet_words(mt);
+    if words.len() < len {
+      return Err(UmpError::Truncated{ needed: len, got: words.len() })
+    }
+    let words = &words[..len];
+    let bytes = unpack(words);
+    let group = bytes[0] & 0xf;
+    let ump = match mt {
+      mt::UTILITY => {
+        let value = first & 0xf_ffff;
+        Self::Utility(match (first >> 20) & 0xf {
+          0x0 => Utility::Noop,
+          0x1 => Utility::JrClock(value as u16),
+          0x2 => Utility::JrTimestamp(value as u16),
+          0x3 => Utility::DeltaTicksPerQuarter(value as u16),
+          0x4 => Utility::DeltaTicks(value),
+          s => return Err(UmpError::Invalid(format!("utility status {s}")))
+        })
+      },
+      mt::SYSTEM | mt::MIDI1_CHANNEL_VOICE => {
+        let event = MidiEvent::parse(&bytes[1..4])
+          .ok_or_else(|| UmpError::Invalid(format!("status byte {:#04x}", bytes[1])))?;
+        match (mt, event.channel()) {
+          (_, None) if matches!(event, MidiEvent::SysEx(_)) => {
+            return Err(UmpError::Invalid("SysEx in a 32 bit packet".to_owned()))
+          },
+          (mt::SYSTEM, None) => Self::System{ group, event },
+          (mt::MIDI1_CHANNEL_VOICE, Some(_)) => Self::Midi1{ group, event },
+          _ => return Err(UmpError::Invalid(format!("status byte {:#04x} for message type {mt}", bytes[1])))
+        }
+      },
+      mt::DATA64 => {
+        let chunk = Chunk::from_bits(bytes[1] >> 4)
+          .ok_or_else(|| UmpError::Invalid(format!("SysEx7 status {}", bytes[1] >> 4)))?;
+        let n = usize::from(bytes[1] & 0xf);
+        if n > 6 { return Err(UmpError::Invalid(format!("SysEx7 with {n} bytes"))) }
+        Self::SysEx7{ group, chunk, data: bytes[2..2 + n].to_vec() }
+      },
+      mt::MIDI2_CHANNEL_VOICE => Self::Midi2{
+        group,
+        ch: Channel(bytes[1] & 0xf),
+        message: decode_midi2(bytes[1] >> 4, bytes[2], bytes[3], words[1])?
+      },
+      mt::DATA128 => match bytes[1] >> 4 {
+        status @ 0..=3 => {
+          let n = usize::from(bytes[1] & 0xf);
+          if !(1..=14).contains(&n) { return Err(UmpError::Invalid(format!("SysEx8 with {n} bytes"))) }
+          Self::SysEx8{
+            group,
+            chunk: Chunk::from_bits(status).unwrap_or(Chunk::Complete),
+            stream: bytes[2],
+            data: bytes[3..2 + n].to_vec()
+          }
+        },
+        status @ (8 | 9) => {
+          let mut payload = [0u8; 13];
+          payload.copy_from_slice(&bytes[2..15]);
+          Self::MixedDataSet{ group, status, id: bytes[1] & 0xf, bytes: payload }
+        },
+        s => return Err(UmpError::Invalid(format!("Data128 status {s}")))
+      },
+      mt::FLEX_DATA => Self::FlexData(FlexData::decode(words)?),
+      _ => Self::Unknown(words.to_vec()),
+    };
+    Ok((ump, len))
+  }
+
+  /// Decodes every packet in `words`.
+  pub fn decode_all(words: &[u32]) -> Result<Vec<Self>, UmpError> {
+    let mut packets = vec![];
+    let mut i = 0;
+    while i < words.len() {
+      let (ump, used) = Self::decode(&words[i..])?;
+      packets.push(ump);
+      i += used;
+    }
+    Ok(packets)
+  }
+
+  /// Returns the group of the packet, if it has one.
+  pub fn group(&self) -> Option<u8> {
+    match self {
+      Self::System { group, .. }
+      | Self::Midi1 { group, .. }
+      | Self::SysEx7 { group, .. }
+      | Self::Midi2 { group, .. }
+      | Self::SysEx8 { group, .. }
+      | Self::MixedDataSet { group, .. } => Some(*group),
+      Self::FlexData(flex) => Some(flex.group),
+      Self::Utility(_) | Self::Unknown(_) => None,
+    }
+  }
+
+  /// Splits a SysEx message into 7 bit SysEx packets.
+  /// The `0xF0` and `0xF7` framing is dropped if present.
+  pub fn sysex7(group: u8, sysex: &[u8]) -> Vec<Self> {
+    let data = unframe(sysex);
+    let n = data.len().div_ceil(6).max(1);
+    (0..n)
+      .map(|i| Self::SysEx7{
+        group,
+        chunk: Chunk::of(i, n),
+        data: data[(i * 6).min(data.len())..((i + 1) * 6).min(data.len())].to_vec()
+      })
+      .collect()
+  }
+
+  /// Splits 8 bit data into SysEx8 packets on `stream`.
+  pub fn sysex8(group: u8, stream: u8, data: &[u8]) -> Vec<Self> {
+    let n = data.len().div_ceil(13).max(1);
+    (0..n)
+      .map(|i| Self::SysEx8{
+        group,
+        chunk: Chunk::of(i, n),
+        stream,
+        data: data[(i * 13).min(data.len())..((i + 1) * 13).min(data.len())].to_vec()
+      })
+      .collect()
+  }
+}
+
+fn decode_midi2(opcode: u8, b2: u8, b3: u8, value: u32) -> Result<Midi2Message, UmpError> {
+  let note = b2 & 0x7f;
+  Ok(match opcode {
+    0x0 => Midi2Message::RegisteredPerNoteController{ note, index: b3, value },
+    0x1 => Midi2Message::AssignablePerNoteController{ note, index: b3, value },
+    0x2 => Midi2Message::RegisteredController{ bank: note, index: b3 & 0x7f, value },
+    0x3 => Midi2Message::AssignableController{ bank: note, index: b3 & 0x7f, value },
+    0x4 => Midi2Message::RelativeRegisteredController{ bank: note, index: b3 & 0x7f, value: value as i32 },
+    0x5 => Midi2Message::RelativeAssignableController{ bank: note, index: b3 & 0x7f, value: value as i32 },
+    0x6 => Midi2Message::PerNotePitchBend{ note, value },
+    0x8 => Midi2Message::NoteOff{ note, velo: (value >> 16) as u16, attribute: b3, attribute_data: value as u16 },
+    0x9 => Midi2Message::NoteOn{ note, velo: (value >> 16) as u16, attribute: b3, attribute_data: value as u16 },
+    0xA => Midi2Message::PolyPressure{ note, value },
+    0xB => Midi2Message::ControlChange{ index: note, value },
+    0xC => Midi2Message::ProgramChange{
+      program: (value >> 24) as u8 & 0x7f,
+      bank: (b3 & 1 == 1).then_some(((value >> 8) as u8 & 0x7f, value as u8 & 0x7f))
+    },
+    0xD => Midi2Message::ChannelPressure{ value },
+    0xE => Midi2Message::PitchBend{ value },
+    0xF => Midi2Message::PerNoteManagement{ note, detach: b3 & 2 != 0, reset: b3 & 1 != 0 },
+    op => return Err(UmpError::Invalid(format!("MIDI 2.0 opcode {op:#x}")))
+  })
+}
+
+#[inline]
+fn header(mt: u8, group: u8) -> u8 { (mt & 0xf) << 4 | (group & 0xf) }
+
+/// Packs bytes into big endian words. `bytes.len()` must be a multiple of 4.
+fn pack(bytes: &[u8]) -> Vec<u32> {
+  bytes
+    .chunks_exact(4)
+    .map(|c| u32::from_be_bytes([c[0], c[1], c[2], c[3]]))
+    .collect()
+}
+
+fn unpack(words: &[u32]) -> Vec<u8> {
+  words.iter().flat_map(|w| w.to_be_bytes()).collect()
+}
+
+/// Strips the `0xF0` and `0xF7` framing off a SysEx message, if present.
+fn unframe(sysex: &[u8]) -> &[u8] {
+  let sysex = sysex.strip_prefix(&[0xF0]).unwrap_or(sysex);
+  sysex.strip_suffix(&[0xF7]).unwrap_or(sysex)
+}
@@ -0,0 +1,304 @@
+use super::*;
+
+use crate::consts::message::{
+  NRPN_LSB, NRPN_MSB, NRPN_VAL_LSB, NRPN_VAL_MSB, RPN_LSB, RPN_MSB,
+};
+use crate::message::{Message, MessageKind};
+
+const BANK_MSB: u8 = 0x00;
+const BANK_LSB: u8 = 0x20;
+
+/// Scales `value` from `src_bits` up to `dst_bits` with the MIDI 2.0
+/// Min-Center-Max algorithm: minimum, centre and maximum map exactly.
+/// ```
+/// use midi::ump::translate::upscale;
+/// assert_eq!(upscale(0, 7, 16), 0);
+/// assert_eq!(upscale(64, 7, 16), 0x8000);
+/// assert_eq!(upscale(127, 7, 16), 0xffff);
+/// ```
+pub fn upscale(value: u32, src_bits: u32, dst_bits: u32) -> u32 {
+  if src_bits >= dst_bits { return value }
+  let scale_bits = dst_bits - src_bits;
+  let shifted = value << scale_bits;
+  if value <= 1 << (src_bits - 1) { return shifted }
+  let repeat_bits = src_bits - 1;
+  let repeat_mask = (1 << repeat_bits) - 1;
+  let mut repeat = value & repeat_mask;
+  if scale_bits > repeat_bits {
+    repeat <<= scale_bits - repeat_bits;
+  } else {
+    repeat >>= repeat_bits - scale_bits;
+  }
+  let mut result = shifted;
+  while repeat != 0 {
+    result |= repeat;
+    repeat >>= repeat_bits;
+  }
+  result
+}
+
+/// Scales `value` from `src_bits` down to `dst_bits` by dropping the low bits.
+pub fn downscale(value: u32, src_bits: u32, dst_bits: u32) -> u32 {
+  if src_bits <= dst_bits { return value }
+  value >> (src_bits - dst_bits)
+}
+
+/// How MIDI 1.0 messages are carried in UMP.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Translation {
+  /// MIDI 1.0 Channel Voice packets (Message Type 0x2), byte for byte.
+  Lossless,
+  /// MIDI 2.0 Channel Voice packets (Message Type 0x4), values scaled up.
+  /// RPN/NRPN sequences become single controller messages, and Bank
+  /// Select is folded into the Program Change that follows it.
+  Scaled,
+}
+
+#[derive(Clone, Copy, Default)]
+struct ChannelState {
+  rpn: (Option<u8>, Option<u8>),
+  nrpn: (Option<u8>, Option<u8>),
+  // true if NRPN was selected last, false for RPN
+  nrpn_active: bool,
+  value_msb: Option<u8>,
+  bank: (Option<u8>, Option<u8>),
+}
+
+impl ChannelState {
+  fn parameter(&self) -> Option<(bool, u8, u8)> {
+    let (msb, lsb) = if self.nrpn_active { self.nrpn } else { self.rpn };
+    match (msb?, lsb?) {
+      (127, 127) => None,
+      (msb, lsb) => Some((self.nrpn_active, msb, lsb))
+    }
+  }
+}
+
+/// Translates MIDI 1.0 byte messages into Universal MIDI Packets.
+/// ```
+/// use midi::{message::Message, ump::{translate::{ToUmp, Translation}, Ump, Midi2Message}, util::Channel};
+/// let mut ump = ToUmp::new(0, Translation::Scaled);
+/// let nrpn = Message::nrpn((1, 2), (0x7f, 0x7f)).unwrap();
+/// let packets = ump.message(&nrpn, Channel(0));
+/// assert_eq!(packets, vec![Ump::Midi2{
+///   group: 0,
+///   ch: Channel(0),
+///   message: Midi2Message::AssignableController{ bank: 1, index: 2, value: u32::MAX },
+/// }]);
+/// ```
+pub struct ToUmp {
+  group: u8,
+  translation: Translation,
+  channels: [ChannelState; 16],
+}
+
+impl ToUmp {
+  pub fn new(group: u8, translation: Translation) -> Self {
+    Self{ group: group & 0xf, translation, channels: [ChannelState::default(); 16] }
+  }
+
+  /// Translates a [`Message`] as it would be sent on `ch`.
+  pub fn message<T: MessageKind>(&mut self, message: &Message<T>, ch: Channel) -> Vec<Ump> {
+    self.bytes(&message.to_bytes(ch))
+  }
+
+  /// Translates every message in `bytes`.
+  pub fn bytes(&mut self, bytes: &[u8]) -> Vec<Ump> {
+    MidiEvent::parse_all(bytes)
+      .iter()
+      .flat_map(|e| self.event(e))
+      .collect()
+  }
+
+  pub fn event(&mut self, event: &MidiEvent) -> Vec<Ump> {
+    let group = self.group;
+    let Some(ch) = event.channel() else {
+      return match event {
+        MidiEvent::SysEx(data) => Ump::sysex7(group, data),
+        event => vec![Ump::System{ group, event: event.clone() }]
+      }
+    };
+    if self.translation == Translation::Lossless {
+      return vec![Ump::Midi1{ group, event: event.clone() }]
+    }
+
+    let mut packets = vec![];
+    let state = &mut self.channels[usize::from(ch.0 & 0xf)];
+    let is_data_entry_lsb = matches!(event, MidiEvent::Cc{ addr: NRPN_VAL_LSB, .. });
+    if !is_data_entry_lsb {
+      packets.extend(flush(group, ch, state));
+    }
+    let midi2 = |message| Ump::Midi2{ group, ch, message };
+    let message = match *event {
+      MidiEvent::NoteOn{ note, velo: 0, .. } => {
+        // a MIDI 1.0 note off carries velocity 64 by convention
+        Midi2Message::NoteOff{ note, velo: upscale(64, 7, 16) as u16, attribute: 0, attribute_data: 0 }
+      },
+      MidiEvent::NoteOn{ note, velo, .. } => {
+        Midi2Message::NoteOn{ note, velo: upscale(velo.into(), 7, 16) as u16, attribute: 0, attribute_data: 0 }
+      },
+      MidiEvent::NoteOff{ note, velo, .. } => {
+        Midi2Message::NoteOff{ note, velo: upscale(velo.into(), 7, 16) as u16, attribute: 0, attribute_data: 0 }
+      },
+      MidiEvent::PolyPressure{ note, pressure, .. } => {
+        Midi2Message::PolyPressure{ note, value: upscale(pressure.into(), 7, 32) }
+      },
+      MidiEvent::ChannelPressure{ pressure, .. } => {
+        Midi2Message::ChannelPressure{ value: upscale(pressure.into(), 7, 32) }
+      },
+      MidiEvent::PitchBend{ value, .. } => {
+        Midi2Message::PitchBend{ value: upscale(value.into(), 14, 32) }
+      },
+      MidiEvent::ProgramChange{ program, .. } => {
+        let bank = match state.bank {
+          (None, None) => None,
+          (msb, lsb) => Some((msb.unwrap_or(0), lsb.unwrap_or(0)))
+        };
+        Midi2Message::ProgramChange{ program, bank }
+      },
+      MidiEvent::Cc{ addr, val, .. } => match addr {
+        BANK_MSB => { state.bank.0 = Some(val); return packets },
+        BANK_LSB => { state.bank.1 = Some(val); return packets },
+        RPN_MSB => { state.rpn.0 = Some(val); state.nrpn_active = false; return packets },
+        RPN_LSB => { state.rpn.1 = Some(val); state.nrpn_active = false; return packets },
+        NRPN_MSB => { state.nrpn.0 = Some(val); state.nrpn_active = true; return packets },
+        NRPN_LSB => { state.nrpn.1 = Some(val); state.nrpn_active = true; return packets },
+        NRPN_VAL_MSB if state.parameter().is_some() => {
+          state.value_msb = Some(val);
+          return packets
+        },
+        NRPN_VAL_LSB if state.parameter().is_some() => {
+          let msb = state.value_msb.take().unwrap_or(0);
+          packets.extend(controller(group, ch, state, msb, val));
+          return packets
+        },
+        _ => Midi2Message::ControlChange{ index: addr, value: upscale(val.into(), 7, 32) }
+      },
+      _ => return packets
+    };
+    packets.push(midi2(message));
+    packets
+  }
+
+  /// Sends any Data Entry MSB still waiting for its LSB.
+  pub fn flush(&mut self) -> Vec<Ump> {
+    let group = self.group;
+    self.channels
+      .iter_mut()
+      .enumerate()
+      .flat_map(|(ch, state)| flush(group, Channel(ch as u8), state))
+      .collect()
+  }
+}
+
+fn flush(group: u8, ch: Channel, state: &mut ChannelState) -> Option<Ump> {
+  let msb = state.value_msb.take()?;
+  controller(group, ch, state, msb, 0)
+}
+
+fn controller(group: u8, ch: Channel, state: &ChannelState, msb: u8, lsb: u8) -> Option<Ump> {
+  let (nrpn, bank, index) = state.parameter()?;
+  let value = upscale(u32::from(msb) << 7 | u32::from(lsb), 14, 32);
+  let message = if nrpn {
+    Midi2Message::AssignableController{ bank, index, value }
+  } else {
+    Midi2Message::RegisteredController{ bank, index, value }
+  };
+  Some(Ump::Midi2{ group, ch, message })
+}
+
+/// Translates Universal MIDI Packets back into MIDI 1.0 messages.
+///
+/// MIDI 2.0 values are scaled down, and controller messages become the
+/// CC sequences MIDI 1.0 uses for them. Per-note controllers, per-note
+/// pitch bend and other MIDI 2.0 only messages have no MIDI 1.0 form
+/// and are dropped, as are Flex Data, SysEx8 and Mixed Data Set packets.
+/// ```
+/// use midi::{message::event::MidiEvent, ump::{translate::FromUmp, Ump}};
+/// let mut midi1 = FromUmp::new();
+/// let sysex = [0xF0, 0x7E, 0x7F, 0x06, 0x01, 0x00, 0x11, 0xF7];
+/// let events: Vec<MidiEvent> = Ump::sysex7(0, &sysex)
+///   .iter()
+///   .flat_map(|p| midi1.packet(p))
+///   .collect();
+/// assert_eq!(events, vec![MidiEvent::SysEx(sysex.to_vec())]);
+/// ```
+#[derive(Default)]
+pub struct FromUmp {
+  sysex: [Vec<u8>; 16],
+}
+
+impl FromUmp {
+  pub fn new() -> Self { Self::default() }
+
+  /// Translates one packet. SysEx is returned once its last packet arrives.
+  pub fn packet(&mut self, ump: &Ump) -> Vec<MidiEvent> {
+    match ump {
+      Ump::System{ event, .. } | Ump::Midi1{ event, .. } => vec![event.clone()],
+      Ump::SysEx7{ group, chunk, data } => {
+        let buffer = &mut self.sysex[usize::from(group & 0xf)];
+        if matches!(chunk, Chunk::Complete | Chunk::Start) {
+          buffer.clear();
+          buffer.push(0xF0);
+        }
+        buffer.extend_from_slice(data);
+        if matches!(chunk, Chunk::Complete | Chunk::End) {
+          buffer.push(0xF7);
+          return vec![MidiEvent::SysEx(std::mem::take(buffer))]
+        }
+        vec![]
+      },
+      Ump::Midi2{ ch, message, .. } => midi2_to_midi1(*ch, message),
+      _ => vec![]
+    }
+  }
+
+  /// Translates `packets` into MIDI 1.0 bytes.
+  pub fn bytes(&mut self, packets: &[Ump]) -> Vec<u8> {
+    packets
+      .iter()
+      .flat_map(|p| self.packet(p))
+      .flat_map(|e| e.to_bytes())
+      .collect()
+  }
+}
+
+fn midi2_to_midi1(ch: Channel, message: &Midi2Message) -> Vec<MidiEvent> {
+  let seven = |v: u32, bits: u32| downscale(v, bits, 7) as u8;
+  let cc = |addr: u8, val: u8| MidiEvent::Cc{ ch, addr, val };
+  match *message {
+    Midi2Message::NoteOn{ note, velo, .. } => {
+      // velocity 0 would turn the note off in MIDI 1.0
+      vec![MidiEvent::NoteOn{ ch, note, velo: seven(velo.into(), 16).max(1) }]
+    },
+    Midi2Message::NoteOff{ note, velo, .. } => vec![MidiEvent::NoteOff{ ch, note, velo: seven(velo.into(), 16) }],
+    Midi2Message::PolyPressure{ note, value } => vec![MidiEvent::PolyPressure{ ch, note, pressure: seven(value, 32) }],
+    Midi2Message::ControlChange{ index, value } => vec![cc(index, seven(value, 32))],
+    Midi2Message::ChannelPressure{ value } => vec![MidiEvent::ChannelPressure{ ch, pressure: seven(value, 32) }],
+    Midi2Message::PitchBend{ value } => vec![MidiEvent::PitchBend{ ch, value: downscale(value, 32, 14) as u16 }],
+    Midi2Message::ProgramChange{ program, bank } => {
+      let mut events = vec![];
+      if let Some((msb, lsb)) = bank {
+        events.push(cc(BANK_MSB, msb));
+        events.push(cc(BANK_LSB, lsb));
+      }
+      events.push(MidiEvent::ProgramChange{ ch, program });
+      events
+    },
+    Midi2Message::RegisteredController{ bank, index, value }
+    | Midi2Message::AssignableController{ bank, index, value } => {
+      let (msb_addr, lsb_addr) = match message {
+        Midi2Message::RegisteredController{ .. } => (RPN_MSB, RPN_LSB),
+        _ => (NRPN_MSB, NRPN_LSB),
+      };
+      let value = downscale(value, 32, 14);
+      vec![
+        cc(msb_addr, bank),
+        cc(lsb_addr, index),
+        cc(NRPN_VAL_MSB, (value >> 7) as u8 & 0x7f),
+        cc(NRPN_VAL_LSB, value as u8 & 0x7f),
+      ]
+    },
+    _ => vec![]
+  }
+}
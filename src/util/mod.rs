@@ -1,7 +1,7 @@
 use crate::SendError;
 use std::ops::BitOr;
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
 /// Wrapper around a `u8` that represents the MIDI channel. 
 /// Will make sure that channel is within a range of 
 /// 0 - 15, representing 16 channels. 
@@ -43,20 +43,21 @@ impl BitOr for Channel {
   }
 }
 
-pub fn calc_midi_ppq(bpm: f64) -> f64 { 60.0 / (28.0 * bpm) }
+/// Seconds between two MIDI clock ticks at `bpm`, 24 to the quarter note.
+/// ```
+/// use midi::util::calc_midi_ppq;
+/// assert_eq!(calc_midi_ppq(125.0), 0.02);
+/// ```
+pub fn calc_midi_ppq(bpm: f64) -> f64 { 60.0 / (f64::from(crate::consts::transport::PPQ) * bpm) }
 
 pub mod logging {
   use super::*;
+  /// Logs a failed send. The message is dropped, but the process keeps
+  /// running, as a port going away is not fatal.
   pub(crate) fn err_send_log(err: Result<(), SendError>) {
     match err {
-      Err(SendError::InvalidData(e)) => {
-        eprintln!("Error type: Invalid data -  {e}");
-        std::process::exit(-2)
-      },
-      Err(SendError::Other(e)) => {
-        eprintln!("Error type: Other -  {e}");
-        std::process::exit(-3)
-      },
+      Err(SendError::InvalidData(e)) => eprintln!("Error type: Invalid data -  {e}"),
+      Err(SendError::Other(e)) => eprintln!("Error type: Other -  {e}"),
     _ => () 
     }
   }